@@ -0,0 +1,71 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build::{BytesType, Config, MapType, Privacy, StringType};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    Config::new()
+        .privacy(Privacy::Public)
+        .include_file("mod.rs")
+        .compile_protos(
+            &[
+                "../tests/tests.proto",
+                "../tests/names.proto",
+                "../tests/imports.proto",
+                "../tests/matrix.proto",
+                "../tests/types.proto",
+                "../tests/evolution.proto",
+                "../tests/packed.proto",
+            ],
+            &["../tests"],
+        )
+        .unwrap();
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+
+    // The same messages with the default private fields, only reachable
+    // through the generated accessors.
+    let private = out_dir.join("private");
+    fs::create_dir_all(&private).unwrap();
+    Config::new()
+        .out_dir(private)
+        .compile_protos(&["../tests/tests.proto"], &["../tests"])
+        .unwrap();
+
+    let open = out_dir.join("open");
+    fs::create_dir_all(&open).unwrap();
+    Config::new()
+        .privacy(Privacy::Public)
+        .open_enums(true)
+        .out_dir(open)
+        .compile_protos(&["../tests/tests.proto"], &["../tests"])
+        .unwrap();
+
+    // Every map, bytes and string field of the matrix generated with other types.
+    let mapped = out_dir.join("mapped");
+    fs::create_dir_all(&mapped).unwrap();
+    Config::new()
+        .privacy(Privacy::Public)
+        .map_type(MapType::BTreeMap)
+        .bytes_type(BytesType::Bytes)
+        .string_type(StringType::ArcStr)
+        .out_dir(mapped)
+        .compile_protos(&["../tests/matrix.proto"], &["../tests"])
+        .unwrap();
+}
@@ -0,0 +1,95 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::private::{
+    choice, Choice, Data, Decode, Encode, Node, Nullable, StockPrices, StockPricesWrapper, Test,
+};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+#[test]
+fn test_new_and_getters() {
+    let data = Data::new("Hello".to_string(), Test::Monkey);
+    assert_eq!(data.message(), "Hello");
+    assert_eq!(data.checker(), Test::Monkey);
+
+    let nullable = Nullable::new(
+        "name".to_string(),
+        Some(1),
+        None,
+        Some("note".to_string()),
+        Some(data.clone()),
+    );
+    assert_eq!(nullable.name(), "name");
+    assert_eq!(nullable.count(), Some(1));
+    assert_eq!(nullable.kind(), None);
+    assert_eq!(nullable.note(), Some("note"));
+    assert_eq!(nullable.data(), Some(&data));
+
+    let choice = Choice::new("label".to_string(), Some(choice::Value::Number(4)));
+    assert_eq!(choice.value(), Some(&choice::Value::Number(4)));
+}
+
+#[test]
+fn test_setters() {
+    let mut node = Node::default();
+    node.set_name("root".to_string());
+    node.children_mut()
+        .push(Node::builder().name("child").build());
+    node.set_parent(Some(Box::new(Node::builder().name("parent").build())));
+    assert_eq!(node.name(), "root");
+    assert_eq!(node.children().len(), 1);
+    assert_eq!(node.children()[0].name(), "child");
+    assert_eq!(node.parent().map(Node::name), Some("parent"));
+
+    node.name_mut().push_str("-renamed");
+    assert_eq!(node.name(), "root-renamed");
+}
+
+#[test]
+fn test_builder() {
+    let nullable = Nullable::builder()
+        .name("name")
+        .count(3)
+        .kind(Test::Potato)
+        .data(Data::new("Hello".to_string(), Test::Monkey))
+        .build();
+    assert_eq!(nullable.name(), "name");
+    assert_eq!(nullable.count(), Some(3));
+    assert_eq!(nullable.kind(), Some(Test::Potato));
+    assert_eq!(nullable.note(), None);
+    assert_eq!(nullable.data().map(Data::message), Some("Hello"));
+
+    let mut encoder = Cursor::new(Vec::new());
+    nullable.encode(&mut encoder).unwrap();
+    let mut buf = encoder.into_inner();
+    let decoded = Nullable::decode(&mut Cursor::new(&mut buf))
+        .unwrap()
+        .unwrap();
+    assert_eq!(decoded, nullable);
+}
+
+#[test]
+fn test_conversions() {
+    let prices = StockPrices::from(HashMap::from([("price".to_string(), 100.0)]));
+    assert_eq!(prices.prices()["price"], 100.0);
+
+    let wrapper: StockPricesWrapper = vec![prices.clone()].into();
+    assert_eq!(wrapper.s_prices().len(), 1);
+
+    let unwrapped: Vec<StockPrices> = wrapper.into();
+    assert_eq!(unwrapped, vec![prices]);
+}
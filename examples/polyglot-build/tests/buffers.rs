@@ -0,0 +1,170 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::matrix::{matrix, Matrix};
+use polyglot_rs::{Decode, DecodingError, Encode, Encoder, EncodingError};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Cursor;
+
+fn message() -> Matrix {
+    Matrix {
+        string_value: "string".to_string(),
+        repeated_message: vec![matrix::Item::from("item".to_string())],
+        ..Matrix::default()
+    }
+}
+
+#[test]
+fn test_encode_to_vec() {
+    let message = message();
+    let buf = message.encode_to_vec().unwrap();
+
+    let mut encoder = Cursor::new(Vec::new());
+    message.encode(&mut encoder).unwrap();
+    assert_eq!(buf, encoder.into_inner());
+    assert_eq!(Matrix::decode_from_slice(&buf).unwrap(), message);
+}
+
+#[test]
+fn test_encode_into() {
+    let message = message();
+    let mut buf = vec![1, 2, 3];
+    message.encode_into(&mut buf).unwrap();
+    assert_eq!(&buf[..3], &[1, 2, 3]);
+    assert_eq!(buf[3..], message.encode_to_vec().unwrap());
+}
+
+/// Writes a field and then fails, like a message whose last field is too large.
+struct Failing;
+
+impl Encode for Failing {
+    fn encode<'a>(
+        &'a self,
+        b: &'a mut Cursor<Vec<u8>>,
+    ) -> Result<&'a mut Cursor<Vec<u8>>, Box<dyn Error>> {
+        b.encode_string(&"written".to_string())?;
+        Err(EncodingError::TooLarge.into())
+    }
+}
+
+#[test]
+fn test_encode_into_error() {
+    let mut buf = vec![1, 2, 3];
+    let err = Failing.encode_into(&mut buf).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<EncodingError>(),
+        Some(EncodingError::TooLarge)
+    ));
+    assert_eq!(buf, [1, 2, 3]);
+
+    message().encode_into(&mut buf).unwrap();
+    assert_eq!(buf[3..], message().encode_to_vec().unwrap());
+}
+
+#[test]
+fn test_decode_from_reader() {
+    let message = message();
+    let buf = message.encode_to_vec().unwrap();
+    assert_eq!(Matrix::decode_from_reader(buf.as_slice()).unwrap(), message);
+}
+
+#[test]
+fn test_decode_from_borrowed_slice() {
+    let message = message();
+    let buf = message.encode_to_vec().unwrap();
+    let mut cursor = Cursor::new(buf.as_slice());
+    assert_eq!(Matrix::decode(&mut cursor).unwrap(), Some(message.clone()));
+    assert_eq!(cursor.position(), buf.len() as u64);
+
+    let mut decoded = Matrix::default();
+    decoded
+        .decode_into(&mut Cursor::new(buf.as_slice()))
+        .unwrap();
+    assert_eq!(decoded, message);
+}
+
+#[test]
+fn test_decode_errors() {
+    let mut buf = message().encode_to_vec().unwrap();
+    buf.push(0);
+    let err = Matrix::decode_from_slice(&buf).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DecodingError>(),
+        Some(&DecodingError::TrailingBytes)
+    );
+
+    let mut encoder = Cursor::new(Vec::new());
+    encoder.encode_none().unwrap();
+    let err = Matrix::decode_from_slice(encoder.get_ref()).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DecodingError>(),
+        Some(&DecodingError::InvalidNone)
+    );
+
+    assert!(Matrix::decode_from_slice(&buf[..buf.len() / 2]).is_err());
+}
+
+#[test]
+fn test_decode_into() {
+    let mut message = Matrix {
+        string_value: "a much longer string than the next one".to_string(),
+        bytes_value: vec![1; 32],
+        message_value: Some(matrix::Item::from("stale".to_string())),
+        optional_string: Some("stale".to_string()),
+        repeated_string: vec!["one".to_string(), "two".to_string(), "three".to_string()],
+        repeated_message: vec![
+            matrix::Item::from("one".to_string()),
+            matrix::Item::from("two".to_string()),
+        ],
+        string_values: HashMap::from([("stale".to_string(), "value".to_string())]),
+        choice: Some(matrix::Choice::ChoiceBool(true)),
+        ..Matrix::default()
+    };
+    let capacity = message.string_value.capacity();
+    let repeated = message.repeated_string.as_ptr();
+
+    let next = Matrix {
+        string_value: "string".to_string(),
+        int32_value: -5,
+        repeated_string: vec!["four".to_string(), "five".to_string()],
+        repeated_message: vec![
+            matrix::Item::from("three".to_string()),
+            matrix::Item::from("four".to_string()),
+            matrix::Item::from("five".to_string()),
+        ],
+        string_values: HashMap::from([("key".to_string(), "value".to_string())]),
+        choice: Some(matrix::Choice::ChoiceString("choice".to_string())),
+        ..Matrix::default()
+    };
+    let mut buf = next.encode_to_vec().unwrap();
+    message.decode_into(&mut Cursor::new(&mut buf)).unwrap();
+
+    assert_eq!(message, next);
+    assert_eq!(message, Matrix::decode_from_slice(&buf).unwrap());
+    assert_eq!(message.string_value.capacity(), capacity);
+    assert_eq!(message.repeated_string.as_ptr(), repeated);
+
+    let mut encoder = Cursor::new(Vec::new());
+    encoder.encode_none().unwrap();
+    let err = message
+        .decode_into(&mut Cursor::new(encoder.get_mut()))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DecodingError>(),
+        Some(&DecodingError::InvalidNone)
+    );
+}
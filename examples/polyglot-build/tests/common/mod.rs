@@ -0,0 +1,34 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+#![allow(dead_code)]
+
+use polyglot_rs::{Decode, Encode};
+use std::io::Cursor;
+
+pub fn encode<T: Encode>(val: &T) -> Vec<u8> {
+    let mut encoder = Cursor::new(Vec::new());
+    val.encode(&mut encoder).unwrap();
+    encoder.into_inner()
+}
+
+pub fn decode<T: Decode>(mut buf: Vec<u8>) -> T {
+    T::decode(&mut Cursor::new(&mut buf)).unwrap().unwrap()
+}
+
+pub fn round_trip<T: Encode + Decode>(val: &T) -> T {
+    decode(encode(val))
+}
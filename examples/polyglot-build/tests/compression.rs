@@ -0,0 +1,49 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::tests::{StockPrices, StockPricesWrapper};
+use polyglot_rs::{Compression, Decode, Encode, Kind};
+use std::collections::HashMap;
+
+#[test]
+fn test_compressed() {
+    let wrapper: StockPricesWrapper = (0..1000)
+        .map(|i| StockPrices::from(HashMap::from([("AAPL".to_string(), (i % 4) as f64)])))
+        .collect::<Vec<_>>()
+        .into();
+    let buf = wrapper.encode_to_vec().unwrap();
+    let compressed = wrapper.encode_compressed_to_vec(Compression::Lz4).unwrap();
+    assert_eq!(compressed[0], Kind::Compressed as u8);
+    assert!(compressed.len() < buf.len() / 10);
+
+    assert_eq!(
+        StockPricesWrapper::decode_from_slice(&compressed).unwrap(),
+        wrapper
+    );
+    assert_eq!(
+        StockPricesWrapper::decode_compressed_from_slice(&compressed, buf.len()).unwrap(),
+        wrapper
+    );
+    assert!(StockPricesWrapper::decode_compressed_from_slice(&compressed, buf.len() - 1).is_err());
+
+    // Interned messages can be compressed too.
+    let interned = wrapper.encode_interned_to_vec().unwrap();
+    let compressed = polyglot_rs::compress(&interned, Compression::Lz4);
+    assert_eq!(
+        StockPricesWrapper::decode_from_slice(&compressed).unwrap(),
+        wrapper
+    );
+}
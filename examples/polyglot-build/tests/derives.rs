@@ -0,0 +1,81 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::tests::{
+    request, sample_message, Data, Decode, Encode, Node, Request, Response, SampleMessage,
+    StockPrices, Test,
+};
+use std::collections::HashSet;
+use std::io::Cursor;
+
+#[test]
+fn test_default() {
+    assert_eq!(Test::default(), Test::Potato);
+    assert_eq!(request::Corpus::default(), request::Corpus::Universal);
+    assert!(Test::try_from(2).is_err());
+
+    let request = Request::default();
+    assert_eq!(request.message, "");
+    assert_eq!(request.corpus, request::Corpus::Universal);
+    assert_eq!(Response::default().test, None);
+    assert_eq!(SampleMessage::default().test_oneof, None);
+    assert!(Node::default().parent.is_none());
+}
+
+#[test]
+fn test_clone_eq_hash() {
+    let response = Response {
+        message: "Hello".to_string(),
+        test: Some(Data {
+            message: "World".to_string(),
+            checker: Test::Monkey,
+        }),
+    };
+    let mut encoder = Cursor::new(Vec::new());
+    response.encode(&mut encoder).unwrap();
+    let mut buf = encoder.into_inner();
+    let decoded = Response::decode(&mut Cursor::new(&mut buf))
+        .unwrap()
+        .unwrap();
+    assert_eq!(decoded, response);
+    assert_ne!(decoded, Response::default());
+
+    let mut set = HashSet::new();
+    set.insert(response.clone());
+    set.insert(decoded);
+    assert_eq!(set.len(), 1);
+
+    let sample = SampleMessage {
+        test_oneof: Some(sample_message::TestOneof::Name("name".to_string())),
+    };
+    assert_eq!(sample.clone(), sample);
+}
+
+#[test]
+fn test_debug() {
+    let data = Data {
+        message: "Hello".to_string(),
+        checker: Test::Monkey,
+    };
+    assert_eq!(
+        format!("{data:?}"),
+        "Data { message: \"Hello\", checker: Monkey }"
+    );
+
+    // Messages holding floats only get `PartialEq`.
+    let prices = StockPrices::default();
+    assert_eq!(prices.clone(), prices);
+}
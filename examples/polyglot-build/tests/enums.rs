@@ -0,0 +1,84 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::tests::{my_message1, numbered, Decode, Encode, MyMessage1, Numbered};
+use polyglot_build_example::{open, tests};
+use polyglot_rs::{Decoder, Encoder};
+use std::io::Cursor;
+
+#[test]
+fn test_aliases() {
+    assert_eq!(
+        my_message1::EnumAllowingAlias::RUNNING,
+        my_message1::EnumAllowingAlias::STARTED
+    );
+    assert_eq!(my_message1::EnumAllowingAlias::RUNNING as u32, 1);
+    assert_eq!(
+        my_message1::EnumAllowingAlias::try_from(1).unwrap(),
+        my_message1::EnumAllowingAlias::RUNNING
+    );
+
+    let message = MyMessage1 {
+        state: my_message1::EnumAllowingAlias::RUNNING,
+    };
+    let mut encoder = Cursor::new(Vec::new());
+    message.encode(&mut encoder).unwrap();
+    let mut buf = encoder.into_inner();
+    let mut decoder = Cursor::new(&mut buf);
+    assert_eq!(decoder.decode_u32().unwrap(), 1);
+    decoder.set_position(0);
+    let decoded = MyMessage1::decode(&mut decoder).unwrap().unwrap();
+    assert_eq!(decoded.state, my_message1::EnumAllowingAlias::STARTED);
+}
+
+#[test]
+fn test_numbers() {
+    assert_eq!(numbered::Level::Five as u32, 5);
+    assert_eq!(numbered::Level::Negative as u32, -1i32 as u32);
+    assert!(numbered::Level::try_from(1).is_err());
+
+    for level in [
+        numbered::Level::Zero,
+        numbered::Level::Five,
+        numbered::Level::Negative,
+    ] {
+        let mut encoder = Cursor::new(Vec::new());
+        Numbered { level }.encode(&mut encoder).unwrap();
+        let mut buf = encoder.into_inner();
+
+        let mut expected = Cursor::new(Vec::new());
+        expected.encode_u32(level as u32).unwrap();
+        assert_eq!(buf, expected.into_inner());
+
+        let decoded = Numbered::decode(&mut Cursor::new(&mut buf))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.level, level);
+    }
+}
+
+#[test]
+fn test_open_numbers() {
+    assert_eq!(open::numbered::Level::from(5), open::numbered::Level::Five);
+    assert_eq!(
+        open::numbered::Level::from(1),
+        open::numbered::Level::Unknown(1)
+    );
+    assert_eq!(
+        u32::from(open::my_message1::EnumAllowingAlias::RUNNING),
+        tests::my_message1::EnumAllowingAlias::RUNNING as u32
+    );
+}
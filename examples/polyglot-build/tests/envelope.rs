@@ -0,0 +1,83 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::evolution::evolution::Profile;
+use polyglot_build_example::tests::{Data, Request, Test};
+use polyglot_rs::{Encode, Envelope, MessageType, Named, TypeRegistry};
+use std::any::Any;
+
+#[test]
+fn test_full_names() {
+    assert_eq!(Request::FULL_NAME, "Request");
+    assert_eq!(Profile::FULL_NAME, "evolution.Profile");
+    assert_eq!(
+        polyglot_build_example::tests::search_response::Result2::FULL_NAME,
+        "SearchResponse.Result"
+    );
+}
+
+#[test]
+fn test_dispatch() {
+    enum Routed {
+        Data(Data),
+        Profile(Profile),
+    }
+
+    let mut registry = TypeRegistry::new();
+    registry
+        .register_named_with(Routed::Data)
+        .register_named_with(Routed::Profile);
+
+    let data = Data {
+        message: "Hello".to_string(),
+        checker: Test::Monkey,
+    };
+    let buf = Envelope::named(&data).unwrap().encode_to_vec().unwrap();
+    match registry.decode_from_slice(&buf).unwrap() {
+        Routed::Data(decoded) => assert_eq!(decoded.message, "Hello"),
+        Routed::Profile(_) => panic!("decoded as a profile"),
+    }
+
+    let profile = Profile {
+        bio: "bio".to_string(),
+    };
+    let envelope = Envelope::named(&profile).unwrap();
+    assert_eq!(
+        envelope.message_type,
+        MessageType::Name("evolution.Profile".to_string())
+    );
+    assert!(matches!(
+        registry.decode(&envelope).unwrap(),
+        Routed::Profile(decoded) if decoded == profile
+    ));
+}
+
+#[test]
+fn test_dispatch_any() {
+    let mut registry = TypeRegistry::new();
+    registry.register_named::<Data>().register::<Profile>(2);
+
+    let envelope = Envelope::new(
+        2,
+        &Profile {
+            bio: "bio".to_string(),
+        },
+    )
+    .unwrap();
+    let decoded: Box<dyn Any + Send> = registry.decode(&envelope).unwrap();
+    assert_eq!(decoded.downcast_ref::<Profile>().unwrap().bio, "bio");
+    assert!(registry.contains(&MessageType::Name("Data".to_string())));
+}
@@ -0,0 +1,113 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::evolution::evolution::{v2, Batch, OldBatch, Profile, V1, V2};
+use polyglot_rs::{Decode, DecodingError, Encode};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+fn profile() -> Option<Profile> {
+    Some(Profile {
+        bio: "bio".to_string(),
+    })
+}
+
+fn v2() -> V2 {
+    V2 {
+        scores: HashMap::from([("score".to_string(), -3)]),
+        name: "name".to_string(),
+        tags: vec!["a".to_string(), "b".to_string()],
+        profile: profile(),
+        age: 42,
+        contact: Some(v2::Contact::Phone(5)),
+    }
+}
+
+#[test]
+fn test_skip_newer_fields() {
+    let buf = v2().encode_to_vec().unwrap();
+    let decoded = V1::decode_from_slice(&buf).unwrap();
+    assert_eq!(
+        decoded,
+        V1 {
+            name: "name".to_string(),
+            tags: vec!["a".to_string(), "b".to_string()],
+            profile: profile(),
+        }
+    );
+}
+
+#[test]
+fn test_default_missing_fields() {
+    let v1 = V1 {
+        name: "name".to_string(),
+        tags: vec!["a".to_string()],
+        profile: None,
+    };
+    let buf = v1.encode_to_vec().unwrap();
+    let decoded = V2::decode_from_slice(&buf).unwrap();
+    assert_eq!(
+        decoded,
+        V2 {
+            name: "name".to_string(),
+            tags: vec!["a".to_string()],
+            ..V2::default()
+        }
+    );
+
+    // Fields missing from the message are reset when decoding into an
+    // existing one.
+    let mut message = v2();
+    message
+        .decode_into(&mut Cursor::new(&mut buf.clone()))
+        .unwrap();
+    assert_eq!(message, decoded);
+}
+
+#[test]
+fn test_nested_delimited() {
+    let batch = Batch {
+        users: vec![v2(), V2::default()],
+        count: 2,
+    };
+    let buf = batch.encode_to_vec().unwrap();
+    let old = OldBatch::decode_from_slice(&buf).unwrap();
+    assert_eq!(old.users.len(), 2);
+    assert_eq!(old.users[0].name, "name");
+    assert_eq!(old.users[1], V1::default());
+    assert_eq!(old.count, 2);
+
+    let buf = old.encode_to_vec().unwrap();
+    let new = Batch::decode_from_slice(&buf).unwrap();
+    assert_eq!(new.users[0].age, 0);
+    assert_eq!(new.users[0].profile, profile());
+    assert_eq!(new.count, 2);
+}
+
+#[test]
+fn test_delimited_errors() {
+    let mut buf = v2().encode_to_vec().unwrap();
+    buf.truncate(buf.len() - 1);
+    assert!(V1::decode_from_slice(&buf).is_err());
+
+    // Messages that aren't delimited can't be read as delimited ones.
+    let buf = OldBatch::default().encode_to_vec().unwrap();
+    let err = V1::decode_from_slice(&buf).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DecodingError>(),
+        Some(&DecodingError::InvalidStruct)
+    );
+}
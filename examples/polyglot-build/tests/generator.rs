@@ -0,0 +1,102 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use base64::{engine::general_purpose, Engine as _};
+use polyglot_build_example::tests::{
+    request, search_response, Data, Decode, Encode, Request, Response, SearchResponse, StockPrices,
+    StockPricesSuperWrap, StockPricesWrapper, Test, TestAll,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct GeneratorTestData {
+    testall: String,
+}
+
+fn get_test_data() -> GeneratorTestData {
+    serde_json::from_slice::<GeneratorTestData>(
+        &fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("..")
+                .join("..")
+                .join("resources")
+                .join("test")
+                .join("generator-test-data.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_decode() {
+    let test_b64 = get_test_data();
+    let mut poly_data = general_purpose::STANDARD.decode(test_b64.testall).unwrap();
+    let mut decoder = Cursor::new(poly_data.as_mut());
+    TestAll::decode(&mut decoder).unwrap().unwrap();
+}
+
+#[test]
+fn test_encode() {
+    let test_b64 = get_test_data();
+    let poly_data = general_purpose::STANDARD.decode(test_b64.testall).unwrap();
+
+    let test = TestAll {
+        request: Some(Request {
+            message: "Hello".to_string(),
+            corpus: request::Corpus::Universal,
+        }),
+        response: Some(Response {
+            message: "Hello".to_string(),
+            test: Some(Data {
+                message: "Hello".to_string(),
+                checker: Test::Potato,
+            }),
+        }),
+        search_response: Some(SearchResponse {
+            results: Vec::from([search_response::Result2 {
+                url: "https://www.google.com".to_string(),
+                title: "Google".to_string(),
+                snippets: ["Google is a search engine".to_string()].to_vec(),
+            }]),
+            results2: Vec::from([search_response::Result2 {
+                url: "https://www.google.com".to_string(),
+                title: "Google".to_string(),
+                snippets: ["Google is a search engine".to_string()].to_vec(),
+            }]),
+            snippets: ["Google is a search engine".to_string()].to_vec(),
+            snippets2: ["Google is a search engine".to_string()].to_vec(),
+        }),
+        stock_prices_super_wrap: Some(StockPricesSuperWrap {
+            prices: HashMap::from([(
+                "AAPL".to_string(),
+                StockPricesWrapper {
+                    s_prices: Vec::from([StockPrices {
+                        prices: HashMap::from([("price".to_string(), 100.0)]),
+                    }]),
+                },
+            )]),
+        }),
+    };
+
+    let mut encoder: Cursor<Vec<u8>> = Cursor::new(Vec::with_capacity(512));
+    test.encode(&mut encoder).expect("Failed to encode");
+    assert_eq!(poly_data, encoder.into_inner());
+}
@@ -0,0 +1,42 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::imports::imports::{imported, Imported};
+use polyglot_build_example::names::example::v1::{keywords, outer, Keywords, Outer};
+use polyglot_rs::{Decode, Encode};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+#[test]
+fn test_imported_types() {
+    let inner = keywords::Type::from("inner".to_string());
+    let message = Imported {
+        keywords: Some(Keywords::builder().r#type("type").build()),
+        outers: vec![Outer::from(Some(outer::Result2::from(Some(inner.clone()))))],
+        results: HashMap::from([(
+            "result".to_string(),
+            outer::Result2::from(Some(inner.clone())),
+        )]),
+        kind: keywords::Kind2::Known,
+        choice: Some(imported::Choice::Type(inner)),
+    };
+
+    let mut encoder = Cursor::new(Vec::new());
+    message.encode(&mut encoder).unwrap();
+    let mut buf = encoder.into_inner();
+    let mut decoder = Cursor::new(&mut buf);
+    assert_eq!(Imported::decode(&mut decoder).unwrap().unwrap(), message);
+}
@@ -0,0 +1,61 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build::load;
+use polyglot_build_example::tests::{StockPrices, StockPricesWrapper};
+use polyglot_rs::{resolve, validate, Decode, Encode, Kind};
+use std::collections::HashMap;
+use std::path::Path;
+
+fn wrapper() -> StockPricesWrapper {
+    (0..1000)
+        .map(|i| {
+            StockPrices::from(HashMap::from([
+                ("AAPL".to_string(), i as f64),
+                ("MSFT".to_string(), i as f64 * 2.0),
+                (format!("NEW{i}"), 1.0),
+            ]))
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[test]
+fn test_interned() {
+    let wrapper = wrapper();
+    let buf = wrapper.encode_to_vec().unwrap();
+    let interned = wrapper.encode_interned_to_vec().unwrap();
+    assert_eq!(interned[0], Kind::StringTable as u8);
+    // Each repeated ticker takes three bytes instead of seven.
+    assert!(interned.len() + 7_000 < buf.len());
+
+    assert_eq!(
+        StockPricesWrapper::decode_from_slice(&interned).unwrap(),
+        wrapper
+    );
+    assert_eq!(resolve(&interned).unwrap(), buf);
+}
+
+#[test]
+fn test_interned_schema() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("tests");
+    let (pool, _) = load(&[dir.join("tests.proto")], &[dir]).unwrap();
+    let schema = pool.schema("StockPricesWrapper");
+    let interned = wrapper().encode_interned_to_vec().unwrap();
+    validate(&interned, &schema).unwrap();
+}
@@ -0,0 +1,150 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+mod common;
+
+use common::round_trip;
+use polyglot_build_example::matrix::{matrix, Matrix};
+use std::collections::HashMap;
+
+fn item(name: &str) -> matrix::Item {
+    matrix::Item::from(name.to_string())
+}
+
+fn values<T>(value: T) -> HashMap<String, T> {
+    HashMap::from([("key".to_string(), value)])
+}
+
+fn full() -> Matrix {
+    Matrix {
+        bool_value: true,
+        int32_value: -32,
+        sint32_value: -33,
+        uint32_value: 32,
+        int64_value: -64,
+        sint64_value: -65,
+        uint64_value: 64,
+        sfixed32_value: -34,
+        sfixed64_value: -66,
+        fixed32_value: 35,
+        fixed64_value: 67,
+        float_value: 1.5,
+        double_value: -2.5,
+        string_value: "string".to_string(),
+        bytes_value: vec![1, 2, 3],
+        enum_value: matrix::Level::High,
+        message_value: Some(item("message")),
+
+        optional_bool: Some(false),
+        optional_int32: Some(-1),
+        optional_sint32: Some(-2),
+        optional_uint32: Some(3),
+        optional_int64: Some(-4),
+        optional_sint64: Some(-5),
+        optional_uint64: Some(6),
+        optional_sfixed32: Some(-7),
+        optional_sfixed64: Some(-8),
+        optional_fixed32: Some(9),
+        optional_fixed64: Some(10),
+        optional_float: Some(0.25),
+        optional_double: Some(0.5),
+        optional_string: Some("optional".to_string()),
+        optional_bytes: Some(vec![4, 5]),
+        optional_enum: Some(matrix::Level::High),
+
+        repeated_bool: vec![true, false],
+        repeated_int32: vec![-1, 1],
+        repeated_sint32: vec![-2, 2],
+        repeated_uint32: vec![3, 4],
+        repeated_int64: vec![-5, 5],
+        repeated_sint64: vec![-6, 6],
+        repeated_uint64: vec![7, 8],
+        repeated_sfixed32: vec![-9, 9],
+        repeated_sfixed64: vec![-10, 10],
+        repeated_fixed32: vec![11, 12],
+        repeated_fixed64: vec![13, 14],
+        repeated_float: vec![1.25, -1.25],
+        repeated_double: vec![2.25, -2.25],
+        repeated_string: vec!["a".to_string(), "b".to_string()],
+        repeated_bytes: vec![vec![1], vec![], vec![2, 3]],
+        repeated_enum: vec![matrix::Level::High, matrix::Level::Low],
+        repeated_message: vec![item("first"), item("second")],
+
+        bool_keys: HashMap::from([(true, "true".to_string()), (false, "false".to_string())]),
+        int32_keys: HashMap::from([(-1, "a".to_string())]),
+        sint32_keys: HashMap::from([(-2, "b".to_string())]),
+        uint32_keys: HashMap::from([(3, "c".to_string())]),
+        int64_keys: HashMap::from([(-4, "d".to_string())]),
+        sint64_keys: HashMap::from([(-5, "e".to_string())]),
+        uint64_keys: HashMap::from([(6, "f".to_string())]),
+        sfixed32_keys: HashMap::from([(-7, "g".to_string())]),
+        sfixed64_keys: HashMap::from([(-8, "h".to_string())]),
+        fixed32_keys: HashMap::from([(9, "i".to_string())]),
+        fixed64_keys: HashMap::from([(10, "j".to_string())]),
+        string_keys: HashMap::from([("k".to_string(), "l".to_string())]),
+
+        bool_values: values(true),
+        int32_values: values(-1),
+        sint32_values: values(-2),
+        uint32_values: values(3),
+        int64_values: values(-4),
+        sint64_values: values(-5),
+        uint64_values: values(6),
+        sfixed32_values: values(-7),
+        sfixed64_values: values(-8),
+        fixed32_values: values(9),
+        fixed64_values: values(10),
+        float_values: values(0.75),
+        double_values: values(-0.75),
+        string_values: values("value".to_string()),
+        bytes_values: values(vec![6, 7, 8]),
+        enum_values: values(matrix::Level::High),
+        message_values: values(item("value")),
+
+        choice: Some(matrix::Choice::ChoiceBytes(vec![9])),
+    }
+}
+
+#[test]
+fn test_full() {
+    let message = full();
+    assert_eq!(round_trip(&message), message);
+}
+
+#[test]
+fn test_empty() {
+    let message = Matrix::default();
+    assert_eq!(round_trip(&message), message);
+}
+
+#[test]
+fn test_choices() {
+    for choice in [
+        matrix::Choice::ChoiceBool(true),
+        matrix::Choice::ChoiceInt64(-1),
+        matrix::Choice::ChoiceDouble(0.5),
+        matrix::Choice::ChoiceString("choice".to_string()),
+        matrix::Choice::ChoiceBytes(vec![]),
+        matrix::Choice::ChoiceEnum(matrix::Level::High),
+        matrix::Choice::ChoiceMessage(item("choice")),
+    ] {
+        let message = Matrix {
+            choice: Some(choice),
+            ..full()
+        };
+        assert_eq!(round_trip(&message), message);
+    }
+}
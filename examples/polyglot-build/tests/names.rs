@@ -0,0 +1,67 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::names::example::v1::{keywords, outer, Keywords, Outer};
+use polyglot_build_example::names::{Decode, Encode};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+#[test]
+fn test_keywords() {
+    let message = Keywords::builder()
+        .r#type("type")
+        .foo_bar("fooBar")
+        .foo_bar_2("foo_bar")
+        .new_2("new")
+        .r#match(keywords::Type::from("self".to_string()))
+        .kind(keywords::Kind2::Known)
+        .kinds(HashMap::from([(
+            "known".to_string(),
+            keywords::Kind2::Known,
+        )]))
+        .value(keywords::Value::Level(keywords::Kind2::Known))
+        .build();
+    assert_eq!(message.r#type(), "type");
+    assert_eq!(message.r#match().unwrap().self_(), "self");
+
+    let mut encoder = Cursor::new(Vec::new());
+    message.encode(&mut encoder).unwrap();
+    let mut buf = encoder.into_inner();
+    let mut decoder = Cursor::new(&mut buf);
+    assert_eq!(Keywords::decode(&mut decoder).unwrap().unwrap(), message);
+
+    let mut message = message;
+    message.set_type("changed".to_string());
+    message.type_mut().push('!');
+    assert_eq!(message.r#type, "changed!");
+}
+
+#[test]
+fn test_nested_paths() {
+    let message = Outer {
+        result: Some(outer::Result2 {
+            inner: Some(keywords::Type {
+                self_: "nested".to_string(),
+            }),
+        }),
+    };
+
+    let mut encoder = Cursor::new(Vec::new());
+    message.encode(&mut encoder).unwrap();
+    let mut buf = encoder.into_inner();
+    let mut decoder = Cursor::new(&mut buf);
+    assert_eq!(Outer::decode(&mut decoder).unwrap().unwrap(), message);
+}
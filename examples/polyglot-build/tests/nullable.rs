@@ -0,0 +1,152 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+mod common;
+
+use base64::{engine::general_purpose, Engine as _};
+use common::round_trip;
+use polyglot_build_example::evolution::evolution::{Holder, Leading};
+use polyglot_build_example::tests::{Data, Decode, Encode, Nullable, Response, Test};
+use polyglot_rs::{Encoder, Kind};
+use serde::Deserialize;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct NullableTestData {
+    name: String,
+    #[serde(rename = "encodedValue")]
+    encoded_value: String,
+}
+
+/// Messages with absent fields as every polyglot implementation writes them.
+fn get_test_data() -> Vec<(String, Vec<u8>)> {
+    let data = serde_json::from_slice::<Vec<NullableTestData>>(
+        &fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("..")
+                .join("..")
+                .join("resources")
+                .join("test")
+                .join("nullable-test-data.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    data.into_iter()
+        .map(|td| {
+            let buf = general_purpose::STANDARD.decode(td.encoded_value).unwrap();
+            (td.name, buf)
+        })
+        .collect()
+}
+
+#[test]
+fn test_nullable_present() {
+    let nullable = round_trip(&Nullable {
+        name: "present".to_string(),
+        count: Some(42),
+        kind: Some(Test::Monkey),
+        note: Some("note".to_string()),
+        data: Some(Data {
+            message: "Hello".to_string(),
+            checker: Test::Potato,
+        }),
+    });
+    assert_eq!(nullable.name, "present");
+    assert_eq!(nullable.count, Some(42));
+    assert_eq!(nullable.kind, Some(Test::Monkey));
+    assert_eq!(nullable.note.as_deref(), Some("note"));
+    let data = nullable.data.unwrap();
+    assert_eq!(data.message, "Hello");
+    assert_eq!(data.checker, Test::Potato);
+}
+
+#[test]
+fn test_nullable_absent() {
+    let nullable = Nullable {
+        name: "absent".to_string(),
+        count: None,
+        kind: None,
+        note: None,
+        data: None,
+    };
+    let mut encoder = Cursor::new(Vec::new());
+    nullable.encode(&mut encoder).unwrap();
+    let none = Kind::None as u8;
+    assert_eq!(encoder.get_ref()[encoder.get_ref().len() - 4..], [none; 4]);
+
+    let nullable = round_trip(&nullable);
+    assert_eq!(nullable.name, "absent");
+    assert_eq!(nullable.count, None);
+    assert_eq!(nullable.kind, None);
+    assert_eq!(nullable.note, None);
+    assert!(nullable.data.is_none());
+}
+
+#[test]
+fn test_decode_none_message() {
+    let mut encoder = Cursor::new(Vec::new());
+    encoder
+        .encode_string(&"Hello".to_string())
+        .unwrap()
+        .encode_none()
+        .unwrap();
+    let mut buf = encoder.into_inner();
+    let response = Response::decode(&mut Cursor::new(&mut buf))
+        .unwrap()
+        .unwrap();
+    assert_eq!(response.message, "Hello");
+    assert!(response.test.is_none());
+}
+
+#[test]
+fn test_nullable_fixtures() {
+    for (name, buf) in get_test_data() {
+        match name.as_str() {
+            "Nullable absent" | "Nullable present" => {
+                let present = name == "Nullable present";
+                let nullable = Nullable::decode_from_slice(&buf).unwrap();
+                assert_eq!(nullable.name, "a");
+                assert_eq!(nullable.count, present.then_some(5));
+                assert_eq!(nullable.kind, None);
+                assert_eq!(nullable.note.as_deref(), present.then_some("b"));
+                assert!(nullable.data.is_none());
+                assert_eq!(nullable.encode_to_vec().unwrap(), buf);
+            }
+            "Holder absent" => {
+                let holder = Holder::decode_from_slice(&buf).unwrap();
+                assert_eq!(holder.id, "x");
+                assert_eq!(holder.leading, None);
+                assert_eq!(holder.encode_to_vec().unwrap(), buf);
+            }
+            // Delimited messages start with their length, so one that starts with
+            // an absent field isn't read as a nil message.
+            "Holder leading absent" => {
+                let holder = Holder::decode_from_slice(&buf).unwrap();
+                assert_eq!(holder.id, "x");
+                let leading = Leading {
+                    count: None,
+                    name: "a".to_string(),
+                };
+                assert_eq!(holder.leading, Some(leading));
+                assert_eq!(round_trip(&holder), holder);
+            }
+            _ => panic!("unknown test data {name}"),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+mod common;
+
+use common::round_trip;
+use polyglot_build_example::tests::{
+    choice, sample_message, Choice, Data, Decode, Encode, SampleMessage, Test,
+};
+use polyglot_rs::{DecodingError, Encoder, Kind};
+use std::io::Cursor;
+
+#[test]
+fn test_oneof_string() {
+    let message = SampleMessage {
+        test_oneof: Some(sample_message::TestOneof::Potato("potato".to_string())),
+    };
+    let mut encoder = Cursor::new(Vec::new());
+    message.encode(&mut encoder).unwrap();
+    assert_eq!(
+        encoder.get_ref()[..2],
+        [Kind::U32 as u8, 9],
+        "oneofs start with the field number of the active member"
+    );
+
+    match round_trip(&message).test_oneof {
+        Some(sample_message::TestOneof::Potato(val)) => assert_eq!(val, "potato"),
+        _ => panic!("expected the potato variant"),
+    }
+
+    let message = SampleMessage { test_oneof: None };
+    let mut encoder = Cursor::new(Vec::new());
+    message.encode(&mut encoder).unwrap();
+    assert_eq!(encoder.get_ref(), &[Kind::U32 as u8, 0]);
+    assert!(round_trip(&message).test_oneof.is_none());
+}
+
+#[test]
+fn test_oneof_variants() {
+    let choice = round_trip(&Choice {
+        label: "data".to_string(),
+        value: Some(choice::Value::Data(Data {
+            message: "Hello World".to_string(),
+            checker: Test::Monkey,
+        })),
+    });
+    assert_eq!(choice.label, "data");
+    match choice.value {
+        Some(choice::Value::Data(data)) => {
+            assert_eq!(data.message, "Hello World");
+            assert_eq!(data.checker, Test::Monkey);
+        }
+        _ => panic!("expected the data variant"),
+    }
+
+    let choice = round_trip(&Choice {
+        label: "test".to_string(),
+        value: Some(choice::Value::Test(Test::Potato)),
+    });
+    assert!(matches!(
+        choice.value,
+        Some(choice::Value::Test(Test::Potato))
+    ));
+
+    let choice = round_trip(&Choice {
+        label: "number".to_string(),
+        value: Some(choice::Value::Number(u64::MAX)),
+    });
+    assert!(matches!(
+        choice.value,
+        Some(choice::Value::Number(u64::MAX))
+    ));
+}
+
+#[test]
+fn test_oneof_unknown_member() {
+    let mut encoder = Cursor::new(Vec::new());
+    encoder
+        .encode_string(&"label".to_string())
+        .unwrap()
+        .encode_u32(7)
+        .unwrap()
+        .encode_u64(1)
+        .unwrap();
+    let mut buf = encoder.into_inner();
+    let err = Choice::decode(&mut Cursor::new(&mut buf)).err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<DecodingError>(),
+        Some(&DecodingError::InvalidOneof)
+    );
+}
@@ -0,0 +1,70 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::{open, tests};
+use polyglot_rs::{DecodingError, Encoder};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+#[test]
+fn test_unknown_value() {
+    // A `Data` written by a peer that knows about a third `Test` value.
+    let mut encoder = Cursor::new(Vec::new());
+    encoder
+        .encode_string(&"Hello".to_string())
+        .unwrap()
+        .encode_u32(2)
+        .unwrap();
+    let mut buf = encoder.into_inner();
+
+    let err =
+        <tests::Data as tests::Decode>::decode(&mut Cursor::new(&mut buf.clone())).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<DecodingError>(),
+        Some(&DecodingError::InvalidEnum)
+    );
+
+    let data = <open::Data as open::Decode>::decode(&mut Cursor::new(&mut buf))
+        .unwrap()
+        .unwrap();
+    assert_eq!(data.checker, open::Test::Unknown(2));
+
+    let mut encoder = Cursor::new(Vec::new());
+    open::Encode::encode(&data, &mut encoder).unwrap();
+    assert_eq!(encoder.into_inner(), buf);
+}
+
+#[test]
+fn test_known_values() {
+    assert_eq!(open::Test::from(1), open::Test::Monkey);
+    assert_eq!(u32::from(open::Test::Monkey), 1);
+    assert_eq!(u32::from(open::Test::Unknown(9)), 9);
+    assert_eq!(open::Test::default(), open::Test::Potato);
+
+    let potato = open::TestPotato {
+        prices: HashMap::from([
+            ("known".to_string(), open::Test::Monkey),
+            ("unknown".to_string(), open::Test::Unknown(7)),
+        ]),
+    };
+    let mut encoder = Cursor::new(Vec::new());
+    open::Encode::encode(&potato, &mut encoder).unwrap();
+    let mut buf = encoder.into_inner();
+    let decoded = <open::TestPotato as open::Decode>::decode(&mut Cursor::new(&mut buf))
+        .unwrap()
+        .unwrap();
+    assert_eq!(decoded, potato);
+}
@@ -0,0 +1,181 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::packed::packed::{Packed, Samples, Series, Unpacked, UnpackedSeries};
+use polyglot_rs::{Decode, Encode, Kind};
+
+fn packed() -> Packed {
+    Packed {
+        doubles: vec![1.5, -2.25, f64::MAX],
+        floats: vec![0.5],
+        int32s: vec![-1, 0, i32::MAX],
+        sint64s: vec![i64::MIN, 300],
+        fixed32s: vec![u32::MAX],
+        uint64s: Vec::new(),
+        name: "name".to_string(),
+        flags: vec![true, false, false, true, true, true, false, true, true],
+    }
+}
+
+fn unpacked() -> Unpacked {
+    let packed = packed();
+    Unpacked {
+        doubles: packed.doubles,
+        floats: packed.floats,
+        int32s: packed.int32s,
+        sint64s: packed.sint64s,
+        fixed32s: packed.fixed32s,
+        uint64s: packed.uint64s,
+        name: packed.name,
+        flags: packed.flags,
+    }
+}
+
+#[test]
+fn test_packed() {
+    let buf = packed().encode_to_vec().unwrap();
+    // The name comes first, followed by the arrays.
+    assert_eq!(buf[7..9], [Kind::PackedArray as u8, Kind::F64 as u8]);
+    assert_eq!(Packed::decode_from_slice(&buf).unwrap(), packed());
+
+    // Each double of the unpacked array has its own kind.
+    let unpacked_buf = unpacked().encode_to_vec().unwrap();
+    assert!(buf.len() < unpacked_buf.len());
+}
+
+#[test]
+fn test_packed_interop() {
+    let buf = packed().encode_to_vec().unwrap();
+    assert_eq!(Unpacked::decode_from_slice(&buf).unwrap(), unpacked());
+
+    let buf = unpacked().encode_to_vec().unwrap();
+    assert_eq!(Packed::decode_from_slice(&buf).unwrap(), packed());
+
+    let mut reused = Packed {
+        doubles: vec![0.0; 16],
+        ..Default::default()
+    };
+    let mut buf = packed().encode_to_vec().unwrap();
+    reused
+        .decode_into(&mut std::io::Cursor::new(&mut buf))
+        .unwrap();
+    assert_eq!(reused, packed());
+}
+
+#[test]
+fn test_packed_bools() {
+    let flags = (0..10_000).map(|i| i % 3 == 0).collect::<Vec<_>>();
+    let packed = Packed {
+        flags: flags.clone(),
+        ..Default::default()
+    };
+    let unpacked = Unpacked {
+        flags,
+        ..Default::default()
+    };
+
+    // A bit per flag instead of two bytes.
+    let buf = packed.encode_to_vec().unwrap();
+    assert!(buf.len() < 1_300);
+    assert_eq!(Unpacked::decode_from_slice(&buf).unwrap(), unpacked);
+
+    let buf = unpacked.encode_to_vec().unwrap();
+    assert!(buf.len() > 20_000);
+    assert_eq!(Packed::decode_from_slice(&buf).unwrap(), packed);
+}
+
+#[test]
+fn test_packed_delimited() {
+    let samples = Samples {
+        name: "samples".to_string(),
+        values: vec![1.0, 2.0, 3.0],
+    };
+    let buf = samples.encode_to_vec().unwrap();
+    assert!(buf.contains(&(Kind::PackedArray as u8)));
+    assert_eq!(Samples::decode_from_slice(&buf).unwrap(), samples);
+}
+
+fn series() -> Series {
+    Series {
+        timestamps: vec![1_700_000_000, 1_700_000_001, 1_700_000_003, 1_700_000_003],
+        ids: vec![u64::MAX, 0, u64::MAX, 1],
+        offsets: vec![i64::MIN, i64::MAX, i64::MIN, -1],
+        values: vec![0.0, 0.0, -0.0, f64::NAN, f64::NAN, 1.5],
+        flags: vec![7; 100],
+        gains: Vec::new(),
+    }
+}
+
+fn unpacked_series() -> UnpackedSeries {
+    let series = series();
+    UnpackedSeries {
+        timestamps: series.timestamps,
+        ids: series.ids,
+        offsets: series.offsets,
+        values: series.values,
+        flags: series.flags,
+        gains: series.gains,
+    }
+}
+
+fn same_values(a: &[f64], b: &[f64]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.to_bits() == b.to_bits())
+}
+
+#[test]
+fn test_delta_and_run_length() {
+    let buf = series().encode_to_vec().unwrap();
+    assert_eq!(buf[..2], [Kind::DeltaArray as u8, Kind::I64 as u8]);
+    assert!(buf.contains(&(Kind::RunLengthArray as u8)));
+
+    let decoded = Series::decode_from_slice(&buf).unwrap();
+    let expected = series();
+    assert_eq!(decoded.timestamps, expected.timestamps);
+    assert_eq!(decoded.ids, expected.ids);
+    assert_eq!(decoded.offsets, expected.offsets);
+    assert!(same_values(&decoded.values, &expected.values));
+    assert_eq!(decoded.flags, expected.flags);
+    assert!(decoded.gains.is_empty());
+
+    // A hundred of the same flag take up a single run.
+    let unpacked_buf = unpacked_series().encode_to_vec().unwrap();
+    assert!(buf.len() + 100 < unpacked_buf.len());
+}
+
+#[test]
+fn test_delta_and_run_length_interop() {
+    let buf = series().encode_to_vec().unwrap();
+    let decoded = UnpackedSeries::decode_from_slice(&buf).unwrap();
+    assert_eq!(decoded.timestamps, unpacked_series().timestamps);
+    assert!(same_values(&decoded.values, &unpacked_series().values));
+    assert_eq!(decoded.flags, unpacked_series().flags);
+
+    let buf = unpacked_series().encode_to_vec().unwrap();
+    let decoded = Series::decode_from_slice(&buf).unwrap();
+    assert_eq!(decoded.ids, series().ids);
+    assert_eq!(decoded.offsets, series().offsets);
+
+    let mut reused = Series {
+        flags: vec![1; 4],
+        ..Default::default()
+    };
+    let mut buf = series().encode_to_vec().unwrap();
+    reused
+        .decode_into(&mut std::io::Cursor::new(&mut buf))
+        .unwrap();
+    assert_eq!(reused.flags, series().flags);
+    assert_eq!(reused.timestamps, series().timestamps);
+}
@@ -0,0 +1,114 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+mod common;
+
+use common::encode;
+use polyglot_build_example::tests::{expr, Decode, Expr, Node, Ping, Pong};
+use polyglot_rs::{DecodingError, MAX_DEPTH};
+use std::io::Cursor;
+
+fn node(name: &str, children: Vec<Node>, parent: Option<Node>) -> Node {
+    Node {
+        name: name.to_string(),
+        children,
+        parent: parent.map(Box::new),
+    }
+}
+
+/// A node whose chain of parents is `depth` messages long, including itself.
+fn chain(depth: usize) -> Node {
+    let mut current = node("0", vec![], None);
+    for i in 1..depth {
+        current = node(&i.to_string(), vec![], Some(current));
+    }
+    current
+}
+
+#[test]
+fn test_recursive_round_trip() {
+    let tree = node(
+        "root",
+        vec![
+            node("a", vec![node("a.a", vec![], None)], None),
+            node("b", vec![], None),
+        ],
+        Some(node("parent", vec![], None)),
+    );
+    let mut buf = encode(&tree);
+    let decoded = Node::decode(&mut Cursor::new(&mut buf)).unwrap().unwrap();
+    assert_eq!(decoded.name, "root");
+    assert_eq!(decoded.children.len(), 2);
+    assert_eq!(decoded.children[0].children[0].name, "a.a");
+    assert_eq!(decoded.parent.unwrap().name, "parent");
+
+    let ping = Ping {
+        label: "ping".to_string(),
+        pong: Some(Box::new(Pong {
+            label: "pong".to_string(),
+            ping: None,
+        })),
+    };
+    let mut buf = encode(&ping);
+    let decoded = Ping::decode(&mut Cursor::new(&mut buf)).unwrap().unwrap();
+    assert_eq!(decoded.pong.unwrap().label, "pong");
+
+    let expr = Expr {
+        kind: Some(expr::Kind2::Negate(Box::new(Expr {
+            kind: Some(expr::Kind2::Literal(7)),
+        }))),
+    };
+    let mut buf = encode(&expr);
+    let decoded = Expr::decode(&mut Cursor::new(&mut buf)).unwrap().unwrap();
+    match decoded.kind {
+        Some(expr::Kind2::Negate(inner)) => {
+            assert!(matches!(inner.kind, Some(expr::Kind2::Literal(7))))
+        }
+        _ => panic!("expected a negation"),
+    }
+}
+
+#[test]
+fn test_depth_limit() {
+    let mut buf = encode(&chain(MAX_DEPTH));
+    let decoded = Node::decode(&mut Cursor::new(&mut buf)).unwrap().unwrap();
+    assert_eq!(decoded.name, (MAX_DEPTH - 1).to_string());
+
+    let mut buf = encode(&chain(MAX_DEPTH + 1));
+    let err = Node::decode(&mut Cursor::new(&mut buf)).err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<DecodingError>(),
+        Some(&DecodingError::MaxDepthExceeded)
+    );
+
+    let mut buf = encode(&chain(10));
+    assert!(Node::decode_with_depth(&mut Cursor::new(&mut buf), 10).is_ok());
+    assert!(Node::decode_with_depth(&mut Cursor::new(&mut buf), 9).is_err());
+}
+
+#[test]
+fn test_depth_limit_arrays() {
+    let mut nested = node("leaf", vec![], None);
+    for _ in 0..MAX_DEPTH {
+        nested = node("branch", vec![nested], None);
+    }
+    let mut buf = encode(&nested);
+    let err = Node::decode(&mut Cursor::new(&mut buf)).err().unwrap();
+    assert_eq!(
+        err.downcast_ref::<DecodingError>(),
+        Some(&DecodingError::MaxDepthExceeded)
+    );
+}
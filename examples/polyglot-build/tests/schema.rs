@@ -0,0 +1,162 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build::{load, DescriptorPool};
+use polyglot_build_example::evolution::evolution::{v2, Batch, Profile, V2};
+use polyglot_build_example::tests::{
+    choice, Choice, Data, Encode, Node, Nullable, StockPrices, StockPricesSuperWrap,
+    StockPricesWrapper, Test,
+};
+use polyglot_rs::{check_compatibility, validate, DynamicMessage, Value};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+fn pool() -> DescriptorPool {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("tests");
+    let (pool, _) = load(
+        &[dir.join("tests.proto"), dir.join("evolution.proto")],
+        &[dir],
+    )
+    .unwrap();
+    pool
+}
+
+fn check<T: Encode>(pool: &DescriptorPool, root: &str, val: &T) {
+    let buf = val.encode_to_vec().unwrap();
+    validate(&buf, &pool.schema(root)).unwrap();
+}
+
+#[test]
+fn test_validate_generated() {
+    let pool = pool();
+    check(
+        &pool,
+        "Nullable",
+        &Nullable {
+            name: "name".to_string(),
+            count: Some(42),
+            kind: None,
+            note: Some("note".to_string()),
+            data: Some(Data {
+                message: "Hello".to_string(),
+                checker: Test::Monkey,
+            }),
+        },
+    );
+    check(
+        &pool,
+        "Choice",
+        &Choice {
+            label: "data".to_string(),
+            value: Some(choice::Value::Data(Data::default())),
+        },
+    );
+    check(
+        &pool,
+        "Node",
+        &Node {
+            name: "root".to_string(),
+            children: vec![Node::default(), Node::default()],
+            parent: Some(Box::default()),
+        },
+    );
+    check(
+        &pool,
+        "StockPricesSuperWrap",
+        &StockPricesSuperWrap {
+            prices: HashMap::from([(
+                "wrap".to_string(),
+                StockPricesWrapper {
+                    s_prices: vec![StockPrices {
+                        prices: HashMap::from([("price".to_string(), 1.5)]),
+                    }],
+                },
+            )]),
+        },
+    );
+    check(
+        &pool,
+        "evolution.Batch",
+        &Batch {
+            users: vec![V2 {
+                scores: HashMap::from([("score".to_string(), -3)]),
+                name: "name".to_string(),
+                tags: vec!["tag".to_string()],
+                profile: Some(Profile {
+                    bio: "bio".to_string(),
+                }),
+                age: 42,
+                contact: Some(v2::Contact::Email("email".to_string())),
+            }],
+            count: 1,
+        },
+    );
+}
+
+#[test]
+fn test_dynamic_generated() {
+    let pool = pool();
+    let buf = Choice {
+        label: "number".to_string(),
+        value: Some(choice::Value::Number(7)),
+    }
+    .encode_to_vec()
+    .unwrap();
+    let choice = DynamicMessage::decode(
+        Arc::new(pool.schema("Choice")),
+        &mut Cursor::new(buf.as_slice()),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        choice.get("label"),
+        Some(&Value::String("number".to_string()))
+    );
+    assert_eq!(
+        choice.get("value"),
+        Some(&Value::Oneof(4, Box::new(Value::U64(7))))
+    );
+}
+
+#[test]
+fn test_compatibility() {
+    let pool = pool();
+    let old = pool.schema("evolution.OldBatch");
+    let new = pool.schema("evolution.Batch");
+    assert_eq!(check_compatibility(&old, &new), []);
+    assert_eq!(check_compatibility(&new, &old), []);
+
+    let changes = check_compatibility(
+        &pool.schema("Outer.MiddleAA"),
+        &pool.schema("Outer.MiddleBB"),
+    );
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        changes[0].to_string(),
+        "Outer.MiddleBB.Inner.ival: ChangedKind { old: I64, new: I32 } breaks old and new readers"
+    );
+    assert_eq!(
+        check_compatibility(
+            &pool.schema("Resulting"),
+            &pool.schema("SearchResponse.Result")
+        ),
+        []
+    );
+}
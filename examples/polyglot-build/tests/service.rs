@@ -0,0 +1,142 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build_example::tests::{
+    request, Data, EchoService, EchoServiceClient, EchoServiceMethod, EchoServiceServer,
+    LowerServiceMethod, Request, Response, SearchResponse, StockPricesWrapper, Test, WatchService,
+    WatchServiceServer,
+};
+use polyglot_rs::{Handler, RpcError, Transport};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+type Call = (u32, Vec<u8>, oneshot::Sender<Result<Vec<u8>, RpcError>>);
+
+/// Forwards calls over a channel to a task serving them with `handler`.
+struct ChannelTransport {
+    sender: mpsc::Sender<Call>,
+}
+
+impl ChannelTransport {
+    fn serve(handler: impl Handler + 'static) -> Self {
+        let handler = Arc::new(handler);
+        let (sender, mut receiver) = mpsc::channel::<Call>(16);
+        tokio::spawn(async move {
+            while let Some((method, request, reply)) = receiver.recv().await {
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let _ = reply.send(handler.handle(method, request).await);
+                });
+            }
+        });
+        Self { sender }
+    }
+}
+
+impl Transport for ChannelTransport {
+    async fn call(&self, method: u32, request: Vec<u8>) -> Result<Vec<u8>, RpcError> {
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send((method, request, reply))
+            .await
+            .map_err(|e| RpcError::Transport(e.to_string()))?;
+        response
+            .await
+            .map_err(|e| RpcError::Transport(e.to_string()))?
+    }
+}
+
+struct Echo;
+
+impl EchoService for Echo {
+    async fn echo(&self, request: Request) -> Result<Response, RpcError> {
+        Ok(Response {
+            message: request.message,
+            test: Some(Data {
+                message: format!("{:?}", request.corpus),
+                checker: Test::Monkey,
+            }),
+        })
+    }
+
+    async fn testy(&self, _: SearchResponse) -> Result<StockPricesWrapper, RpcError> {
+        Err(RpcError::Remote("not implemented".to_string()))
+    }
+}
+
+#[tokio::test]
+async fn test_echo() {
+    let client = EchoServiceClient::new(ChannelTransport::serve(EchoServiceServer::new(Echo)));
+
+    let response = client
+        .echo(&Request {
+            message: "Hello World".to_string(),
+            corpus: request::Corpus::Images,
+        })
+        .await
+        .unwrap();
+    assert_eq!(response.message, "Hello World");
+    let data = response.test.unwrap();
+    assert_eq!(data.message, "Images");
+    assert_eq!(data.checker, Test::Monkey);
+}
+
+#[tokio::test]
+async fn test_errors() {
+    let client = EchoServiceClient::new(ChannelTransport::serve(EchoServiceServer::new(Echo)));
+
+    let err = client
+        .testy(&SearchResponse {
+            results: vec![],
+            results2: vec![],
+            snippets: vec![],
+            snippets2: vec![],
+        })
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err, RpcError::Remote("not implemented".to_string()));
+
+    let server = EchoServiceServer::new(Echo);
+    assert_eq!(EchoServiceMethod::Testy as u32, 1);
+    assert_eq!(
+        server.handle(2, vec![]).await.err().unwrap(),
+        RpcError::UnknownMethod(2)
+    );
+    assert!(matches!(
+        server.handle(EchoServiceMethod::Echo as u32, vec![]).await,
+        Err(RpcError::Decoding(_))
+    ));
+}
+
+struct Watch;
+
+impl WatchService for Watch {}
+
+#[tokio::test]
+async fn test_streaming_only_service() {
+    let server = WatchServiceServer::new(Watch);
+    assert!(matches!(
+        server.handle(0, Vec::new()).await,
+        Err(RpcError::UnknownMethod(0))
+    ));
+}
+
+#[test]
+fn test_method_variants() {
+    assert_eq!(LowerServiceMethod::New as u32, 0);
+    assert_eq!(LowerServiceMethod::GetEcho as u32, 1);
+}
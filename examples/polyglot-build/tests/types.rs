@@ -0,0 +1,106 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+mod common;
+
+use bytes::Bytes;
+use common::{decode, encode};
+use indexmap::IndexMap;
+use polyglot_build_example::mapped;
+use polyglot_build_example::matrix::{matrix, Matrix};
+use polyglot_build_example::types::types::{mapped as types_mapped, Mapped, Sorted};
+use smol_str::SmolStr;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+#[test]
+fn test_field_options() {
+    let message = Mapped::builder()
+        .id("id")
+        .label(Some(SmolStr::new("label")))
+        .tags(vec![Arc::from("a"), Arc::from("b")])
+        .payload(Bytes::from_static(&[1, 2, 3]))
+        .chunks(vec![Bytes::new(), Bytes::from_static(&[4])])
+        .ordered(BTreeMap::from([
+            (
+                SmolStr::new("b"),
+                types_mapped::Entry::from(SmolStr::new("second")),
+            ),
+            (
+                SmolStr::new("a"),
+                types_mapped::Entry::from(SmolStr::new("first")),
+            ),
+        ]))
+        .indexed(IndexMap::from([
+            ("z".to_string(), Bytes::from_static(&[26])),
+            ("y".to_string(), Bytes::from_static(&[25])),
+        ]))
+        .plain("plain")
+        .value(Some(types_mapped::Value::Data(Bytes::from_static(&[5]))))
+        .build();
+
+    assert_eq!(message.id(), "id");
+    assert_eq!(message.label(), Some("label"));
+    assert_eq!(message.payload(), &[1, 2, 3]);
+
+    let decoded: Mapped = decode(encode(&message));
+    assert_eq!(decoded, message);
+    assert_eq!(decoded.indexed.keys().collect::<Vec<_>>(), vec!["z", "y"]);
+}
+
+#[test]
+fn test_hashable_maps() {
+    let sorted = Sorted::from(BTreeMap::from([(1, "one".to_string())]));
+    let set = HashSet::from([sorted.clone()]);
+    assert!(set.contains(&sorted));
+}
+
+#[test]
+fn test_config_types() {
+    let message = Matrix {
+        string_value: "string".to_string(),
+        bytes_value: vec![1, 2, 3],
+        optional_string: Some("optional".to_string()),
+        repeated_string: vec!["a".to_string(), "b".to_string()],
+        repeated_bytes: vec![vec![4]],
+        string_keys: HashMap::from([("key".to_string(), "value".to_string())]),
+        bytes_values: HashMap::from([("key".to_string(), vec![5])]),
+        message_values: HashMap::from([(
+            "key".to_string(),
+            matrix::Item::from("item".to_string()),
+        )]),
+        choice: Some(matrix::Choice::ChoiceString("choice".to_string())),
+        ..Matrix::default()
+    };
+    let buf = encode(&message);
+
+    // The same wire format decodes into the other types and encodes back to it.
+    let mapped: mapped::Matrix = decode(buf.clone());
+    assert_eq!(&*mapped.string_value, "string");
+    assert_eq!(mapped.bytes_value, Bytes::from_static(&[1, 2, 3]));
+    assert_eq!(mapped.optional_string.as_deref(), Some("optional"));
+    assert_eq!(mapped.repeated_bytes, vec![Bytes::from_static(&[4])]);
+    assert_eq!(
+        mapped.string_keys,
+        BTreeMap::from([(Arc::from("key"), Arc::from("value"))])
+    );
+    assert_eq!(
+        mapped.choice,
+        Some(mapped::matrix::Choice::ChoiceString(Arc::from("choice")))
+    );
+    assert_eq!(encode(&mapped), buf);
+    assert_eq!(decode::<Matrix>(encode(&mapped)), message);
+}
@@ -0,0 +1,87 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import (
+	"fmt"
+	"strings"
+
+	"google.golang.org/protobuf/reflect/protoreflect"
+)
+
+// accessor describes a struct field of a generated message along with the
+// return type and body of its getter.
+type accessor struct {
+	Name   string
+	Base   string
+	Type   string
+	Getter string
+	Body   string
+}
+
+// getAccessors returns the struct fields of a message, plain fields first and
+// then oneofs, matching the struct declaration. Base is the name without
+// escaping, for the names that get a prefix or suffix added.
+func (n *names) getAccessors(message protoreflect.MessageDescriptor, types fieldTypes) []accessor {
+	var accessors []accessor
+	for i := 0; i < message.Fields().Len(); i++ {
+		field := message.Fields().Get(i)
+		if isOneofField(field) {
+			continue
+		}
+		base := n.member(field.FullName())
+		name := rustIdent(base)
+		value := n.findValue(field, types)
+		a := accessor{Name: name, Base: base, Type: value, Getter: value, Body: "self." + name}
+		switch {
+		case field.IsMap():
+			a.Getter, a.Body = "&"+value, "&self."+name
+		case field.Cardinality() == protoreflect.Repeated:
+			a.Getter, a.Body = "&["+strings.TrimSuffix(strings.TrimPrefix(value, "Vec<"), ">")+"]", "&self."+name
+		case field.Kind() == protoreflect.MessageKind:
+			if isRecursive(field) {
+				a.Getter, a.Body = fmt.Sprintf("Option<&%s>", n.path(field.Message().FullName())), "self."+name+".as_deref()"
+			} else {
+				a.Getter, a.Body = fmt.Sprintf("Option<&%s>", n.path(field.Message().FullName())), "self."+name+".as_ref()"
+			}
+		case field.Kind() == protoreflect.StringKind || field.Kind() == protoreflect.BytesKind:
+			borrowed := "str"
+			if field.Kind() == protoreflect.BytesKind {
+				borrowed = "[u8]"
+			}
+			if field.HasOptionalKeyword() {
+				a.Getter, a.Body = fmt.Sprintf("Option<&%s>", borrowed), "self."+name+".as_deref()"
+			} else {
+				a.Getter, a.Body = "&"+borrowed, "&self."+name
+			}
+		}
+		accessors = append(accessors, a)
+	}
+	for _, oneof := range getOneofs(message) {
+		base := n.member(oneof.FullName())
+		name := rustIdent(base)
+		value := n.path(oneof.FullName())
+		accessors = append(accessors, accessor{
+			Name:   name,
+			Base:   base,
+			Type:   fmt.Sprintf("Option<%s>", value),
+			Getter: fmt.Sprintf("Option<&%s>", value),
+			Body:   fmt.Sprintf("self.%s.as_ref()", name),
+		})
+	}
+	return accessors
+}
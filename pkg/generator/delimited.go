@@ -0,0 +1,117 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+
+package generator
+
+import (
+	"sort"
+
+	"google.golang.org/protobuf/encoding/protowire"
+	"google.golang.org/protobuf/reflect/protoreflect"
+	"google.golang.org/protobuf/types/descriptorpb"
+)
+
+// delimitedOption is the field number of the delimited message option declared
+// in polyglot/options.proto.
+const delimitedOption protowire.Number = 50200
+
+// isDelimited reports whether a message is written with its length in front of
+// it and its fields in field number order.
+func isDelimited(message protoreflect.MessageDescriptor) bool {
+	options, ok := message.Options().(*descriptorpb.MessageOptions)
+	if !ok || options == nil {
+		return false
+	}
+	delimited := false
+	unknown := options.ProtoReflect().GetUnknown()
+	for len(unknown) > 0 {
+		number, typ, n := protowire.ConsumeTag(unknown)
+		if n < 0 {
+			break
+		}
+		unknown = unknown[n:]
+		n = protowire.ConsumeFieldValue(number, typ, unknown)
+		if n < 0 {
+			break
+		}
+		if number == delimitedOption && typ == protowire.VarintType {
+			value, _ := protowire.ConsumeVarint(unknown)
+			delimited = value != 0
+		}
+		unknown = unknown[n:]
+	}
+	return delimited
+}
+
+// member is a oneof or a run of fields of a delimited message. Consecutive
+// required scalars are kept together so that they can be chained.
+type member struct {
+	Fields []protoreflect.FieldDescriptor
+	Oneof  protoreflect.OneofDescriptor
+}
+
+func isChained(field protoreflect.FieldDescriptor) bool {
+	return field.Cardinality() != protoreflect.Repeated && field.Kind() != protoreflect.MessageKind && !field.HasOptionalKeyword()
+}
+
+// getMembers returns the fields and oneofs of a delimited message by field
+// number, so that new fields go at the end. Oneofs take the place of their
+// lowest numbered field.
+func getMembers(message protoreflect.MessageDescriptor) []member {
+	type numbered struct {
+		number protoreflect.FieldNumber
+		member member
+	}
+	var members []numbered
+	fields := message.Fields()
+	for i := 0; i < fields.Len(); i++ {
+		if field := fields.Get(i); !isOneofField(field) {
+			members = append(members, numbered{field.Number(), member{Fields: []protoreflect.FieldDescriptor{field}}})
+		}
+	}
+	for _, oneof := range getOneofs(message) {
+		number := protoreflect.FieldNumber(1<<31 - 1)
+		for _, field := range getOneofFields(oneof) {
+			if field.Number() < number {
+				number = field.Number()
+			}
+		}
+		members = append(members, numbered{number, member{Oneof: oneof}})
+	}
+	sort.SliceStable(members, func(i, j int) bool {
+		return members[i].number < members[j].number
+	})
+
+	var out []member
+	for _, m := range members {
+		if last := len(out) - 1; last >= 0 && out[last].Oneof == nil && m.member.Oneof == nil &&
+			isChained(out[last].Fields[0]) && isChained(m.member.Fields[0]) {
+			out[last].Fields = append(out[last].Fields, m.member.Fields[0])
+			continue
+		}
+		out = append(out, m.member)
+	}
+	return out
+}
+
+func fieldList(fields protoreflect.FieldDescriptors) []protoreflect.FieldDescriptor {
+	list := make([]protoreflect.FieldDescriptor, 0, fields.Len())
+	for i := 0; i < fields.Len(); i++ {
+		list = append(list, fields.Get(i))
+	}
+	return list
+}
@@ -0,0 +1,156 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import (
+	"fmt"
+	"strings"
+
+	"google.golang.org/protobuf/reflect/protoreflect"
+)
+
+// listFlag collects every value of a plugin parameter that may be repeated,
+// such as derive=serde::Serialize,derive=serde::Deserialize.
+type listFlag []string
+
+func (l *listFlag) String() string {
+	return strings.Join(*l, ",")
+}
+
+func (l *listFlag) Set(value string) error {
+	*l = append(*l, value)
+	return nil
+}
+
+type comparability struct {
+	eq   bool
+	hash bool
+}
+
+func (c comparability) and(other comparability) comparability {
+	return comparability{eq: c.eq && other.eq, hash: c.hash && other.hash}
+}
+
+// getComparable works out whether a message can derive Eq and Hash. Floats rule
+// out both and maps other than BTreeMap rule out Hash. Every message reachable from the given one
+// starts out comparable and loses derives until nothing changes, so that
+// recursive messages settle on a consistent answer.
+func getComparable(message protoreflect.MessageDescriptor, types fieldTypes) comparability {
+	var messages []protoreflect.MessageDescriptor
+	done := map[protoreflect.FullName]comparability{}
+	stack := []protoreflect.MessageDescriptor{message}
+	for len(stack) > 0 {
+		m := stack[len(stack)-1]
+		stack = stack[:len(stack)-1]
+		if _, ok := done[m.FullName()]; ok {
+			continue
+		}
+		done[m.FullName()] = comparability{eq: true, hash: true}
+		messages = append(messages, m)
+		for i := 0; i < m.Fields().Len(); i++ {
+			f := m.Fields().Get(i)
+			if f.IsMap() {
+				f = f.MapValue()
+			}
+			if f.Kind() == protoreflect.MessageKind {
+				stack = append(stack, f.Message())
+			}
+		}
+	}
+
+	var field func(f protoreflect.FieldDescriptor) comparability
+	field = func(f protoreflect.FieldDescriptor) comparability {
+		switch {
+		case f.IsMap():
+			hash := types.of(f).Map == MapTypeBTreeMap
+			return comparability{eq: true, hash: hash}.and(field(f.MapKey())).and(field(f.MapValue()))
+		case f.Kind() == protoreflect.FloatKind || f.Kind() == protoreflect.DoubleKind:
+			return comparability{}
+		case f.Kind() == protoreflect.MessageKind:
+			return done[f.Message().FullName()]
+		default:
+			return comparability{eq: true, hash: true}
+		}
+	}
+
+	for changed := true; changed; {
+		changed = false
+		for _, m := range messages {
+			c := done[m.FullName()]
+			for i := 0; i < m.Fields().Len(); i++ {
+				c = c.and(field(m.Fields().Get(i)))
+			}
+			if c != done[m.FullName()] {
+				done[m.FullName()] = c
+				changed = true
+			}
+		}
+	}
+	return done[message.FullName()]
+}
+
+func getEnumDerives(openEnums bool) []string {
+	if openEnums {
+		return []string{"Debug", "Eq", "PartialEq", "FromPrimitive", "IntoPrimitive", "Copy", "Clone", "Hash"}
+	}
+	return []string{"Debug", "Eq", "PartialEq", "TryFromPrimitive", "Copy", "Clone", "Hash"}
+}
+
+// getMessageDerives returns the derives for a message struct, or for one of its
+// oneof enums when withDefault is false.
+func getMessageDerives(message protoreflect.MessageDescriptor, withDefault bool, types fieldTypes) []string {
+	derives := []string{"Debug", "Clone", "PartialEq"}
+	if withDefault {
+		derives = append(derives, "Default")
+	}
+	c := getComparable(message, types)
+	if c.eq {
+		derives = append(derives, "Eq")
+	}
+	if c.hash {
+		derives = append(derives, "Hash")
+	}
+	return derives
+}
+
+// getDefaultValue returns the enum's default: its zero value, or the first value
+// for proto2 enums that don't have one. Default is implemented by hand rather
+// than derived with #[default], which TryFromPrimitive would also use for every
+// unknown value instead of returning an error.
+func getDefaultValue(enum protoreflect.EnumDescriptor) protoreflect.EnumValueDescriptor {
+	if value := enum.Values().ByNumber(0); value != nil {
+		return value
+	}
+	return enum.Values().Get(0)
+}
+
+// getUnknownVariant names the catch-all variant of an open enum, which holds
+// values this side doesn't know about so that they are written back unchanged.
+func getUnknownVariant(enum protoreflect.EnumDescriptor) string {
+	name := "Unknown"
+	for enum.Values().ByName(protoreflect.Name(name)) != nil {
+		name += "_"
+	}
+	return name
+}
+
+func enumToU32(value string, openEnums bool) string {
+	if openEnums {
+		return fmt.Sprintf("u32::from(%s)", value)
+	}
+	return value + " as u32"
+}
@@ -0,0 +1,69 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import "google.golang.org/protobuf/reflect/protoreflect"
+
+// enumVariant is a value of an enum along with the number it is written as,
+// which is its declared number cast to a u32.
+type enumVariant struct {
+	Name   string
+	Number uint32
+}
+
+// enumAlias is a value of an allow_alias enum that shares its number with an
+// earlier value, and is generated as an associated constant pointing at it.
+type enumAlias struct {
+	Name    string
+	Variant string
+}
+
+func getEnumVariants(enum protoreflect.EnumDescriptor) []enumVariant {
+	var variants []enumVariant
+	for i := 0; i < enum.Values().Len(); i++ {
+		value := enum.Values().Get(i)
+		if enum.Values().ByNumber(value.Number()) == value {
+			variants = append(variants, enumVariant{Name: rustIdent(string(value.Name())), Number: uint32(value.Number())})
+		}
+	}
+	return variants
+}
+
+func getEnumAliases(enum protoreflect.EnumDescriptor) []enumAlias {
+	var aliases []enumAlias
+	for i := 0; i < enum.Values().Len(); i++ {
+		value := enum.Values().Get(i)
+		if variant := enum.Values().ByNumber(value.Number()); variant != value {
+			aliases = append(aliases, enumAlias{Name: rustIdent(string(value.Name())), Variant: rustIdent(string(variant.Name()))})
+		}
+	}
+	return aliases
+}
+
+// getUnknownDiscriminant returns a discriminant for the catch-all variant of an
+// open enum. It is never used, but it has to be one that no other variant has.
+func getUnknownDiscriminant(enum protoreflect.EnumDescriptor) uint32 {
+	used := map[uint32]bool{}
+	for _, variant := range getEnumVariants(enum) {
+		used[variant.Number] = true
+	}
+	var n uint32
+	for used[n] {
+		n++
+	}
+	return n
+}
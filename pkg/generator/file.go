@@ -16,6 +16,20 @@
 
 package generator
 
+import "google.golang.org/protobuf/reflect/protoreflect"
+
 type File interface {
 	P(v ...interface{})
 }
+
+// includedFile is a generated file as listed in the include file, which makes
+// it a module named after the file so that other generated files can refer to
+// its types.
+type includedFile struct {
+	Module string
+	Path   string
+}
+
+func newIncludedFile(file protoreflect.FileDescriptor, path string) includedFile {
+	return includedFile{Module: fileModule(file), Path: path}
+}
@@ -19,14 +19,17 @@ package generator
 import (
 	"bytes"
 	"flag"
+	"fmt"
 	"github.com/loopholelabs/polyglot-rs/pkg/utils"
 	"os/exec"
+	"strings"
 	"text/template"
 
 	"github.com/loopholelabs/polyglot-rs/internal/version"
 	"github.com/loopholelabs/polyglot-rs/templates"
 	"google.golang.org/protobuf/compiler/protogen"
 	"google.golang.org/protobuf/proto"
+	"google.golang.org/protobuf/reflect/protoreflect"
 	"google.golang.org/protobuf/types/pluginpb"
 )
 
@@ -41,6 +44,9 @@ const (
 type Generator struct {
 	options      *protogen.Options
 	templ        *template.Template
+	names        *names
+	types        *fieldTypes
+	includeFile  *string
 	CustomFields func() string
 	CustomEncode func() string
 	CustomDecode func() string
@@ -51,6 +57,15 @@ func New() *Generator {
 
 	var flags flag.FlagSet
 	privacy := flags.String("privacy", GeneratedFieldPrivacyPrivate, "Privacy of generated fields (private, public, crate)")
+	var derives, attributes listFlag
+	flags.Var(&derives, "derive", "Extra trait to derive on every generated type (repeatable)")
+	flags.Var(&attributes, "attribute", "Extra attribute to add to every generated type (repeatable)")
+	openEnums := flags.Bool("open_enums", false, "Keep unknown enum values in an Unknown(u32) variant instead of failing to decode")
+	includeFile := flags.String("include_file", "", "Also generate a file with this name that includes every generated file as a module named after it")
+	var types fieldTypes
+	flags.StringVar(&types.Map, "map_type", MapTypeHashMap, "Rust type of map fields (hash_map, btree_map, index_map)")
+	flags.StringVar(&types.Bytes, "bytes_type", BytesTypeVec, "Rust type of bytes fields (vec, bytes)")
+	flags.StringVar(&types.String, "string_type", StringTypeString, "Rust type of string fields (string, arc_str, smol_str)")
 
 	templ := template.Must(template.New("main").Funcs(template.FuncMap{
 		"CamelCase":          utils.CamelCaseFullName,
@@ -59,13 +74,24 @@ func New() *Generator {
 		"Counter":            utils.Counter,
 		"FirstLowerCase":     utils.FirstLowerCase,
 		"FirstLowerCaseName": utils.FirstLowerCaseName,
-		"FindValue":          findValue,
 		"GetKind":            getKind,
 		"GetLUTEncoder":      getLUTEncoder,
 		"GetLUTDecoder":      getLUTDecoder,
-		"GetEncodingFields":  getEncodingFields,
 		"GetDecodingFields":  getDecodingFields,
+		"IsDelimited":        isDelimited,
+		"ArrayEncoder":       arrayEncoder,
+		"PackedSuffix":       packedSuffix,
+		"GetMembers":         getMembers,
 		"GetKindLUT":         getKindLUT,
+		"GetOneofs":          getOneofs,
+		"GetOneofFields":     getOneofFields,
+		"IsOneofField":       isOneofField,
+		"IsRecursive":        isRecursive,
+		"GetUnaryMethods":    getUnaryMethods,
+		"GetUnknownVariant":  getUnknownVariant,
+		"GetEnumVariants":    getEnumVariants,
+		"GetEnumAliases":     getEnumAliases,
+		"GetDefaultValue":    getDefaultValue,
 		"SnakeCase":          utils.SnakeCase,
 		"SnakeCaseName":      utils.SnakeCaseName,
 		"CustomFields": func() string {
@@ -77,6 +103,87 @@ func New() *Generator {
 		"CustomDecode": func() string {
 			return g.CustomDecode()
 		},
+		"FindValue": func(field protoreflect.FieldDescriptor) string {
+			return g.names.findValue(field, *g.types)
+		},
+		"GetEncodingFields": func(fields protoreflect.FieldDescriptors, openEnums bool) encodingFields {
+			return g.names.getEncodingFields(fields, openEnums, *g.types)
+		},
+		"GetFieldDecoding": func(field protoreflect.FieldDescriptor) decodingFields {
+			return decodingFieldsOf([]protoreflect.FieldDescriptor{field})
+		},
+		"GetMemberEncoding": func(m member, openEnums bool) encodingFields {
+			return g.names.encodingFieldsOf(m.Fields, openEnums, *g.types)
+		},
+		"GetAccessors": func(message protoreflect.MessageDescriptor) []accessor {
+			return g.names.getAccessors(message, *g.types)
+		},
+		"GetMessageDerives": func(message protoreflect.MessageDescriptor, withDefault bool) []string {
+			return getMessageDerives(message, withDefault, *g.types)
+		},
+		"Encoder": func(field protoreflect.FieldDescriptor) string {
+			return g.types.of(field).encoder(field.Kind())
+		},
+		"Decoder": func(field protoreflect.FieldDescriptor) string {
+			return g.types.of(field).decoder(field.Kind())
+		},
+		"DecoderInto": func(field protoreflect.FieldDescriptor) string {
+			return g.types.of(field).decoderInto(field.Kind())
+		},
+		"MapType": func(field protoreflect.FieldDescriptor) string {
+			return g.types.of(field).mapType()
+		},
+		"TypeName": func(fullName protoreflect.FullName) string {
+			return g.names.ident(fullName)
+		},
+		"TypePath": func(fullName protoreflect.FullName) string {
+			return g.names.path(fullName)
+		},
+		"Member": func(desc protoreflect.Descriptor) string {
+			return rustIdent(g.names.member(desc.FullName()))
+		},
+		"MemberBase": func(desc protoreflect.Descriptor) string {
+			return g.names.member(desc.FullName())
+		},
+		"Variant": func(field protoreflect.FieldDescriptor) string {
+			return g.names.variant(field)
+		},
+		"Module": func(message protoreflect.MessageDescriptor) string {
+			return g.names.modules[message.FullName()]
+		},
+		"OpenModule": func(module string) string {
+			return g.names.openModule(module)
+		},
+		"CloseModule": func() string {
+			return g.names.closeModule()
+		},
+		"RustIdent": func(name protoreflect.Name) string {
+			return rustIdent(string(name))
+		},
+		"Derive": func(base []string) string {
+			var out strings.Builder
+			out.WriteString("#[derive(" + strings.Join(append(base, derives...), ", ") + ")]")
+			for _, attribute := range attributes {
+				out.WriteString("\n" + attribute)
+			}
+			return out.String()
+		},
+		"GetUnknownDiscriminant": getUnknownDiscriminant,
+		"OpenEnums": func() bool {
+			return *openEnums
+		},
+		"GetEnumDerives": func() []string {
+			return getEnumDerives(*openEnums)
+		},
+		"EnumToU32": func(value string) string {
+			return enumToU32(value, *openEnums)
+		},
+		"DecodeEnum": func(name string, err string) string {
+			if *openEnums {
+				return fmt.Sprintf("%s::from(b.decode_u32()?)", name)
+			}
+			return fmt.Sprintf("%s::try_from(b.decode_u32()?).ok().ok_or(DecodingError::%s)?", name, err)
+		},
 		"GeneratedFieldPrivacy": func() GeneratedFieldPrivacy {
 			return GeneratedFieldPrivacy(*privacy)
 		},
@@ -88,6 +195,8 @@ func New() *Generator {
 			ImportRewriteFunc: func(path protogen.GoImportPath) protogen.GoImportPath { return path },
 		},
 		templ:        templ,
+		includeFile:  includeFile,
+		types:        &types,
 		CustomEncode: func() string { return "" },
 		CustomDecode: func() string { return "" },
 		CustomFields: func() string { return "" },
@@ -110,11 +219,13 @@ func (g *Generator) Generate(req *pluginpb.CodeGeneratorRequest) (res *pluginpb.
 		return nil, err
 	}
 
+	var included []includedFile
 	for _, f := range plugin.Files {
 		if !f.Generate {
 			continue
 		}
 		genFile := plugin.NewGeneratedFile(FileName(f.GeneratedFilenamePrefix), f.GoImportPath)
+		included = append(included, newIncludedFile(f.Desc, FileName(f.GeneratedFilenamePrefix)))
 
 		packageName := string(f.Desc.Package().Name())
 		if packageName == "" {
@@ -127,6 +238,13 @@ func (g *Generator) Generate(req *pluginpb.CodeGeneratorRequest) (res *pluginpb.
 		}
 	}
 
+	if *g.includeFile != "" {
+		err = g.executeIncludeFile(plugin.NewGeneratedFile(*g.includeFile, ""), included)
+		if err != nil {
+			return nil, err
+		}
+	}
+
 	return plugin.Response(), nil
 }
 
@@ -137,14 +255,17 @@ func (g *Generator) ExecuteTemplate(
 	header bool,
 ) error {
 	var buf bytes.Buffer
-	deps := DependencyAnalysis(protoFile)
+	deps := DependencyAnalysis(protoFile, *g.types)
+	g.names = newNames(protoFile.Desc)
 
 	err := g.templ.ExecuteTemplate(&buf, "base.templ", map[string]interface{}{
 		"pluginVersion": version.Version,
 		"sourcePath":    protoFile.Desc.Path(),
 		"package":       packageName,
+		"modules":       packageModules(protoFile.Desc),
 		"enums":         protoFile.Desc.Enums(),
 		"messages":      protoFile.Desc.Messages(),
+		"services":      protoFile.Desc.Services(),
 		"header":        header,
 		"dependencies":  deps,
 	})
@@ -152,8 +273,24 @@ func (g *Generator) ExecuteTemplate(
 		return err
 	}
 
+	return writeFormatted(genFile, buf.Bytes())
+}
+
+func (g *Generator) executeIncludeFile(genFile *protogen.GeneratedFile, files []includedFile) error {
+	var buf bytes.Buffer
+	err := g.templ.ExecuteTemplate(&buf, "includeFile", map[string]interface{}{
+		"pluginVersion": version.Version,
+		"files":         files,
+	})
+	if err != nil {
+		return err
+	}
+	return writeFormatted(genFile, buf.Bytes())
+}
+
+func writeFormatted(genFile *protogen.GeneratedFile, source []byte) error {
 	cmd := exec.Command("rustfmt")
-	cmd.Stdin = bytes.NewReader(buf.Bytes())
+	cmd.Stdin = bytes.NewReader(source)
 	output, err := cmd.CombinedOutput()
 	if err != nil {
 		println(string(output))
@@ -22,43 +22,53 @@ import (
 )
 
 type Dependencies struct {
-	Enums bool
-	Maps  bool
+	Enums        bool
+	Maps         bool
+	Services     bool
+	UnaryMethods bool
 }
 
-func DependencyAnalysis(file *protogen.File) *Dependencies {
+func DependencyAnalysis(file *protogen.File, types fieldTypes) *Dependencies {
 	dependencies := &Dependencies{
-		Enums: false,
-		Maps:  false,
+		Enums:        false,
+		Maps:         false,
+		Services:     false,
+		UnaryMethods: false,
 	}
 
 	if len(file.Enums) > 0 {
 		dependencies.Enums = true
 	}
-	for _, message := range file.Messages {
-		for _, field := range message.Fields {
-			if field.Desc.Kind() == protoreflect.MessageKind {
-				if field.Desc.Message().IsMapEntry() {
-					dependencies.Maps = true
-				}
-				if field.Desc.Message().Fields().Len() > 0 {
-					dependencies = traverseFields(field.Message, dependencies)
-				}
+	if len(file.Services) > 0 {
+		dependencies.Services = true
+	}
+	for _, service := range file.Services {
+		for _, method := range service.Methods {
+			if isUnary(method.Desc) {
+				dependencies.UnaryMethods = true
 			}
 		}
 	}
+	seen := make(map[protoreflect.FullName]bool)
+	for _, message := range file.Messages {
+		dependencies = traverseFields(message, dependencies, types, seen)
+	}
 	return dependencies
 }
 
-func traverseFields(message *protogen.Message, dependencies *Dependencies) *Dependencies {
+// traverseFields looks for dependencies in the fields of message and the messages
+// they embed, visiting each message once so that recursive messages terminate.
+func traverseFields(message *protogen.Message, dependencies *Dependencies, types fieldTypes, seen map[protoreflect.FullName]bool) *Dependencies {
+	if seen[message.Desc.FullName()] {
+		return dependencies
+	}
+	seen[message.Desc.FullName()] = true
 	for _, field := range message.Fields {
 		if field.Desc.Kind() == protoreflect.MessageKind {
-			if field.Desc.Message().IsMapEntry() {
+			if field.Desc.Message().IsMapEntry() && types.of(field.Desc).Map == MapTypeHashMap {
 				dependencies.Maps = true
 			}
-			if field.Desc.Message().Fields().Len() > 0 {
-				dependencies = traverseFields(field.Message, dependencies)
-			}
+			dependencies = traverseFields(field.Message, dependencies, types, seen)
 		}
 	}
 	return dependencies
@@ -0,0 +1,122 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import (
+	"os/exec"
+	"strings"
+	"testing"
+
+	"google.golang.org/protobuf/compiler/protogen"
+	"google.golang.org/protobuf/proto"
+	"google.golang.org/protobuf/types/descriptorpb"
+	"google.golang.org/protobuf/types/pluginpb"
+)
+
+func testField(name string, number int32, label descriptorpb.FieldDescriptorProto_Label, typeName string) *descriptorpb.FieldDescriptorProto {
+	field := &descriptorpb.FieldDescriptorProto{
+		Name:     proto.String(name),
+		JsonName: proto.String(name),
+		Number:   proto.Int32(number),
+		Label:    label.Enum(),
+		Type:     descriptorpb.FieldDescriptorProto_TYPE_STRING.Enum(),
+	}
+	if typeName != "" {
+		field.Type = descriptorpb.FieldDescriptorProto_TYPE_MESSAGE.Enum()
+		field.TypeName = proto.String(typeName)
+	}
+	return field
+}
+
+// recursionRequest holds the recursive messages of examples/tests/tests.proto.
+func recursionRequest() *pluginpb.CodeGeneratorRequest {
+	optional := descriptorpb.FieldDescriptorProto_LABEL_OPTIONAL
+	repeated := descriptorpb.FieldDescriptorProto_LABEL_REPEATED
+	negate := testField("negate", 2, optional, ".Expr")
+	negate.OneofIndex = proto.Int32(0)
+	literal := testField("literal", 1, optional, "")
+	literal.Type = descriptorpb.FieldDescriptorProto_TYPE_UINT64.Enum()
+	literal.OneofIndex = proto.Int32(0)
+
+	file := &descriptorpb.FileDescriptorProto{
+		Name:    proto.String("recursion.proto"),
+		Syntax:  proto.String("proto3"),
+		Options: &descriptorpb.FileOptions{GoPackage: proto.String("github.com/loopholelabs/polyglot-rs/recursion")},
+		MessageType: []*descriptorpb.DescriptorProto{
+			{
+				Name: proto.String("Node"),
+				Field: []*descriptorpb.FieldDescriptorProto{
+					testField("name", 1, optional, ""),
+					testField("children", 2, repeated, ".Node"),
+					testField("parent", 3, optional, ".Node"),
+				},
+			},
+			{
+				Name: proto.String("Ping"),
+				Field: []*descriptorpb.FieldDescriptorProto{
+					testField("label", 1, optional, ""),
+					testField("pong", 2, optional, ".Pong"),
+				},
+			},
+			{
+				Name: proto.String("Pong"),
+				Field: []*descriptorpb.FieldDescriptorProto{
+					testField("label", 1, optional, ""),
+					testField("ping", 2, optional, ".Ping"),
+				},
+			},
+			{
+				Name:      proto.String("Expr"),
+				Field:     []*descriptorpb.FieldDescriptorProto{literal, negate},
+				OneofDecl: []*descriptorpb.OneofDescriptorProto{{Name: proto.String("kind")}},
+			},
+		},
+	}
+	return &pluginpb.CodeGeneratorRequest{
+		FileToGenerate: []string{file.GetName()},
+		ProtoFile:      []*descriptorpb.FileDescriptorProto{file},
+	}
+}
+
+func TestDependencyAnalysisRecursion(t *testing.T) {
+	plugin, err := protogen.Options{}.New(recursionRequest())
+	if err != nil {
+		t.Fatal(err)
+	}
+	dependencies := DependencyAnalysis(plugin.Files[0], fieldTypes{Map: MapTypeHashMap})
+	if dependencies.Maps || dependencies.Enums || dependencies.Services {
+		t.Fatalf("unexpected dependencies %+v", dependencies)
+	}
+}
+
+func TestGenerateRecursion(t *testing.T) {
+	if _, err := exec.LookPath("rustfmt"); err != nil {
+		t.Skip("rustfmt is not installed")
+	}
+	res, err := New().Generate(recursionRequest())
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(res.File) != 1 {
+		t.Fatalf("expected one generated file, got %d", len(res.File))
+	}
+	for _, name := range []string{"pub struct Node", "pub struct Ping", "pub struct Pong", "pub struct Expr"} {
+		if !strings.Contains(res.File[0].GetContent(), name) {
+			t.Errorf("generated file is missing %q", name)
+		}
+	}
+}
@@ -0,0 +1,320 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import (
+	"fmt"
+	"path"
+	"strings"
+
+	"github.com/loopholelabs/polyglot-rs/pkg/utils"
+	"google.golang.org/protobuf/reflect/protoreflect"
+)
+
+// keywords can't be used as identifiers, including the reserved ones.
+var keywords = map[string]bool{
+	"abstract": true, "as": true, "async": true, "await": true, "become": true, "box": true,
+	"break": true, "const": true, "continue": true, "crate": true, "do": true, "dyn": true,
+	"else": true, "enum": true, "extern": true, "false": true, "final": true, "fn": true,
+	"for": true, "gen": true, "if": true, "impl": true, "in": true, "let": true, "loop": true,
+	"macro": true, "match": true, "mod": true, "move": true, "mut": true, "override": true,
+	"priv": true, "pub": true, "ref": true, "return": true, "self": true, "Self": true,
+	"static": true, "struct": true, "super": true, "trait": true, "true": true, "try": true,
+	"type": true, "typeof": true, "unsafe": true, "unsized": true, "use": true,
+	"virtual": true, "where": true, "while": true, "yield": true,
+}
+
+// reserved are the names the generated code relies on in every module, either
+// through its imports, the traits it defines or the prelude, which generated
+// types can't shadow.
+var reserved = []string{
+	"Encode", "Decode", "Cursor", "DecodingError", "Encoder", "Decoder", "DecoderBuffer", "Kind",
+	"HashMap", "TryFromPrimitive", "TryFrom", "FromPrimitive", "IntoPrimitive", "Future", "Handler",
+	"RpcError", "Transport", "Box", "Default", "From", "Into", "Option", "Result", "Send",
+	"String", "Sync", "Vec", "std", "num_enum", "polyglot_rs", "bytes", "indexmap", "smol_str",
+}
+
+// methods are the methods of generated messages, including the ones they get
+// from Encode and Decode, which accessors can't be named after.
+var methods = []string{
+	"new", "builder", "encode", "encode_into", "encode_to_vec", "encode_interned_to_vec",
+	"encode_compressed_to_vec", "decode", "decode_with_depth", "decode_from_slice",
+	"decode_compressed_from_slice", "decode_from_reader", "decode_into", "decode_into_with_depth",
+}
+
+// rustIdent escapes keywords as raw identifiers. `self`, `Self`, `super` and
+// `crate` can't be raw identifiers and get a trailing underscore instead.
+func rustIdent(name string) string {
+	switch {
+	case name == "self" || name == "Self" || name == "super" || name == "crate":
+		return name + "_"
+	case keywords[name]:
+		return "r#" + name
+	default:
+		return name
+	}
+}
+
+// unescape reverses rustIdent, for names that get a prefix or suffix added.
+func unescape(ident string) string {
+	switch ident {
+	case "self_", "Self_", "super_", "crate_":
+		return strings.TrimSuffix(ident, "_")
+	default:
+		return strings.TrimPrefix(ident, "r#")
+	}
+}
+
+// scope holds the names taken in a Rust module or impl block. Names are claimed
+// in declaration order, and a name that is already taken gets the lowest number
+// that makes it unique appended, so the same input always produces the same
+// identifiers.
+type scope map[string]bool
+
+func newScope(reserved ...string) scope {
+	s := scope{}
+	for _, name := range reserved {
+		s[name] = true
+	}
+	return s
+}
+
+// claim claims name, or name followed by separator and a number, such that
+// every name produced by forms for it is free, and returns it.
+func (s scope) claim(name string, separator string, forms func(string) []string) string {
+	candidate := name
+	for n := 2; s.anyUsed(forms(candidate)); n++ {
+		candidate = fmt.Sprintf("%s%s%d", name, separator, n)
+	}
+	for _, form := range forms(candidate) {
+		s[form] = true
+	}
+	return candidate
+}
+
+func (s scope) anyUsed(names []string) bool {
+	for _, name := range names {
+		if s[name] {
+			return true
+		}
+	}
+	return false
+}
+
+func suffixed(suffixes ...string) func(string) []string {
+	return func(name string) []string {
+		var forms []string
+		for _, suffix := range suffixes {
+			forms = append(forms, name+suffix)
+		}
+		return forms
+	}
+}
+
+func memberForms(name string) []string {
+	return []string{name, name + "_mut", "set_" + name}
+}
+
+type typeName struct {
+	Module []string
+	Ident  string
+}
+
+// names holds the Rust names of everything generated for a file and the files it
+// imports. Nested types live in a module named after their message, and each
+// file lives in modules mirroring its package, inside the module the file is
+// included as. The module being generated is tracked while the templates run so
+// that types can be referred to by relative paths.
+type names struct {
+	// types holds every message, enum, oneof and service by full name, along
+	// with its module path starting with the module of its file.
+	types map[protoreflect.FullName]typeName
+	// modules holds the module with the nested types and oneofs of a message.
+	modules map[protoreflect.FullName]string
+	// members holds the struct field of every field and oneof by full name.
+	members map[protoreflect.FullName]string
+	// variants holds the variant of every oneof member by full name.
+	variants map[protoreflect.FullName]string
+	scope    []string
+}
+
+// fileModule returns the module a generated file is included as, named after the
+// file. Generated files refer to types from the files they import through these
+// modules, so they have to be included side by side.
+func fileModule(file protoreflect.FileDescriptor) string {
+	stem := strings.TrimSuffix(path.Base(file.Path()), path.Ext(file.Path()))
+	module := []byte(stem)
+	for i, c := range module {
+		if !('a' <= c && c <= 'z' || 'A' <= c && c <= 'Z' || '0' <= c && c <= '9') {
+			module[i] = '_'
+		}
+	}
+	if len(module) > 0 && '0' <= module[0] && module[0] <= '9' {
+		module = append([]byte{'_'}, module...)
+	}
+	return rustIdent(utils.SnakeCase(string(module)))
+}
+
+// packageModules returns the modules a file's types are declared in.
+func packageModules(file protoreflect.FileDescriptor) []string {
+	var modules []string
+	for _, part := range strings.Split(string(file.Package()), ".") {
+		if part != "" {
+			modules = append(modules, rustIdent(utils.SnakeCase(part)))
+		}
+	}
+	return modules
+}
+
+func newNames(file protoreflect.FileDescriptor) *names {
+	n := &names{
+		types:    map[protoreflect.FullName]typeName{},
+		modules:  map[protoreflect.FullName]string{},
+		members:  map[protoreflect.FullName]string{},
+		variants: map[protoreflect.FullName]string{},
+		scope:    []string{fileModule(file)},
+	}
+	imports := []protoreflect.FileDescriptor{file}
+	seen := map[string]bool{file.Path(): true}
+	for len(imports) > 0 {
+		f := imports[len(imports)-1]
+		imports = imports[:len(imports)-1]
+		n.addFile(f)
+		for i := 0; i < f.Imports().Len(); i++ {
+			if imported := f.Imports().Get(i).FileDescriptor; !seen[imported.Path()] {
+				seen[imported.Path()] = true
+				imports = append(imports, imported)
+			}
+		}
+	}
+	return n
+}
+
+func (n *names) addFile(file protoreflect.FileDescriptor) {
+	module := append([]string{fileModule(file)}, packageModules(file)...)
+	s := newScope(reserved...)
+	for i := 0; i < file.Enums().Len(); i++ {
+		e := file.Enums().Get(i)
+		n.addType(s, module, e.FullName(), string(e.Name()), suffixed(""))
+	}
+	for i := 0; i < file.Messages().Len(); i++ {
+		n.addMessage(s, module, file.Messages().Get(i))
+	}
+	for i := 0; i < file.Services().Len(); i++ {
+		service := file.Services().Get(i)
+		n.addType(s, module, service.FullName(), string(service.Name()), suffixed("", "Method", "Client", "Server"))
+	}
+}
+
+func (n *names) addType(s scope, module []string, fullName protoreflect.FullName, name string, forms func(string) []string) {
+	ident := s.claim(utils.CamelCase(name), "", forms)
+	n.types[fullName] = typeName{Module: module, Ident: rustIdent(ident)}
+}
+
+func (n *names) addMessage(s scope, module []string, message protoreflect.MessageDescriptor) {
+	n.addType(s, module, message.FullName(), string(message.Name()), suffixed("", "Builder"))
+
+	members := newScope(methods...)
+	for i := 0; i < message.Fields().Len(); i++ {
+		field := message.Fields().Get(i)
+		if !isOneofField(field) {
+			n.members[field.FullName()] = members.claim(utils.SnakeCaseName(field.Name()), "_", memberForms)
+		}
+	}
+	oneofs := getOneofs(message)
+	for _, oneof := range oneofs {
+		n.members[oneof.FullName()] = members.claim(utils.SnakeCaseName(oneof.Name()), "_", memberForms)
+	}
+
+	var nested []protoreflect.MessageDescriptor
+	for i := 0; i < message.Messages().Len(); i++ {
+		if m := message.Messages().Get(i); !m.IsMapEntry() {
+			nested = append(nested, m)
+		}
+	}
+	if len(nested) == 0 && message.Enums().Len() == 0 && len(oneofs) == 0 {
+		return
+	}
+	name := rustIdent(s.claim(utils.SnakeCaseName(message.Name()), "_", suffixed("")))
+	n.modules[message.FullName()] = name
+	module = append(append([]string{}, module...), name)
+
+	inner := newScope(reserved...)
+	for i := 0; i < message.Enums().Len(); i++ {
+		e := message.Enums().Get(i)
+		n.addType(inner, module, e.FullName(), string(e.Name()), suffixed(""))
+	}
+	for _, m := range nested {
+		n.addMessage(inner, module, m)
+	}
+	for _, oneof := range oneofs {
+		n.addType(inner, module, oneof.FullName(), string(oneof.Name()), suffixed(""))
+		variants := newScope()
+		for _, field := range getOneofFields(oneof) {
+			n.variants[field.FullName()] = rustIdent(variants.claim(utils.CamelCaseName(field.Name()), "", suffixed("")))
+		}
+	}
+}
+
+// ident returns the identifier a type is declared with. Types from other files
+// keep the flattened name of their full name.
+func (n *names) ident(fullName protoreflect.FullName) string {
+	if t, ok := n.types[fullName]; ok {
+		return t.Ident
+	}
+	return utils.CamelCaseFullName(fullName)
+}
+
+// path returns the path to a type from the module being generated.
+func (n *names) path(fullName protoreflect.FullName) string {
+	t, ok := n.types[fullName]
+	if !ok {
+		return utils.CamelCaseFullName(fullName)
+	}
+	common := 0
+	for common < len(t.Module) && common < len(n.scope) && t.Module[common] == n.scope[common] {
+		common++
+	}
+	var path []string
+	for i := common; i < len(n.scope); i++ {
+		path = append(path, "super")
+	}
+	path = append(path, t.Module[common:]...)
+	return strings.Join(append(path, t.Ident), "::")
+}
+
+// member returns the struct field for a field or oneof, before escaping, so
+// that it can also be used to build other names.
+func (n *names) member(fullName protoreflect.FullName) string {
+	return n.members[fullName]
+}
+
+func (n *names) variant(field protoreflect.FieldDescriptor) string {
+	return n.variants[field.FullName()]
+}
+
+// openModule starts the module with the nested types and oneofs of a message,
+// or the module of a package.
+func (n *names) openModule(module string) string {
+	n.scope = append(n.scope, module)
+	return fmt.Sprintf("pub mod %s {\n#[allow(unused_imports)]\nuse super::*;\n", module)
+}
+
+func (n *names) closeModule() string {
+	n.scope = n.scope[:len(n.scope)-1]
+	return "}"
+}
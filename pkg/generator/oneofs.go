@@ -0,0 +1,46 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import "google.golang.org/protobuf/reflect/protoreflect"
+
+// isOneofField reports whether a field belongs to a real (non-synthetic) oneof,
+// in which case it is generated as a variant of the oneof's enum instead of as a
+// struct field.
+func isOneofField(field protoreflect.FieldDescriptor) bool {
+	oneof := field.ContainingOneof()
+	return oneof != nil && !oneof.IsSynthetic()
+}
+
+func getOneofs(message protoreflect.MessageDescriptor) []protoreflect.OneofDescriptor {
+	var oneofs []protoreflect.OneofDescriptor
+	for i := 0; i < message.Oneofs().Len(); i++ {
+		oneof := message.Oneofs().Get(i)
+		if !oneof.IsSynthetic() {
+			oneofs = append(oneofs, oneof)
+		}
+	}
+	return oneofs
+}
+
+func getOneofFields(oneof protoreflect.OneofDescriptor) []protoreflect.FieldDescriptor {
+	var fields []protoreflect.FieldDescriptor
+	for i := 0; i < oneof.Fields().Len(); i++ {
+		fields = append(fields, oneof.Fields().Get(i))
+	}
+	return fields
+}
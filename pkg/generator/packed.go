@@ -0,0 +1,104 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import (
+	"google.golang.org/protobuf/encoding/protowire"
+	"google.golang.org/protobuf/reflect/protoreflect"
+	"google.golang.org/protobuf/types/descriptorpb"
+)
+
+// arrayEncodingOption is the field number of the array_encoding field option
+// declared in polyglot/options.proto.
+const arrayEncodingOption protowire.Number = 50103
+
+// The values of the ArrayEncoding enum declared in polyglot/options.proto.
+const (
+	arrayUnpacked  = 0
+	arrayPacked    = 1
+	arrayDelta     = 2
+	arrayRunLength = 3
+)
+
+// packedSuffix returns the suffix of the bulk encoder and decoder methods of a
+// repeated number or bool, which decode arrays whichever way they were written, or an
+// empty string for any other field.
+func packedSuffix(field protoreflect.FieldDescriptor) string {
+	if field.Cardinality() != protoreflect.Repeated || field.IsMap() {
+		return ""
+	}
+	switch field.Kind() {
+	case protoreflect.Int32Kind, protoreflect.Sint32Kind, protoreflect.Sfixed32Kind:
+		return "i32"
+	case protoreflect.Uint32Kind, protoreflect.Fixed32Kind:
+		return "u32"
+	case protoreflect.Int64Kind, protoreflect.Sint64Kind, protoreflect.Sfixed64Kind:
+		return "i64"
+	case protoreflect.Uint64Kind, protoreflect.Fixed64Kind:
+		return "u64"
+	case protoreflect.FloatKind:
+		return "f32"
+	case protoreflect.DoubleKind:
+		return "f64"
+	case protoreflect.BoolKind:
+		return "bool"
+	}
+	return ""
+}
+
+// arrayEncoder returns the encoder method of a repeated number whose
+// array_encoding option is set, or an empty string for a field written with the
+// kind of every element. Repeated numbers and bools can be packed, only
+// numbers can be run-length encoded, and only 64-bit integers can be delta
+// encoded.
+func arrayEncoder(field protoreflect.FieldDescriptor) string {
+	options, ok := field.Options().(*descriptorpb.FieldOptions)
+	if !ok || options == nil {
+		return ""
+	}
+	encoding := uint64(arrayUnpacked)
+	unknown := options.ProtoReflect().GetUnknown()
+	for len(unknown) > 0 {
+		number, typ, n := protowire.ConsumeTag(unknown)
+		if n < 0 {
+			break
+		}
+		unknown = unknown[n:]
+		n = protowire.ConsumeFieldValue(number, typ, unknown)
+		if n < 0 {
+			break
+		}
+		if number == arrayEncodingOption && typ == protowire.VarintType {
+			encoding, _ = protowire.ConsumeVarint(unknown)
+		}
+		unknown = unknown[n:]
+	}
+	suffix := packedSuffix(field)
+	switch {
+	case encoding == arrayUnpacked:
+		return ""
+	case suffix == "":
+		panic(errInvalidOption)
+	case encoding == arrayPacked:
+		return "encode_" + suffix + "_slice"
+	case encoding == arrayDelta && (suffix == "i64" || suffix == "u64"):
+		return "encode_" + suffix + "_delta_slice"
+	case encoding == arrayRunLength && suffix != "bool":
+		return "encode_" + suffix + "_run_length_slice"
+	}
+	panic(errInvalidOption)
+}
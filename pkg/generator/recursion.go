@@ -0,0 +1,50 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import "google.golang.org/protobuf/reflect/protoreflect"
+
+// isRecursive reports whether a singular message field makes its message contain
+// itself, directly or through other messages embedded by value. Such fields are
+// boxed so that the generated struct has a known size. Repeated and map fields
+// already live on the heap.
+func isRecursive(field protoreflect.FieldDescriptor) bool {
+	if field.Kind() != protoreflect.MessageKind || field.Cardinality() == protoreflect.Repeated {
+		return false
+	}
+	target := field.ContainingMessage().FullName()
+	seen := map[protoreflect.FullName]bool{}
+	stack := []protoreflect.MessageDescriptor{field.Message()}
+	for len(stack) > 0 {
+		message := stack[len(stack)-1]
+		stack = stack[:len(stack)-1]
+		if message.FullName() == target {
+			return true
+		}
+		if seen[message.FullName()] {
+			continue
+		}
+		seen[message.FullName()] = true
+		for i := 0; i < message.Fields().Len(); i++ {
+			f := message.Fields().Get(i)
+			if f.Kind() == protoreflect.MessageKind && f.Cardinality() != protoreflect.Repeated {
+				stack = append(stack, f.Message())
+			}
+		}
+	}
+	return false
+}
@@ -0,0 +1,58 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import (
+	"github.com/loopholelabs/polyglot-rs/pkg/utils"
+	"google.golang.org/protobuf/reflect/protoreflect"
+)
+
+// unaryMethod is a method of a service along with its ID, its variant of the
+// method enum and its function on the trait and the client, which can't be the
+// client's `new`.
+type unaryMethod struct {
+	protoreflect.MethodDescriptor
+	ID       int
+	Variant  string
+	Function string
+}
+
+// getUnaryMethods returns the methods of a service that neither stream requests
+// nor responses, which are the only ones the generated services support. IDs are
+// indexes among all the methods of the service, streaming ones included, so that
+// adding or removing a streaming method doesn't renumber the others.
+func getUnaryMethods(service protoreflect.ServiceDescriptor) []unaryMethod {
+	var methods []unaryMethod
+	variants := newScope()
+	functions := newScope("new")
+	for i := 0; i < service.Methods().Len(); i++ {
+		method := service.Methods().Get(i)
+		if isUnary(method) {
+			methods = append(methods, unaryMethod{
+				MethodDescriptor: method,
+				ID:               i,
+				Variant:          rustIdent(variants.claim(utils.CamelCaseName(method.Name()), "", suffixed(""))),
+				Function:         rustIdent(functions.claim(utils.SnakeCaseName(method.Name()), "_", suffixed(""))),
+			})
+		}
+	}
+	return methods
+}
+
+func isUnary(method protoreflect.MethodDescriptor) bool {
+	return !method.IsStreamingClient() && !method.IsStreamingServer()
+}
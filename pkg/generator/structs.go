@@ -19,7 +19,6 @@ package generator
 import (
 	"errors"
 	"fmt"
-	"github.com/loopholelabs/polyglot-rs/pkg/utils"
 	"google.golang.org/protobuf/reflect/protoreflect"
 )
 
@@ -105,27 +104,37 @@ var (
 	}
 )
 
-func findValue(field protoreflect.FieldDescriptor) string {
-	if kind, ok := typeLUT[field.Kind()]; !ok {
+func (n *names) findValue(field protoreflect.FieldDescriptor, types fieldTypes) string {
+	if kind := types.of(field).rustType(field.Kind()); kind == "" {
 		switch field.Kind() {
 		case protoreflect.EnumKind:
 			switch field.Cardinality() {
 			case protoreflect.Optional, protoreflect.Required:
-				return utils.CamelCase(string(field.Enum().FullName()))
+				if field.HasOptionalKeyword() {
+					return fmt.Sprintf("Option<%s>", n.path(field.Enum().FullName()))
+				}
+				return n.path(field.Enum().FullName())
 			case protoreflect.Repeated:
-				return fmt.Sprintf("Vec<%s>", utils.CamelCase(string(field.Enum().FullName())))
+				return fmt.Sprintf("Vec<%s>", n.path(field.Enum().FullName()))
 			default:
 				panic(errUnknownCardinality)
 			}
 		case protoreflect.MessageKind:
 			if field.IsMap() {
-				return fmt.Sprintf("HashMap<%s, %s>", findValue(field.MapKey()), findValue(field.MapValue()))
+				return fmt.Sprintf("%s<%s, %s>", types.of(field).mapType(), n.findValue(field.MapKey(), types), n.findValue(field.MapValue(), types))
 			} else {
 				switch field.Cardinality() {
 				case protoreflect.Optional, protoreflect.Required:
-					return utils.CamelCase(string(field.Message().FullName()))
+					value := n.path(field.Message().FullName())
+					if isRecursive(field) {
+						value = fmt.Sprintf("Box<%s>", value)
+					}
+					if field.ContainingOneof() != nil || field.ContainingMessage().IsMapEntry() {
+						return value
+					}
+					return fmt.Sprintf("Option<%s>", value)
 				case protoreflect.Repeated:
-					return fmt.Sprintf("Vec<%s>", utils.CamelCase(string(field.Message().FullName())))
+					return fmt.Sprintf("Vec<%s>", n.path(field.Message().FullName()))
 				default:
 					panic(errUnknownCardinality)
 				}
@@ -136,47 +145,81 @@ func findValue(field protoreflect.FieldDescriptor) string {
 	} else {
 		if field.Cardinality() == protoreflect.Repeated {
 			kind = "Vec<" + kind + ">"
+		} else if field.HasOptionalKeyword() {
+			kind = "Option<" + kind + ">"
 		}
 		return kind
 	}
 }
 
+// encodingValues is either a chain of encoder calls for consecutive required
+// values, or a single optional value that is encoded as `None` when absent.
+type encodingValues struct {
+	Chain    []string
+	Nullable string
+	Encoder  string
+}
+
 type encodingFields struct {
 	MessageFields []protoreflect.FieldDescriptor
 	SliceFields   []protoreflect.FieldDescriptor
-	Values        []string
+	Values        []encodingValues
 }
 
-func getEncodingFields(fields protoreflect.FieldDescriptors) encodingFields {
+func (n *names) getEncodingFields(fields protoreflect.FieldDescriptors, openEnums bool, types fieldTypes) encodingFields {
+	return n.encodingFieldsOf(fieldList(fields), openEnums, types)
+}
+
+func (n *names) encodingFieldsOf(fields []protoreflect.FieldDescriptor, openEnums bool, types fieldTypes) encodingFields {
 	var messageFields []protoreflect.FieldDescriptor
 	var sliceFields []protoreflect.FieldDescriptor
-	var values []string
+	var values []encodingValues
+	var chain []string
 
-	for i := 0; i < fields.Len(); i++ {
-		field := fields.Get(i)
+	for _, field := range fields {
+		if isOneofField(field) {
+			continue
+		}
 		if field.Cardinality() == protoreflect.Repeated && !field.IsMap() {
 			sliceFields = append(sliceFields, field)
 		} else {
-			if encoder, ok := encodeLUT[field.Kind()]; !ok {
+			if encoder := types.of(field).encoder(field.Kind()); encoder == "" {
 				switch field.Kind() {
 				case protoreflect.MessageKind:
 					messageFields = append(messageFields, field)
 				default:
 					panic(errUnknownKind)
 				}
+			} else if name := rustIdent(n.member(field.FullName())); field.HasOptionalKeyword() {
+				if chain != nil {
+					values = append(values, encodingValues{Chain: chain})
+					chain = nil
+				}
+				var value string
+				if field.Kind() == protoreflect.EnumKind {
+					value = fmt.Sprintf("%s(%s)", encoder, enumToU32("*v", openEnums))
+				} else if field.Kind() == protoreflect.StringKind || field.Kind() == protoreflect.BytesKind {
+					value = fmt.Sprintf("%s(v)", encoder)
+				} else {
+					value = fmt.Sprintf("%s(*v)", encoder)
+				}
+				values = append(values, encodingValues{Nullable: name, Encoder: value})
 			} else {
 				if field.Kind() == protoreflect.EnumKind {
-					values = append(values, fmt.Sprintf("%s(self.%s  as u32)", encoder, utils.SnakeCaseName(field.Name())))
+					chain = append(chain, fmt.Sprintf("%s(%s)", encoder, enumToU32("self."+name, openEnums)))
 				} else if field.Kind() == protoreflect.StringKind {
-					values = append(values, fmt.Sprintf("%s(&self.%s)", encoder, utils.SnakeCaseName(field.Name())))
+					chain = append(chain, fmt.Sprintf("%s(&self.%s)", encoder, name))
 				} else if field.Kind() == protoreflect.BytesKind {
-					values = append(values, fmt.Sprintf("%s(&self.%s)", encoder, utils.SnakeCaseName(field.Name())))
+					chain = append(chain, fmt.Sprintf("%s(&self.%s)", encoder, name))
 				} else {
-					values = append(values, fmt.Sprintf("%s(self.%s)", encoder, utils.SnakeCaseName(field.Name())))
+					chain = append(chain, fmt.Sprintf("%s(self.%s)", encoder, name))
 				}
 			}
 		}
 	}
+	if chain != nil {
+		values = append(values, encodingValues{Chain: chain})
+	}
 	return encodingFields{
 		MessageFields: messageFields,
 		SliceFields:   sliceFields,
@@ -191,12 +234,18 @@ type decodingFields struct {
 }
 
 func getDecodingFields(fields protoreflect.FieldDescriptors) decodingFields {
+	return decodingFieldsOf(fieldList(fields))
+}
+
+func decodingFieldsOf(fields []protoreflect.FieldDescriptor) decodingFields {
 	var messageFields []protoreflect.FieldDescriptor
 	var sliceFields []protoreflect.FieldDescriptor
 	var other []protoreflect.FieldDescriptor
 
-	for i := 0; i < fields.Len(); i++ {
-		field := fields.Get(i)
+	for _, field := range fields {
+		if isOneofField(field) {
+			continue
+		}
 		if field.Cardinality() == protoreflect.Repeated && !field.IsMap() {
 			sliceFields = append(sliceFields, field)
 		} else {
@@ -0,0 +1,178 @@
+/*
+	Copyright 2022 Loophole Labs
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		   http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+package generator
+
+import (
+	"errors"
+	"fmt"
+
+	"google.golang.org/protobuf/encoding/protowire"
+	"google.golang.org/protobuf/reflect/protoreflect"
+	"google.golang.org/protobuf/types/descriptorpb"
+)
+
+// Rust types that maps, bytes and strings can be generated as, set with the
+// map_type, bytes_type and string_type options.
+const (
+	MapTypeHashMap  = "hash_map"
+	MapTypeBTreeMap = "btree_map"
+	MapTypeIndexMap = "index_map"
+
+	BytesTypeVec   = "vec"
+	BytesTypeBytes = "bytes"
+
+	StringTypeString  = "string"
+	StringTypeArcStr  = "arc_str"
+	StringTypeSmolStr = "smol_str"
+)
+
+// Field numbers of the options declared in polyglot/options.proto, which are
+// read from the unknown fields of the field options since the plugin doesn't
+// register them.
+const (
+	mapTypeOption    protowire.Number = 50100
+	bytesTypeOption  protowire.Number = 50101
+	stringTypeOption protowire.Number = 50102
+)
+
+var errInvalidOption = errors.New("invalid value for a polyglot option")
+
+// The types, indexed by the values of the enums in polyglot/options.proto.
+var (
+	mapTypes    = []string{MapTypeHashMap, MapTypeBTreeMap, MapTypeIndexMap}
+	bytesTypes  = []string{BytesTypeVec, BytesTypeBytes}
+	stringTypes = []string{StringTypeString, StringTypeArcStr, StringTypeSmolStr}
+)
+
+// fieldTypes are the Rust types the maps, bytes and strings of a field are
+// generated as.
+type fieldTypes struct {
+	Map    string
+	Bytes  string
+	String string
+}
+
+func optionType(types []string, value uint64) string {
+	if value >= uint64(len(types)) {
+		panic(errInvalidOption)
+	}
+	return types[value]
+}
+
+// of returns the types of field, which are t overridden by the options of the
+// field. The keys and values of a map take the options of the map field.
+func (t fieldTypes) of(field protoreflect.FieldDescriptor) fieldTypes {
+	if entry := field.ContainingMessage(); entry != nil && entry.IsMapEntry() {
+		if parent, ok := entry.Parent().(protoreflect.MessageDescriptor); ok {
+			for i := 0; i < parent.Fields().Len(); i++ {
+				if parent.Fields().Get(i).Message() == entry {
+					field = parent.Fields().Get(i)
+				}
+			}
+		}
+	}
+
+	options, ok := field.Options().(*descriptorpb.FieldOptions)
+	if !ok || options == nil {
+		return t
+	}
+	unknown := options.ProtoReflect().GetUnknown()
+	for len(unknown) > 0 {
+		number, typ, n := protowire.ConsumeTag(unknown)
+		if n < 0 {
+			break
+		}
+		unknown = unknown[n:]
+		n = protowire.ConsumeFieldValue(number, typ, unknown)
+		if n < 0 {
+			break
+		}
+		if typ == protowire.VarintType {
+			value, _ := protowire.ConsumeVarint(unknown)
+			switch number {
+			case mapTypeOption:
+				t.Map = optionType(mapTypes, value)
+			case bytesTypeOption:
+				t.Bytes = optionType(bytesTypes, value)
+			case stringTypeOption:
+				t.String = optionType(stringTypes, value)
+			}
+		}
+		unknown = unknown[n:]
+	}
+	return t
+}
+
+// rustType returns the type of a scalar, or an empty string for enums and
+// messages.
+func (t fieldTypes) rustType(kind protoreflect.Kind) string {
+	switch {
+	case kind == protoreflect.StringKind && t.String == StringTypeArcStr:
+		return "std::sync::Arc<str>"
+	case kind == protoreflect.StringKind && t.String == StringTypeSmolStr:
+		return "smol_str::SmolStr"
+	case kind == protoreflect.BytesKind && t.Bytes == BytesTypeBytes:
+		return "bytes::Bytes"
+	}
+	return typeLUT[kind]
+}
+
+func (t fieldTypes) mapType() string {
+	switch t.Map {
+	case MapTypeBTreeMap:
+		return "std::collections::BTreeMap"
+	case MapTypeIndexMap:
+		return "indexmap::IndexMap"
+	}
+	return "HashMap"
+}
+
+// encoder returns the encoder method for a scalar, which takes a &str for
+// strings that aren't generated as a String.
+func (t fieldTypes) encoder(kind protoreflect.Kind) string {
+	if kind == protoreflect.StringKind && t.String != StringTypeString {
+		return ".encode_str"
+	}
+	return encodeLUT[kind]
+}
+
+// decoder returns the expression decoding a scalar, which for types other than
+// String and Vec<u8> borrows the value from the buffer instead of copying it
+// twice.
+func (t fieldTypes) decoder(kind protoreflect.Kind) string {
+	switch {
+	case kind == protoreflect.StringKind && t.String == StringTypeArcStr:
+		return "std::sync::Arc::from(b.decode_str()?)"
+	case kind == protoreflect.StringKind && t.String == StringTypeSmolStr:
+		return "smol_str::SmolStr::new(b.decode_str()?)"
+	case kind == protoreflect.BytesKind && t.Bytes == BytesTypeBytes:
+		return "bytes::Bytes::copy_from_slice(b.decode_byte_slice()?)"
+	}
+	return fmt.Sprintf("b%s()?", decodeLUT[kind])
+}
+
+// decoderInto returns the decoder method that decodes a scalar into a String
+// or Vec<u8> it already holds, or an empty string for every other type.
+func (t fieldTypes) decoderInto(kind protoreflect.Kind) string {
+	switch {
+	case kind == protoreflect.StringKind && t.String == StringTypeString:
+		return "decode_string_into"
+	case kind == protoreflect.BytesKind && t.Bytes == BytesTypeVec:
+		return "decode_bytes_into"
+	}
+	return ""
+}
@@ -0,0 +1,204 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+// A small subset of the protobuf descriptor model, holding only what the
+// generator needs. Full names never carry a leading dot and include the package.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionValue {
+    Ident(String),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Aggregate(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionDescriptor {
+    pub name: String,
+    pub value: OptionValue,
+}
+
+pub fn find_option<'a>(options: &'a [OptionDescriptor], name: &str) -> Option<&'a OptionValue> {
+    options.iter().find(|o| o.name == name).map(|o| &o.value)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarType {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Bool,
+    String,
+    Bytes,
+}
+
+impl ScalarType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "double" => ScalarType::Double,
+            "float" => ScalarType::Float,
+            "int32" => ScalarType::Int32,
+            "int64" => ScalarType::Int64,
+            "uint32" => ScalarType::Uint32,
+            "uint64" => ScalarType::Uint64,
+            "sint32" => ScalarType::Sint32,
+            "sint64" => ScalarType::Sint64,
+            "fixed32" => ScalarType::Fixed32,
+            "fixed64" => ScalarType::Fixed64,
+            "sfixed32" => ScalarType::Sfixed32,
+            "sfixed64" => ScalarType::Sfixed64,
+            "bool" => ScalarType::Bool,
+            "string" => ScalarType::String,
+            "bytes" => ScalarType::Bytes,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    Scalar(ScalarType),
+    Enum(String),
+    Message(String),
+    Map(Box<FieldType>, Box<FieldType>),
+    /// A type reference that has not been resolved against the pool yet.
+    Named(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    Singular,
+    Optional,
+    Required,
+    Repeated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescriptor {
+    pub name: String,
+    pub number: u32,
+    pub label: Label,
+    pub ty: FieldType,
+    pub oneof: Option<usize>,
+    pub options: Vec<OptionDescriptor>,
+}
+
+impl FieldDescriptor {
+    pub fn is_repeated(&self) -> bool {
+        self.label == Label::Repeated
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self.ty, FieldType::Map(_, _))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageDescriptor {
+    pub name: String,
+    pub full_name: String,
+    pub fields: Vec<FieldDescriptor>,
+    pub oneofs: Vec<String>,
+    pub messages: Vec<MessageDescriptor>,
+    pub enums: Vec<EnumDescriptor>,
+    pub options: Vec<OptionDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValueDescriptor {
+    pub name: String,
+    pub number: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDescriptor {
+    pub name: String,
+    pub full_name: String,
+    pub values: Vec<EnumValueDescriptor>,
+    pub options: Vec<OptionDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodDescriptor {
+    pub name: String,
+    pub input: String,
+    pub output: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+}
+
+impl MethodDescriptor {
+    /// Whether the method neither streams requests nor responses, which are the
+    /// only methods generated services support.
+    pub fn is_unary(&self) -> bool {
+        !self.client_streaming && !self.server_streaming
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceDescriptor {
+    pub name: String,
+    pub full_name: String,
+    pub methods: Vec<MethodDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDescriptor {
+    pub name: String,
+    pub package: String,
+    pub imports: Vec<String>,
+    pub messages: Vec<MessageDescriptor>,
+    pub enums: Vec<EnumDescriptor>,
+    pub services: Vec<ServiceDescriptor>,
+    pub options: Vec<OptionDescriptor>,
+}
+
+impl FileDescriptor {
+    /// Calls `f` for every message in the file, parents before their nested messages.
+    pub fn walk_messages<'a>(&'a self, f: &mut impl FnMut(&'a MessageDescriptor)) {
+        fn walk<'a>(m: &'a MessageDescriptor, f: &mut impl FnMut(&'a MessageDescriptor)) {
+            f(m);
+            for nested in &m.messages {
+                walk(nested, f);
+            }
+        }
+        for m in &self.messages {
+            walk(m, f);
+        }
+    }
+
+    /// Calls `f` for every enum in the file, including enums nested in messages.
+    pub fn walk_enums<'a>(&'a self, f: &mut impl FnMut(&'a EnumDescriptor)) {
+        for e in &self.enums {
+            f(e);
+        }
+        self.walk_messages(&mut |m| {
+            for e in &m.enums {
+                f(e);
+            }
+        });
+    }
+}
@@ -0,0 +1,111 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Reports the changes between two versions of a `.proto` file that break
+//! decoding for readers built from either of them.
+//!
+//! ```text
+//! polyglot-compat [-I dir]... [--root package.Message] old.proto new.proto
+//! ```
+//!
+//! Exits with 0 when the versions are compatible, 1 when there are breaking
+//! changes and 2 when the files can't be loaded.
+
+use polyglot_build::{load, BuildError};
+use polyglot_rs::{check_compatibility, Schema};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "usage: polyglot-compat [-I dir]... [--root package.Message] old.proto new.proto";
+
+fn main() -> ExitCode {
+    let mut includes = Vec::new();
+    let mut root = None;
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-I" | "--include" => match args.next() {
+                Some(dir) => includes.push(PathBuf::from(dir)),
+                None => return usage(),
+            },
+            "--root" => match args.next() {
+                Some(name) => root = Some(name),
+                None => return usage(),
+            },
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ => match arg.strip_prefix("-I") {
+                Some(dir) if !dir.is_empty() => includes.push(PathBuf::from(dir)),
+                _ => files.push(PathBuf::from(arg)),
+            },
+        }
+    }
+    let [old, new] = files.as_slice() else {
+        return usage();
+    };
+
+    let schemas = schema(old, &includes, root.as_deref())
+        .and_then(|old| Ok((old, schema(new, &includes, root.as_deref())?)));
+    let (old, new) = match schemas {
+        Ok(schemas) => schemas,
+        Err(err) => {
+            eprintln!("polyglot-compat: {err}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let changes = check_compatibility(&old, &new);
+    for change in &changes {
+        println!("{change}");
+    }
+    if changes.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{USAGE}");
+    ExitCode::from(2)
+}
+
+/// Loads `proto`, looking its imports up next to it and then in `includes`. The
+/// root defaults to the first message of `proto`.
+fn schema(proto: &Path, includes: &[PathBuf], root: Option<&str>) -> Result<Schema, BuildError> {
+    let parent = match proto.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let includes: Vec<PathBuf> = std::iter::once(parent)
+        .chain(includes.iter().cloned())
+        .collect();
+    let (pool, files) = load(&[proto], &includes)?;
+    let root = match root {
+        Some(root) => root.to_owned(),
+        None => files
+            .first()
+            .and_then(|(name, _)| pool.file(name))
+            .and_then(|file| file.messages.first())
+            .map(|message| message.full_name.clone())
+            .unwrap_or_default(),
+    };
+    Ok(pool.schema(&root))
+}
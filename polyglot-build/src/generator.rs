@@ -0,0 +1,1649 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+// The structure of this module follows the templates in `templates/`: each
+// `generate_*` function corresponds to the template of the same name, and the
+// wire layout of the generated `Encode`/`Decode` impls must stay identical.
+
+use crate::ast::{
+    EnumDescriptor, FieldDescriptor, FieldType, FileDescriptor, Label, MessageDescriptor,
+    ScalarType, ServiceDescriptor,
+};
+use crate::names::{
+    camel_case, file_module, package, rust_ident, snake_case, unescape, Names, Scope,
+};
+use crate::types::{array_encoding, is_delimited, packable, ArrayEncoding, Types};
+use crate::{BytesType, Config, DescriptorPool, MapType, Privacy, StringType};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+struct CodeWriter {
+    buf: String,
+    indent: usize,
+}
+
+impl CodeWriter {
+    fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.buf.push_str("    ");
+            }
+            self.buf.push_str(line);
+        }
+        self.buf.push('\n');
+    }
+
+    fn open(&mut self, line: &str) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(&mut self, line: &str) {
+        self.indent -= 1;
+        self.line(line);
+    }
+}
+
+pub(crate) struct Generator<'a> {
+    config: &'a Config,
+    out: CodeWriter,
+    boxed: HashSet<(String, u32)>,
+    comparable: HashMap<String, Comparable>,
+    names: Names,
+    /// The module code is being generated in, relative to the generated file.
+    scope: Vec<String>,
+}
+
+/// Which of `Eq` and `Hash` can be derived for a message, in addition to the
+/// derives every message gets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Comparable {
+    eq: bool,
+    hash: bool,
+}
+
+impl Comparable {
+    fn and(self, other: Comparable) -> Comparable {
+        Comparable {
+            eq: self.eq && other.eq,
+            hash: self.hash && other.hash,
+        }
+    }
+}
+
+fn scalar_type(scalar: ScalarType, types: Types) -> &'static str {
+    match scalar {
+        ScalarType::Bool => "bool",
+        ScalarType::Int32 | ScalarType::Sint32 | ScalarType::Sfixed32 => "i32",
+        ScalarType::Uint32 | ScalarType::Fixed32 => "u32",
+        ScalarType::Int64 | ScalarType::Sint64 | ScalarType::Sfixed64 => "i64",
+        ScalarType::Uint64 | ScalarType::Fixed64 => "u64",
+        ScalarType::Float => "f32",
+        ScalarType::Double => "f64",
+        ScalarType::String => match types.string {
+            StringType::String => "String",
+            StringType::ArcStr => "std::sync::Arc<str>",
+            StringType::SmolStr => "smol_str::SmolStr",
+        },
+        ScalarType::Bytes => match types.bytes {
+            BytesType::Vec => "Vec<u8>",
+            BytesType::Bytes => "bytes::Bytes",
+        },
+    }
+}
+
+fn map_type(types: Types) -> &'static str {
+    match types.map {
+        MapType::HashMap => "HashMap",
+        MapType::BTreeMap => "std::collections::BTreeMap",
+        MapType::IndexMap => "indexmap::IndexMap",
+    }
+}
+
+fn scalar_suffix(scalar: ScalarType) -> &'static str {
+    match scalar {
+        ScalarType::Bool => "bool",
+        ScalarType::Int32 | ScalarType::Sint32 | ScalarType::Sfixed32 => "i32",
+        ScalarType::Uint32 | ScalarType::Fixed32 => "u32",
+        ScalarType::Int64 | ScalarType::Sint64 | ScalarType::Sfixed64 => "i64",
+        ScalarType::Uint64 | ScalarType::Fixed64 => "u64",
+        ScalarType::Float => "f32",
+        ScalarType::Double => "f64",
+        ScalarType::String => "string",
+        ScalarType::Bytes => "bytes",
+    }
+}
+
+/// The suffix of the bulk encoder and decoder methods of a repeated number or
+/// bool, which decode arrays whichever way they were written.
+fn packed_suffix(field: &FieldDescriptor) -> Option<&'static str> {
+    match field.ty {
+        FieldType::Scalar(scalar) if field.is_repeated() && packable(&field.ty) => {
+            Some(scalar_suffix(scalar))
+        }
+        _ => None,
+    }
+}
+
+/// The suffix of the encoder method for a scalar, which is `str` for strings
+/// that aren't generated as a `String`.
+fn encode_suffix(scalar: ScalarType, types: Types) -> &'static str {
+    match scalar {
+        ScalarType::String if types.string != StringType::String => "str",
+        _ => scalar_suffix(scalar),
+    }
+}
+
+/// Mirrors `kindLUT` and `getKind` in `pkg/generator/structs.go`.
+fn kind(ty: &FieldType) -> &'static str {
+    match ty {
+        FieldType::Scalar(scalar) => match scalar {
+            ScalarType::Bool => "Kind::Bool",
+            ScalarType::Int32 | ScalarType::Sint32 | ScalarType::Sfixed32 => "Kind::I32",
+            ScalarType::Uint32 | ScalarType::Fixed32 => "Kind::U32",
+            ScalarType::Int64 | ScalarType::Sint64 | ScalarType::Sfixed64 => "Kind::I64",
+            ScalarType::Uint64 | ScalarType::Fixed64 => "Kind::U64",
+            ScalarType::Float => "Kind::F32",
+            ScalarType::Double => "Kind::F64",
+            ScalarType::String => "Kind::String",
+            ScalarType::Bytes => "Kind::Bytes",
+        },
+        FieldType::Enum(_) => "Kind::U32",
+        _ => "Kind::Any",
+    }
+}
+
+/// Singular message fields and `optional` scalars are generated as `Option<T>`
+/// and encoded as `None` when absent. Note that when such a field is the first
+/// value on the wire, an absent value can't be told apart from a nil message
+/// unless the message is delimited.
+fn is_nullable(field: &FieldDescriptor) -> bool {
+    match field.ty {
+        FieldType::Message(_) => !field.is_repeated(),
+        FieldType::Map(_, _) => false,
+        _ => field.label == Label::Optional,
+    }
+}
+
+/// A field or oneof of a delimited message.
+#[derive(Clone, Copy)]
+pub(crate) enum Member<'a> {
+    Field(&'a FieldDescriptor),
+    Oneof(usize),
+}
+
+/// The fields and oneofs of a message in the order delimited messages are
+/// written, which is by field number so that new fields go at the end. Oneofs
+/// take the place of their lowest numbered member.
+pub(crate) fn members_by_number(message: &MessageDescriptor) -> Vec<Member<'_>> {
+    let mut members: Vec<(u32, Member)> = plain_fields(message)
+        .map(|f| (f.number, Member::Field(f)))
+        .collect();
+    for index in 0..message.oneofs.len() {
+        let number = oneof_fields(message, index).map(|f| f.number).min();
+        members.push((number.unwrap_or(u32::MAX), Member::Oneof(index)));
+    }
+    members.sort_by_key(|(number, _)| *number);
+    members.into_iter().map(|(_, member)| member).collect()
+}
+
+/// Fields that are not part of a oneof, which are encoded in the usual groups.
+pub(crate) fn plain_fields(message: &MessageDescriptor) -> impl Iterator<Item = &FieldDescriptor> {
+    message.fields.iter().filter(|f| f.oneof.is_none())
+}
+
+/// The members of the oneof at `index`, in declaration order.
+pub(crate) fn oneof_fields(
+    message: &MessageDescriptor,
+    index: usize,
+) -> impl Iterator<Item = &FieldDescriptor> {
+    message
+        .fields
+        .iter()
+        .filter(move |f| f.oneof == Some(index))
+}
+
+/// Whether decoding a value of type `ty` decodes nested messages, and so needs
+/// the remaining `depth` passed along.
+fn needs_depth(ty: &FieldType) -> bool {
+    match ty {
+        FieldType::Message(_) | FieldType::Named(_) => true,
+        FieldType::Map(key, value) => needs_depth(key) || needs_depth(value),
+        _ => false,
+    }
+}
+
+/// Works out which messages can derive `Eq` (no floats anywhere inside them) and
+/// `Hash` (no floats and no maps other than `BTreeMap`), looking through messages
+/// from every file of the pool.
+fn comparable_messages(pool: &DescriptorPool, config: &Config) -> HashMap<String, Comparable> {
+    fn field(ty: &FieldType, types: Types, done: &HashMap<String, Comparable>) -> Comparable {
+        match ty {
+            FieldType::Scalar(ScalarType::Float | ScalarType::Double) => Comparable {
+                eq: false,
+                hash: false,
+            },
+            FieldType::Map(key, value) => Comparable {
+                eq: true,
+                hash: types.map == MapType::BTreeMap,
+            }
+            .and(field(key, types, done))
+            .and(field(value, types, done)),
+            FieldType::Message(name) | FieldType::Named(name) => {
+                done.get(name).copied().unwrap_or(Comparable {
+                    eq: false,
+                    hash: false,
+                })
+            }
+            _ => Comparable {
+                eq: true,
+                hash: true,
+            },
+        }
+    }
+
+    // Start by assuming everything is comparable and drop derives until nothing
+    // changes, so that recursive messages settle on a consistent answer.
+    let mut messages = Vec::new();
+    for file in pool.files() {
+        file.walk_messages(&mut |m| messages.push(m));
+    }
+    let mut done: HashMap<String, Comparable> = messages
+        .iter()
+        .map(|m| {
+            let comparable = Comparable {
+                eq: true,
+                hash: true,
+            };
+            (m.full_name.clone(), comparable)
+        })
+        .collect();
+    loop {
+        let mut changed = false;
+        for m in &messages {
+            let comparable = m.fields.iter().fold(done[&m.full_name], |c, f| {
+                c.and(field(&f.ty, Types::of(config, f), &done))
+            });
+            if comparable != done[&m.full_name] {
+                done.insert(m.full_name.clone(), comparable);
+                changed = true;
+            }
+        }
+        if !changed {
+            return done;
+        }
+    }
+}
+
+/// The contents of the file that includes every generated file as a module named
+/// after it, given each file and the name it was generated to. The module often
+/// shares its name with the package or a message inside it.
+pub(crate) fn include_file(files: &[(&FileDescriptor, String)]) -> String {
+    let mut out = CodeWriter::default();
+    out.line(&format!(
+        "// Code generated by polyglot-rs v{}, DO NOT EDIT.",
+        env!("CARGO_PKG_VERSION")
+    ));
+    for (file, path) in files {
+        let module = file_module(&file.name);
+        out.line("");
+        out.line("#[allow(clippy::module_inception)]");
+        out.open(&format!("pub mod {module} {{"));
+        out.line(&format!("include!({path:?});"));
+        out.close("}");
+    }
+    out.buf
+}
+
+/// Finds the singular message fields (including oneof members) that make a
+/// message contain itself, directly or through other messages, and so must be
+/// boxed for the struct to have a known size. Repeated and map fields already
+/// live on the heap. Returns the full name of the message and the field number.
+fn recursive_fields(file: &FileDescriptor) -> HashSet<(String, u32)> {
+    let mut messages = HashMap::new();
+    file.walk_messages(&mut |m| {
+        messages.insert(m.full_name.as_str(), m);
+    });
+
+    fn embedded(message: &MessageDescriptor) -> impl Iterator<Item = (&FieldDescriptor, &str)> {
+        message.fields.iter().filter_map(|f| match &f.ty {
+            FieldType::Message(name) if !f.is_repeated() => Some((f, name.as_str())),
+            _ => None,
+        })
+    }
+
+    let mut boxed = HashSet::new();
+    for message in messages.values() {
+        for (field, target) in embedded(message) {
+            // Walk everything embedded by value in `target`, looking for `message`.
+            let mut seen = HashSet::new();
+            let mut stack = vec![target];
+            while let Some(name) = stack.pop() {
+                if name == message.full_name {
+                    boxed.insert((message.full_name.clone(), field.number));
+                    break;
+                }
+                if !seen.insert(name) {
+                    continue;
+                }
+                if let Some(m) = messages.get(name) {
+                    stack.extend(embedded(m).map(|(_, target)| target));
+                }
+            }
+        }
+    }
+    boxed
+}
+
+impl<'a> Generator<'a> {
+    pub(crate) fn new(config: &'a Config) -> Self {
+        Generator {
+            config,
+            out: CodeWriter::default(),
+            boxed: HashSet::new(),
+            comparable: HashMap::new(),
+            names: Names::default(),
+            scope: Vec::new(),
+        }
+    }
+
+    pub(crate) fn generate(mut self, pool: &DescriptorPool, file: &FileDescriptor) -> String {
+        self.boxed = recursive_fields(file);
+        self.comparable = comparable_messages(pool, self.config);
+        self.names = Names::new(pool, file);
+        self.generate_headers(file);
+        self.out.line("");
+        self.generate_imports(file);
+        self.out.line("");
+
+        // The file's types live in modules mirroring its package, inside the
+        // module the file itself is included as.
+        self.scope = vec![file_module(&file.name)];
+        let package = package(file);
+        for module in &package {
+            self.open_module(module);
+        }
+        for e in &file.enums {
+            self.generate_enum(e);
+        }
+        for message in &file.messages {
+            self.generate_structs(message);
+        }
+        for service in &file.services {
+            self.generate_service(service);
+        }
+        for _ in &package {
+            self.close_module();
+        }
+        self.out.buf
+    }
+
+    fn generate_headers(&mut self, file: &FileDescriptor) {
+        self.out.line(&format!(
+            "// Code generated by polyglot-rs v{}, DO NOT EDIT.",
+            env!("CARGO_PKG_VERSION")
+        ));
+        self.out.line(&format!("// source: {}", file.name));
+    }
+
+    fn generate_imports(&mut self, file: &FileDescriptor) {
+        let services = !file.services.is_empty();
+        let unary = file
+            .services
+            .iter()
+            .any(|s| s.methods.iter().any(|m| m.is_unary()));
+        let mut enums = false;
+        file.walk_enums(&mut |_| enums = true);
+        let mut maps = false;
+        file.walk_messages(&mut |m| {
+            maps |= m
+                .fields
+                .iter()
+                .any(|f| f.is_map() && self.types(f).map == MapType::HashMap)
+        });
+
+        self.out.line("use std::io::Cursor;");
+        // Files without arrays or maps don't use `Kind`.
+        self.out.line("#[allow(unused_imports)]");
+        self.out
+            .line("use polyglot_rs::{DecodingError, Encoder, Decoder, DecoderBuffer, Kind};");
+        self.out.line("pub use polyglot_rs::{Decode, Encode};");
+        if enums && self.config.open_enums {
+            self.out
+                .line("use num_enum::{FromPrimitive, IntoPrimitive};");
+        }
+        if unary || (enums && !self.config.open_enums) {
+            self.out.line("use num_enum::TryFromPrimitive;");
+            self.out.line("use std::convert::TryFrom;");
+        }
+        if maps {
+            self.out.line("use std::collections::HashMap;");
+        }
+        if unary {
+            self.out.line("use std::future::Future;");
+        }
+        if services {
+            self.out
+                .line("use polyglot_rs::{Handler, RpcError, Transport};");
+        }
+    }
+
+    /// Writes the derives shared by all generated types followed by the ones and
+    /// the attributes added through the `Config`.
+    fn generate_derives(&mut self, derives: &[&str]) {
+        let derives: Vec<&str> = derives
+            .iter()
+            .copied()
+            .chain(self.config.derives.iter().map(String::as_str))
+            .collect();
+        self.out.line(&format!("#[derive({})]", derives.join(", ")));
+        for attribute in &self.config.attributes {
+            self.out.line(attribute);
+        }
+    }
+
+    fn generate_enum(&mut self, e: &EnumDescriptor) {
+        if self.config.open_enums {
+            self.generate_derives(&[
+                "Debug",
+                "Eq",
+                "PartialEq",
+                "FromPrimitive",
+                "IntoPrimitive",
+                "Copy",
+                "Clone",
+                "Hash",
+            ]);
+        } else {
+            self.generate_derives(&[
+                "Debug",
+                "Eq",
+                "PartialEq",
+                "TryFromPrimitive",
+                "Copy",
+                "Clone",
+                "Hash",
+            ]);
+        }
+        let name = self.names.ident(&e.full_name);
+        self.out.line("#[repr(u32)]");
+        self.out.open(&format!("pub enum {name} {{"));
+        // Values are written as their number cast to a `u32`, and each number gets
+        // a single variant. Later values with the same number (`allow_alias`)
+        // become associated constants pointing at it.
+        let mut variants: HashMap<i32, String> = HashMap::new();
+        let mut aliases = Vec::new();
+        for value in &e.values {
+            let ident = rust_ident(&value.name);
+            match variants.get(&value.number) {
+                Some(variant) => aliases.push((ident, variant.clone())),
+                None => {
+                    self.out
+                        .line(&format!("{ident} = {},", value.number as u32));
+                    variants.insert(value.number, ident);
+                }
+            }
+        }
+        if self.config.open_enums {
+            // Values this side doesn't know about are kept, so that they are
+            // written back unchanged.
+            let mut unknown = "Unknown".to_owned();
+            while e.values.iter().any(|v| v.name == unknown) {
+                unknown.push('_');
+            }
+            // Its discriminant is never used, but it has to be one that no
+            // other variant has.
+            let discriminant = (0..=u32::MAX)
+                .find(|n| !variants.keys().any(|v| *v as u32 == *n))
+                .unwrap_or_default();
+            self.out.line("#[num_enum(catch_all)]");
+            self.out.line(&format!("{unknown}(u32) = {discriminant},"));
+        }
+        self.out.close("}");
+        self.out.line("");
+
+        if !aliases.is_empty() {
+            self.out.line("#[allow(non_upper_case_globals)]");
+            self.out.open(&format!("impl {name} {{"));
+            for (alias, variant) in aliases {
+                self.out
+                    .line(&format!("pub const {alias}: {name} = {name}::{variant};"));
+            }
+            self.out.close("}");
+            self.out.line("");
+        }
+
+        // The zero value is the default, falling back to the first value for
+        // proto2 enums that don't have one. This can't be derived with
+        // `#[default]`, which `TryFromPrimitive` would also use for every
+        // unknown value instead of returning an error.
+        let default = e.values.iter().find(|v| v.number == 0).or(e.values.first());
+        if let Some(default) = default {
+            self.out.line("#[allow(clippy::derivable_impls)]");
+            self.out.open(&format!("impl Default for {name} {{"));
+            self.out.open("fn default() -> Self {");
+            self.out
+                .line(&format!("{name}::{}", rust_ident(&default.name)));
+            self.out.close("}");
+            self.out.close("}");
+            self.out.line("");
+        }
+    }
+
+    fn message_derives(&self, message: &MessageDescriptor, default: bool) -> Vec<&'static str> {
+        let mut derives = vec!["Debug", "Clone", "PartialEq"];
+        if default {
+            derives.push("Default");
+        }
+        let comparable = self.comparable[&message.full_name];
+        if comparable.eq {
+            derives.push("Eq");
+        }
+        if comparable.hash {
+            derives.push("Hash");
+        }
+        derives
+    }
+
+    fn open_module(&mut self, module: &str) {
+        self.out.open(&format!("pub mod {module} {{"));
+        self.out.line("#[allow(unused_imports)]");
+        self.out.line("use super::*;");
+        self.out.line("");
+        self.scope.push(module.to_owned());
+    }
+
+    fn close_module(&mut self) {
+        self.scope.pop();
+        if self.out.buf.ends_with("\n\n") {
+            self.out.buf.pop();
+        }
+        self.out.close("}");
+        self.out.line("");
+    }
+
+    fn path(&self, full_name: &str) -> String {
+        self.names.path(full_name, &self.scope)
+    }
+
+    fn oneof_path(&self, message: &MessageDescriptor, index: usize) -> String {
+        self.path(&format!("{}.{}", message.full_name, message.oneofs[index]))
+    }
+
+    /// The struct field for a field or oneof of a message.
+    fn member(&self, message: &MessageDescriptor, name: &str) -> String {
+        rust_ident(self.names.member(message, name))
+    }
+
+    fn types(&self, field: &FieldDescriptor) -> Types {
+        Types::of(self.config, field)
+    }
+
+    /// Mirrors `findValue` in `pkg/generator/structs.go`.
+    fn value_type(&self, ty: &FieldType, types: Types) -> String {
+        match ty {
+            FieldType::Scalar(scalar) => scalar_type(*scalar, types).to_owned(),
+            FieldType::Enum(name) | FieldType::Message(name) | FieldType::Named(name) => {
+                self.path(name)
+            }
+            FieldType::Map(key, value) => {
+                format!(
+                    "{}<{}, {}>",
+                    map_type(types),
+                    self.value_type(key, types),
+                    self.value_type(value, types)
+                )
+            }
+        }
+    }
+
+    fn field_type(&self, field: &FieldDescriptor) -> String {
+        let value = self.value_type(&field.ty, self.types(field));
+        if field.is_repeated() {
+            format!("Vec<{value}>")
+        } else if is_nullable(field) {
+            format!("Option<{value}>")
+        } else {
+            value
+        }
+    }
+
+    fn generate_structs(&mut self, message: &MessageDescriptor) {
+        let visibility = match self.config.privacy {
+            Privacy::Private => "",
+            Privacy::Public => "pub ",
+            Privacy::Crate => "pub(crate) ",
+        };
+        let derives = self.message_derives(message, true);
+        self.generate_derives(&derives);
+        self.out.open(&format!(
+            "pub struct {} {{",
+            self.names.ident(&message.full_name)
+        ));
+        for field in plain_fields(message) {
+            self.out.line(&format!(
+                "{visibility}{}: {},",
+                self.member(message, &field.name),
+                self.boxed_type(message, field, self.field_type(field))
+            ));
+        }
+        for (index, oneof) in message.oneofs.iter().enumerate() {
+            self.out.line(&format!(
+                "{visibility}{}: Option<{}>,",
+                self.member(message, oneof),
+                self.oneof_path(message, index)
+            ));
+        }
+        self.out.close("}");
+        self.out.line("");
+
+        self.generate_accessors(message);
+        self.generate_builder(message);
+        self.generate_conversions(message);
+        self.generate_encode(message);
+        self.generate_decode(message);
+        self.generate_named(message);
+
+        // Nested types and oneofs live in a module named after the message.
+        if let Some(module) = self.names.module(message).map(str::to_owned) {
+            self.open_module(&module);
+            for e in &message.enums {
+                self.generate_enum(e);
+            }
+            for nested in &message.messages {
+                self.generate_structs(nested);
+            }
+            for index in 0..message.oneofs.len() {
+                self.generate_oneof(message, index);
+            }
+            self.close_module();
+        }
+    }
+
+    /// The struct fields of a message as (name, type, getter type, getter body),
+    /// plain fields first and then oneofs, matching the struct declaration.
+    fn members(&self, message: &MessageDescriptor) -> Vec<(String, String, String, String)> {
+        let mut members = Vec::new();
+        for field in plain_fields(message) {
+            let name = self.member(message, &field.name);
+            let ty = self.boxed_type(message, field, self.field_type(field));
+            let value = self.value_type(&field.ty, self.types(field));
+            let (getter, body) = if field.is_repeated() {
+                (format!("&[{value}]"), format!("&self.{name}"))
+            } else if let FieldType::Map(_, _) = field.ty {
+                (format!("&{ty}"), format!("&self.{name}"))
+            } else {
+                let borrowed = match &field.ty {
+                    FieldType::Scalar(ScalarType::String) => Some("str".to_owned()),
+                    FieldType::Scalar(ScalarType::Bytes) => Some("[u8]".to_owned()),
+                    FieldType::Scalar(_) | FieldType::Enum(_) => None,
+                    _ => Some(value.clone()),
+                };
+                match (is_nullable(field), borrowed) {
+                    (_, None) => (ty.clone(), format!("self.{name}")),
+                    (false, Some(borrowed)) => (format!("&{borrowed}"), format!("&self.{name}")),
+                    (true, Some(borrowed)) => {
+                        let deref = if matches!(field.ty, FieldType::Scalar(_))
+                            || self.is_boxed(message, field)
+                        {
+                            "as_deref"
+                        } else {
+                            "as_ref"
+                        };
+                        (
+                            format!("Option<&{borrowed}>"),
+                            format!("self.{name}.{deref}()"),
+                        )
+                    }
+                }
+            };
+            members.push((name, ty, getter, body));
+        }
+        for (index, oneof) in message.oneofs.iter().enumerate() {
+            let name = self.member(message, oneof);
+            let ty = self.oneof_path(message, index);
+            members.push((
+                name.clone(),
+                format!("Option<{ty}>"),
+                format!("Option<&{ty}>"),
+                format!("self.{name}.as_ref()"),
+            ));
+        }
+        members
+    }
+
+    fn generate_named(&mut self, message: &MessageDescriptor) {
+        let name = self.names.ident(&message.full_name);
+        self.out
+            .open(&format!("impl polyglot_rs::Named for {name} {{"));
+        self.out.line(&format!(
+            "const FULL_NAME: &'static str = \"{}\";",
+            message.full_name
+        ));
+        self.out.close("}");
+        self.out.line("");
+    }
+
+    /// Generates `new`, `builder` and a getter, `&mut` accessor and setter per
+    /// field, so that messages stay usable when their fields are private.
+    fn generate_accessors(&mut self, message: &MessageDescriptor) {
+        let name = self.names.ident(&message.full_name);
+        let members = self.members(message);
+        self.out.open(&format!("impl {name} {{"));
+        if members.len() > 7 {
+            self.out.line("#[allow(clippy::too_many_arguments)]");
+        }
+        let params: Vec<String> = members
+            .iter()
+            .map(|(field, ty, _, _)| format!("{field}: {ty}"))
+            .collect();
+        let fields: Vec<&str> = members
+            .iter()
+            .map(|(field, _, _, _)| field.as_str())
+            .collect();
+        self.out
+            .open(&format!("pub fn new({}) -> Self {{", params.join(", ")));
+        if fields.is_empty() {
+            self.out.line("Self {}");
+        } else {
+            self.out.line(&format!("Self {{ {} }}", fields.join(", ")));
+        }
+        self.out.close("}");
+        self.out.line("");
+        self.out
+            .open(&format!("pub fn builder() -> {name}Builder {{"));
+        self.out.line(&format!("{name}Builder::default()"));
+        self.out.close("}");
+        for (field, ty, getter, body) in &members {
+            let base = unescape(field);
+            self.out.line("");
+            self.out
+                .open(&format!("pub fn {field}(&self) -> {getter} {{"));
+            self.out.line(body);
+            self.out.close("}");
+            self.out.line("");
+            self.out
+                .open(&format!("pub fn {base}_mut(&mut self) -> &mut {ty} {{"));
+            self.out.line(&format!("&mut self.{field}"));
+            self.out.close("}");
+            self.out.line("");
+            self.out
+                .open(&format!("pub fn set_{base}(&mut self, value: {ty}) {{"));
+            self.out.line(&format!("self.{field} = value;"));
+            self.out.close("}");
+        }
+        self.out.close("}");
+        self.out.line("");
+    }
+
+    fn generate_builder(&mut self, message: &MessageDescriptor) {
+        let name = self.names.ident(&message.full_name);
+        self.out.line("#[derive(Debug, Clone, Default)]");
+        self.out.open(&format!("pub struct {name}Builder {{"));
+        self.out.line(&format!("inner: {name},"));
+        self.out.close("}");
+        self.out.line("");
+        self.out.open(&format!("impl {name}Builder {{"));
+        for (field, ty, _, _) in self.members(message) {
+            self.out.open(&format!(
+                "pub fn {field}(mut self, value: impl Into<{ty}>) -> Self {{"
+            ));
+            self.out
+                .line(&format!("self.inner.{field} = value.into();"));
+            self.out.line("self");
+            self.out.close("}");
+            self.out.line("");
+        }
+        self.out.open(&format!("pub fn build(self) -> {name} {{"));
+        self.out.line("self.inner");
+        self.out.close("}");
+        self.out.close("}");
+        self.out.line("");
+    }
+
+    /// Messages wrapping a single field convert to and from that field's type.
+    fn generate_conversions(&mut self, message: &MessageDescriptor) {
+        let members = self.members(message);
+        let [(field, ty, _, _)] = members.as_slice() else {
+            return;
+        };
+        let name = self.names.ident(&message.full_name);
+        self.out.open(&format!("impl From<{ty}> for {name} {{"));
+        self.out.open(&format!("fn from({field}: {ty}) -> Self {{"));
+        self.out.line(&format!("Self {{ {field} }}"));
+        self.out.close("}");
+        self.out.close("}");
+        self.out.line("");
+        self.out.open(&format!("impl From<{name}> for {ty} {{"));
+        self.out.open(&format!("fn from(value: {name}) -> Self {{"));
+        self.out.line(&format!("value.{field}"));
+        self.out.close("}");
+        self.out.close("}");
+        self.out.line("");
+    }
+
+    fn generate_oneof(&mut self, message: &MessageDescriptor, index: usize) {
+        let derives = self.message_derives(message, false);
+        self.generate_derives(&derives);
+        self.out.open(&format!(
+            "pub enum {} {{",
+            self.names
+                .ident(&format!("{}.{}", message.full_name, message.oneofs[index]))
+        ));
+        for field in oneof_fields(message, index) {
+            self.out.line(&format!(
+                "{}({}),",
+                self.names.variant(message, field),
+                self.boxed_type(
+                    message,
+                    field,
+                    self.value_type(&field.ty, self.types(field))
+                )
+            ));
+        }
+        self.out.close("}");
+        self.out.line("");
+    }
+
+    fn is_boxed(&self, message: &MessageDescriptor, field: &FieldDescriptor) -> bool {
+        self.boxed
+            .contains(&(message.full_name.clone(), field.number))
+    }
+
+    /// Wraps the message type in `ty` in a `Box` when the field is recursive.
+    fn boxed_type(
+        &self,
+        message: &MessageDescriptor,
+        field: &FieldDescriptor,
+        ty: String,
+    ) -> String {
+        if !self.is_boxed(message, field) {
+            return ty;
+        }
+        let inner = self.value_type(&field.ty, self.types(field));
+        ty.replacen(&inner, &format!("Box<{inner}>"), 1)
+    }
+
+    /// Converts an enum value to its number, which for open enums goes through
+    /// `IntoPrimitive` so that unknown values keep theirs.
+    fn enum_to_u32(&self, value: &str) -> String {
+        if self.config.open_enums {
+            format!("u32::from({value})")
+        } else {
+            format!("{value} as u32")
+        }
+    }
+
+    /// The expression encoding `value` (a place of type `ty`) when it is borrowed,
+    /// as it is while iterating over a `Vec` or `HashMap`.
+    fn encode_borrowed(&self, ty: &FieldType, types: Types, value: &str) -> String {
+        match ty {
+            FieldType::Scalar(s @ (ScalarType::String | ScalarType::Bytes)) => {
+                format!("b.encode_{}({value})?;", encode_suffix(*s, types))
+            }
+            FieldType::Scalar(scalar) => format!("b.encode_{}(*{value})?;", scalar_suffix(*scalar)),
+            FieldType::Enum(_) => {
+                format!("b.encode_u32({})?;", self.enum_to_u32(&format!("*{value}")))
+            }
+            _ => format!("{value}.encode(b)?;"),
+        }
+    }
+
+    /// The expression decoding a single value of type `ty`, using `error` when a
+    /// nested message or enum value is missing or invalid.
+    fn decode_value(&self, ty: &FieldType, types: Types, error: &str) -> String {
+        match ty {
+            FieldType::Scalar(ScalarType::String) => match types.string {
+                StringType::String => "b.decode_string()?".to_owned(),
+                StringType::ArcStr => "std::sync::Arc::from(b.decode_str()?)".to_owned(),
+                StringType::SmolStr => "smol_str::SmolStr::new(b.decode_str()?)".to_owned(),
+            },
+            FieldType::Scalar(ScalarType::Bytes) => match types.bytes {
+                BytesType::Vec => "b.decode_bytes()?".to_owned(),
+                BytesType::Bytes => {
+                    "bytes::Bytes::copy_from_slice(b.decode_byte_slice()?)".to_owned()
+                }
+            },
+            FieldType::Scalar(scalar) => format!("b.decode_{}()?", scalar_suffix(*scalar)),
+            FieldType::Enum(name) if self.config.open_enums => {
+                format!("{}::from(b.decode_u32()?)", self.path(name))
+            }
+            FieldType::Enum(name) => format!(
+                "{}::try_from(b.decode_u32()?).ok().ok_or(DecodingError::{error})?",
+                self.path(name)
+            ),
+            FieldType::Message(name) | FieldType::Named(name) => {
+                format!(
+                    "{}::decode_with_depth(b, depth - 1)?.ok_or(DecodingError::{error})?",
+                    self.path(name)
+                )
+            }
+            FieldType::Map(_, _) => unreachable!(),
+        }
+    }
+
+    fn generate_encode(&mut self, message: &MessageDescriptor) {
+        let name = self.names.ident(&message.full_name);
+        self.out.open(&format!("impl Encode for {name} {{"));
+        self.out.open("fn encode<'a> (&'a self, b: &'a mut Cursor<Vec<u8>>) -> Result<&'a mut Cursor<Vec<u8>>, Box<dyn std::error::Error>> {");
+
+        if is_delimited(message) {
+            self.out.line("let start = b.reserve_delimited()?;");
+            let mut values = Vec::new();
+            for member in members_by_number(message) {
+                match member {
+                    Member::Field(field) => match self.encode_scalar(message, field) {
+                        Some(value) => values.push(value),
+                        None => {
+                            self.generate_encode_values(&mut values);
+                            self.generate_encode_field(message, field);
+                        }
+                    },
+                    Member::Oneof(index) => {
+                        self.generate_encode_values(&mut values);
+                        self.generate_encode_oneof(message, index);
+                    }
+                }
+            }
+            self.generate_encode_values(&mut values);
+            self.out.line("b.encode_delimited(start)?;");
+        } else {
+            // Consecutive required values are chained, optional ones break the chain
+            // since they need a branch for `None`.
+            let mut values = Vec::new();
+            for field in plain_fields(message).filter(|f| !f.is_repeated() && !f.is_map()) {
+                match self.encode_scalar(message, field) {
+                    Some(value) => values.push(value),
+                    None if is_nullable(field) && !matches!(field.ty, FieldType::Message(_)) => {
+                        self.generate_encode_values(&mut values);
+                        self.generate_encode_field(message, field);
+                    }
+                    None => {}
+                }
+            }
+            self.generate_encode_values(&mut values);
+
+            for field in plain_fields(message).filter(|f| f.is_repeated()) {
+                self.generate_encode_field(message, field);
+            }
+            for field in plain_fields(message).filter(|f| !f.is_repeated()) {
+                if field.is_map() || matches!(field.ty, FieldType::Message(_)) {
+                    self.generate_encode_field(message, field);
+                }
+            }
+            for index in 0..message.oneofs.len() {
+                self.generate_encode_oneof(message, index);
+            }
+        }
+        self.out.line("Ok(b)");
+        self.out.close("}");
+        self.out.close("}");
+        self.out.line("");
+    }
+
+    /// The chained encoder call for a field that is a required scalar or enum.
+    fn encode_scalar(
+        &self,
+        message: &MessageDescriptor,
+        field: &FieldDescriptor,
+    ) -> Option<String> {
+        if field.is_repeated() || is_nullable(field) {
+            return None;
+        }
+        let name = self.member(message, &field.name);
+        match &field.ty {
+            FieldType::Enum(_) => Some(format!(
+                ".encode_u32({})",
+                self.enum_to_u32(&format!("self.{name}"))
+            )),
+            FieldType::Scalar(s @ (ScalarType::String | ScalarType::Bytes)) => Some(format!(
+                ".encode_{}(&self.{name})",
+                encode_suffix(*s, self.types(field))
+            )),
+            FieldType::Scalar(s) => Some(format!(".encode_{}(self.{name})", scalar_suffix(*s))),
+            _ => None,
+        }
+    }
+
+    fn generate_encode_values(&mut self, values: &mut Vec<String>) {
+        if !values.is_empty() {
+            self.out.line(&format!("b{}?;", values.join("?")));
+            values.clear();
+        }
+    }
+
+    /// Encodes a field that isn't a required scalar: an optional value, an array,
+    /// a map or a nested message.
+    fn generate_encode_field(&mut self, message: &MessageDescriptor, field: &FieldDescriptor) {
+        let name = self.member(message, &field.name);
+        let types = self.types(field);
+        match &field.ty {
+            _ if array_encoding(field) != ArrayEncoding::Unpacked => {
+                let suffix = packed_suffix(field).unwrap_or_default();
+                let method = match array_encoding(field) {
+                    ArrayEncoding::Delta => "delta_slice",
+                    ArrayEncoding::RunLength => "run_length_slice",
+                    _ => "slice",
+                };
+                self.out
+                    .line(&format!("b.encode_{suffix}_{method}(&self.{name})?;"));
+            }
+            _ if field.is_repeated() => {
+                self.out.line(&format!(
+                    "b.encode_array(self.{name}.len(), {})?;",
+                    kind(&field.ty)
+                ));
+                self.out.open(&format!("for item in &self.{name} {{"));
+                self.out
+                    .line(&self.encode_borrowed(&field.ty, types, "item"));
+                self.out.close("}");
+            }
+            FieldType::Map(key, value) => {
+                self.out.line(&format!(
+                    "b.encode_map(self.{name}.len(), {}, {})?;",
+                    kind(key),
+                    kind(value)
+                ));
+                self.out.open(&format!("for (k, v) in &self.{name} {{"));
+                self.out.line(&self.encode_borrowed(key, types, "k"));
+                self.out.line(&self.encode_borrowed(value, types, "v"));
+                self.out.close("}");
+            }
+            _ => self.generate_encode_nullable(&field.ty, types, &name),
+        }
+    }
+
+    fn generate_encode_nullable(&mut self, ty: &FieldType, types: Types, name: &str) {
+        self.out.open(&format!("match &self.{name} {{"));
+        self.out.open("Some(v) => {");
+        self.out.line(&self.encode_borrowed(ty, types, "v"));
+        self.out.close("}");
+        self.out.open("None => {");
+        self.out.line("b.encode_none()?;");
+        self.out.close("}");
+        self.out.close("}");
+    }
+
+    /// Each oneof is encoded after every other field as the field number of the
+    /// active member followed by its value, or as `0` when it is unset. A `None`
+    /// marker can't be used since it would be read as a nil message when the
+    /// oneof is the first value of a message.
+    fn generate_encode_oneof(&mut self, message: &MessageDescriptor, index: usize) {
+        let oneof_type = self.oneof_path(message, index);
+        self.out.open(&format!(
+            "match &self.{} {{",
+            self.member(message, &message.oneofs[index])
+        ));
+        self.out.open("None => {");
+        self.out.line("b.encode_u32(0)?;");
+        self.out.close("}");
+        for field in oneof_fields(message, index) {
+            self.out.open(&format!(
+                "Some({oneof_type}::{}(v)) => {{",
+                self.names.variant(message, field)
+            ));
+            self.out.line(&format!("b.encode_u32({})?;", field.number));
+            self.out
+                .line(&self.encode_borrowed(&field.ty, self.types(field), "v"));
+            self.out.close("}");
+        }
+        self.out.close("}");
+    }
+
+    fn generate_decode(&mut self, message: &MessageDescriptor) {
+        let name = self.names.ident(&message.full_name);
+        self.out.open(&format!("impl Decode for {name} {{"));
+        self.out.open(&format!("fn decode_with_depth (b: &mut Cursor<impl DecoderBuffer>, depth: usize) -> Result<Option<{name}>, Box<dyn std::error::Error>> {{"));
+        self.out.open("if b.decode_none() {");
+        self.out.line("return Ok(None);");
+        self.out.close("}");
+        self.out.line("");
+        if is_delimited(message) {
+            // Fields missing from the message are left at their defaults.
+            self.out
+                .line(&format!("let mut message = {name}::default();"));
+            self.out.line("message.decode_into_with_depth(b, depth)?;");
+            self.out.line("Ok(Some(message))");
+            self.out.close("}");
+            self.out.line("");
+            self.generate_decode_into(message);
+            self.out.close("}");
+            self.out.line("");
+            return;
+        }
+
+        self.out.open("if let Ok(error) = b.decode_error() {");
+        self.out.line("return Err(error);");
+        self.out.close("}");
+        self.out.line("");
+        self.out.open("if depth == 0 {");
+        self.out
+            .line("return Err(DecodingError::MaxDepthExceeded.into());");
+        self.out.close("}");
+        self.out.line("");
+
+        for field in plain_fields(message).filter(|f| f.is_repeated()) {
+            if packed_suffix(field).is_some() {
+                continue;
+            }
+            let name = self.names.member(message, &field.name).to_owned();
+            let depth = if needs_depth(&field.ty) {
+                ", depth: usize"
+            } else {
+                ""
+            };
+            self.out.line("#[allow(clippy::type_complexity)]");
+            self.out.open(&format!("fn {name}_decode(b: &mut Cursor<impl DecoderBuffer>{depth}) -> Result<Option<{}>, Box<dyn std::error::Error>> {{", self.field_type(field)));
+            self.out.line(&format!(
+                "let {name}_size = b.decode_array({})?;",
+                kind(&field.ty)
+            ));
+            self.out
+                .line(&format!("let mut temp = Vec::with_capacity({name}_size);"));
+            self.out.open(&format!("for _ in 0..{name}_size {{"));
+            self.out.line(&format!(
+                "temp.push({});",
+                self.decode_value(&field.ty, self.types(field), "InvalidArray")
+            ));
+            self.out.close("}");
+            self.out.line("Ok(Some(temp))");
+            self.out.close("}");
+        }
+        for field in plain_fields(message).filter(|f| f.is_map()) {
+            self.generate_decode_map(message, field);
+        }
+
+        self.out.open(&format!("Ok(Some({name}{{"));
+        for field in plain_fields(message) {
+            if field.is_repeated() || field.is_map() || matches!(field.ty, FieldType::Message(_)) {
+                continue;
+            }
+            if is_nullable(field) {
+                self.out.line(&format!(
+                    "{}: if b.decode_none() {{ None }} else {{ Some({}) }},",
+                    self.member(message, &field.name),
+                    self.decode_value(&field.ty, self.types(field), "InvalidEnum")
+                ));
+            } else {
+                self.out.line(&format!(
+                    "{}: {},",
+                    self.member(message, &field.name),
+                    self.decode_value(&field.ty, self.types(field), "InvalidEnum")
+                ));
+            }
+        }
+        for field in plain_fields(message).filter(|f| f.is_repeated()) {
+            let name = self.member(message, &field.name);
+            if let Some(suffix) = packed_suffix(field) {
+                self.out.line(&format!("{name}: b.decode_{suffix}_vec()?,"));
+                continue;
+            }
+            let base = self.names.member(message, &field.name);
+            let args = if needs_depth(&field.ty) {
+                "b, depth"
+            } else {
+                "b"
+            };
+            self.out.line(&format!(
+                "{name}: {base}_decode({args})?.ok_or(DecodingError::InvalidArray)?,"
+            ));
+        }
+        for field in plain_fields(message).filter(|f| !f.is_repeated()) {
+            let name = self.member(message, &field.name);
+            match &field.ty {
+                FieldType::Map(_, _) => {
+                    let args = if needs_depth(&field.ty) {
+                        "b, depth"
+                    } else {
+                        "b"
+                    };
+                    self.out.line(&format!(
+                        "{name}: {}_decode({args})?.ok_or(DecodingError::InvalidMap)?,",
+                        self.names.member(message, &field.name)
+                    ))
+                }
+                FieldType::Message(ty) => {
+                    let boxed = if self.is_boxed(message, field) {
+                        ".map(Box::new)"
+                    } else {
+                        ""
+                    };
+                    self.out.line(&format!(
+                        "{name}: {}::decode_with_depth(b, depth - 1)?{boxed},",
+                        self.path(ty)
+                    ))
+                }
+                _ => {}
+            }
+        }
+        for (index, oneof) in message.oneofs.iter().enumerate() {
+            let oneof_type = self.oneof_path(message, index);
+            self.out.open(&format!(
+                "{}: match b.decode_u32()? {{",
+                self.member(message, oneof)
+            ));
+            self.out.line("0 => None,");
+            for field in oneof_fields(message, index) {
+                let mut value = self.decode_value(&field.ty, self.types(field), "InvalidOneof");
+                if self.is_boxed(message, field) {
+                    value = format!("Box::new({value})");
+                }
+                self.out.line(&format!(
+                    "{} => Some({oneof_type}::{}({value})),",
+                    field.number,
+                    self.names.variant(message, field),
+                ));
+            }
+            self.out
+                .line("_ => return Err(DecodingError::InvalidOneof.into()),");
+            self.out.close("},");
+        }
+        self.out.close("}))");
+        self.out.close("}");
+        self.out.line("");
+        self.generate_decode_into(message);
+        self.out.close("}");
+        self.out.line("");
+    }
+
+    /// The decoder method that decodes a value of type `ty` into a `String` or
+    /// `Vec<u8>` it already holds, for the types that have one.
+    fn decode_in_place(ty: &FieldType, types: Types) -> Option<&'static str> {
+        match ty {
+            FieldType::Scalar(ScalarType::String) if types.string == StringType::String => {
+                Some("decode_string_into")
+            }
+            FieldType::Scalar(ScalarType::Bytes) if types.bytes == BytesType::Vec => {
+                Some("decode_bytes_into")
+            }
+            _ => None,
+        }
+    }
+
+    /// Generates `decode_into_with_depth`, which decodes the fields in the same
+    /// order as `decode_with_depth` but into the ones `self` already has, so that
+    /// their allocations are reused.
+    ///
+    /// Delimited messages are decoded in field number order instead. Fields
+    /// missing from the end of the message are reset to their defaults and any
+    /// fields past the known ones are skipped.
+    fn generate_decode_into(&mut self, message: &MessageDescriptor) {
+        self.out.open("fn decode_into_with_depth (&mut self, b: &mut Cursor<impl DecoderBuffer>, depth: usize) -> Result<(), Box<dyn std::error::Error>> {");
+        self.out.open("if b.decode_none() {");
+        self.out
+            .line("return Err(DecodingError::InvalidNone.into());");
+        self.out.close("}");
+        self.out.line("");
+        self.out.open("if let Ok(error) = b.decode_error() {");
+        self.out.line("return Err(error);");
+        self.out.close("}");
+        self.out.line("");
+        self.out.open("if depth == 0 {");
+        self.out
+            .line("return Err(DecodingError::MaxDepthExceeded.into());");
+        self.out.close("}");
+        self.out.line("");
+
+        if is_delimited(message) {
+            self.out.line("let end = b.decode_delimited()?;");
+            for member in members_by_number(message) {
+                self.out.open("if b.position() < end {");
+                match member {
+                    Member::Field(field) => self.generate_decode_into_field(message, field),
+                    Member::Oneof(index) => self.generate_decode_into_oneof(message, index),
+                }
+                self.out.close("} else {");
+                self.out.indent += 1;
+                let name = match member {
+                    Member::Field(field) => self.member(message, &field.name),
+                    Member::Oneof(index) => self.member(message, &message.oneofs[index]),
+                };
+                self.out.line(&format!("self.{name} = Default::default();"));
+                self.out.close("}");
+            }
+            self.out.open("while b.position() < end {");
+            self.out.line("b.skip_value()?;");
+            self.out.close("}");
+            self.out.open("if b.position() != end {");
+            self.out
+                .line("return Err(DecodingError::InvalidStruct.into());");
+            self.out.close("}");
+        } else {
+            for field in plain_fields(message) {
+                if !field.is_repeated()
+                    && !field.is_map()
+                    && !matches!(field.ty, FieldType::Message(_))
+                {
+                    self.generate_decode_into_field(message, field);
+                }
+            }
+            for field in plain_fields(message).filter(|f| f.is_repeated()) {
+                self.generate_decode_into_field(message, field);
+            }
+            for field in plain_fields(message).filter(|f| !f.is_repeated()) {
+                if field.is_map() || matches!(field.ty, FieldType::Message(_)) {
+                    self.generate_decode_into_field(message, field);
+                }
+            }
+            for index in 0..message.oneofs.len() {
+                self.generate_decode_into_oneof(message, index);
+            }
+        }
+        self.out.line("Ok(())");
+        self.out.close("}");
+    }
+
+    fn generate_decode_into_field(&mut self, message: &MessageDescriptor, field: &FieldDescriptor) {
+        let name = self.member(message, &field.name);
+        let types = self.types(field);
+        if let Some(suffix) = packed_suffix(field) {
+            self.out
+                .line(&format!("b.decode_{suffix}_vec_into(&mut self.{name})?;"));
+            return;
+        }
+        if field.is_repeated() {
+            self.out
+                .line(&format!("let size = b.decode_array({})?;", kind(&field.ty)));
+            self.out.line(&format!("self.{name}.truncate(size);"));
+            self.out.open("for i in 0..size {");
+            self.out
+                .open(&format!("if let Some(item) = self.{name}.get_mut(i) {{"));
+            match (&field.ty, Self::decode_in_place(&field.ty, types)) {
+                (_, Some(method)) => self.out.line(&format!("b.{method}(item)?;")),
+                (FieldType::Message(_), _) => {
+                    self.out.line("item.decode_into_with_depth(b, depth - 1)?;")
+                }
+                _ => self.out.line(&format!(
+                    "*item = {};",
+                    self.decode_value(&field.ty, types, "InvalidArray")
+                )),
+            }
+            self.out.close("} else {");
+            self.out.indent += 1;
+            self.out.line(&format!(
+                "self.{name}.push({});",
+                self.decode_value(&field.ty, types, "InvalidArray")
+            ));
+            self.out.close("}");
+            self.out.close("}");
+            return;
+        }
+        match &field.ty {
+            FieldType::Map(key, value) => {
+                self.out.open("if b.decode_none() {");
+                self.out
+                    .line("return Err(DecodingError::InvalidMap.into());");
+                self.out.close("}");
+                self.out.line(&format!(
+                    "let size = b.decode_map({}, {}).ok().ok_or(DecodingError::InvalidU32)?;",
+                    kind(key),
+                    kind(value)
+                ));
+                self.out.line(&format!("self.{name}.clear();"));
+                self.out.open("for _ in 0..size {");
+                self.out.line(&format!(
+                    "let k = {};",
+                    self.decode_value(key, types, "InvalidMap")
+                ));
+                self.out.line(&format!(
+                    "let v = {};",
+                    self.decode_value(value, types, "InvalidMap")
+                ));
+                self.out.line(&format!("self.{name}.insert(k, v);"));
+                self.out.close("}");
+            }
+            FieldType::Message(ty) => {
+                let boxed = if self.is_boxed(message, field) {
+                    ".map(Box::new)"
+                } else {
+                    ""
+                };
+                self.out.open("if b.decode_none() {");
+                self.out.line(&format!("self.{name} = None;"));
+                self.out
+                    .close(&format!("}} else if let Some(v) = &mut self.{name} {{"));
+                self.out.indent += 1;
+                self.out.line("v.decode_into_with_depth(b, depth - 1)?;");
+                self.out.close("} else {");
+                self.out.indent += 1;
+                self.out.line(&format!(
+                    "self.{name} = {}::decode_with_depth(b, depth - 1)?{boxed};",
+                    self.path(ty)
+                ));
+                self.out.close("}");
+            }
+            _ => {
+                let value = self.decode_value(&field.ty, types, "InvalidEnum");
+                match (is_nullable(field), Self::decode_in_place(&field.ty, types)) {
+                    (false, Some(method)) => {
+                        self.out.line(&format!("b.{method}(&mut self.{name})?;"))
+                    }
+                    (false, None) => self.out.line(&format!("self.{name} = {value};")),
+                    (true, method) => {
+                        self.out.open("if b.decode_none() {");
+                        self.out.line(&format!("self.{name} = None;"));
+                        self.out.close("} else {");
+                        self.out.indent += 1;
+                        match method {
+                            Some(method) => self.out.line(&format!(
+                                "b.{method}(self.{name}.get_or_insert_with(Default::default))?;"
+                            )),
+                            None => self.out.line(&format!("self.{name} = Some({value});")),
+                        }
+                        self.out.close("}");
+                    }
+                }
+            }
+        }
+    }
+
+    fn generate_decode_into_oneof(&mut self, message: &MessageDescriptor, index: usize) {
+        let oneof_type = self.oneof_path(message, index);
+        self.out.open(&format!(
+            "self.{} = match b.decode_u32()? {{",
+            self.member(message, &message.oneofs[index])
+        ));
+        self.out.line("0 => None,");
+        for field in oneof_fields(message, index) {
+            let mut value = self.decode_value(&field.ty, self.types(field), "InvalidOneof");
+            if self.is_boxed(message, field) {
+                value = format!("Box::new({value})");
+            }
+            self.out.line(&format!(
+                "{} => Some({oneof_type}::{}({value})),",
+                field.number,
+                self.names.variant(message, field),
+            ));
+        }
+        self.out
+            .line("_ => return Err(DecodingError::InvalidOneof.into()),");
+        self.out.close("};");
+    }
+
+    fn generate_decode_map(&mut self, message: &MessageDescriptor, field: &FieldDescriptor) {
+        let (key, value) = match &field.ty {
+            FieldType::Map(key, value) => (key, value),
+            _ => return,
+        };
+        let name = self.names.member(message, &field.name).to_owned();
+        let depth = if needs_depth(&field.ty) {
+            ", depth: usize"
+        } else {
+            ""
+        };
+        self.out.line("#[allow(clippy::type_complexity)]");
+        self.out.open(&format!("fn {name}_decode(b: &mut Cursor<impl DecoderBuffer>{depth}) -> Result<Option<{}>, Box<dyn std::error::Error>> {{", self.field_type(field)));
+        self.out.open("if b.decode_none() {");
+        self.out.line("return Ok(None);");
+        self.out.close("}");
+        self.out.line("");
+        self.out.line(&format!(
+            "let size = b.decode_map({}, {}).ok().ok_or(DecodingError::InvalidU32)?;",
+            kind(key),
+            kind(value)
+        ));
+        let types = self.types(field);
+        self.out
+            .line(&format!("let mut map = {}::new();", map_type(types)));
+        self.out.open("for _ in 0..size {");
+        self.out.line(&format!(
+            "let k = {};",
+            self.decode_value(key, types, "InvalidMap")
+        ));
+        self.out.line(&format!(
+            "let v = {};",
+            self.decode_value(value, types, "InvalidMap")
+        ));
+        self.out.line("map.insert(k, v);");
+        self.out.close("}");
+        self.out.line("Ok(Some(map))");
+        self.out.close("}");
+    }
+
+    /// Mirrors the `services` template. Streaming methods are not supported and
+    /// are left out of the generated trait, client and server.
+    fn generate_service(&mut self, service: &ServiceDescriptor) {
+        let name = self.names.ident(&service.full_name);
+        // Each method's variant of the method enum and its function on the
+        // trait and the client, which can't be the client's `new`.
+        let mut variants = Scope::with(&[]);
+        let mut functions = Scope::with(&["new"]);
+        // Methods are numbered by their index in the service, streaming ones
+        // included even though they aren't generated, so that adding or removing
+        // a streaming method doesn't renumber the others.
+        let methods: Vec<_> = service
+            .methods
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.is_unary())
+            .map(|(id, m)| {
+                let variant =
+                    variants.claim(&camel_case(&m.name), "", |name| vec![name.to_owned()]);
+                let function =
+                    functions.claim(&snake_case(&m.name), "_", |name| vec![name.to_owned()]);
+                (m, rust_ident(&variant), rust_ident(&function), id)
+            })
+            .collect();
+
+        // An enum without variants can't have a `repr`, so services without unary
+        // methods get neither the enum nor the dispatch on it.
+        if !methods.is_empty() {
+            self.out
+                .line("#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Copy, Clone)]");
+            self.out.line("#[repr(u32)]");
+            self.out.open(&format!("pub enum {name}Method {{"));
+            for (_, variant, _, id) in &methods {
+                self.out.line(&format!("{variant} = {id},"));
+            }
+            self.out.close("}");
+            self.out.line("");
+        }
+
+        self.out.open(&format!("pub trait {name}: Send + Sync {{"));
+        for (method, _, function, _) in &methods {
+            self.out.line(&format!(
+                "fn {function}(&self, request: {}) -> impl Future<Output = Result<{}, RpcError>> + Send;",
+                self.path(&method.input),
+                self.path(&method.output)
+            ));
+        }
+        self.out.close("}");
+        self.out.line("");
+
+        self.out.open(&format!("pub struct {name}Client<T> {{"));
+        if methods.is_empty() {
+            self.out.line("#[allow(dead_code)]");
+        }
+        self.out.line("transport: T,");
+        self.out.close("}");
+        self.out.line("");
+        self.out
+            .open(&format!("impl<T: Transport> {name}Client<T> {{"));
+        self.out.open("pub fn new(transport: T) -> Self {");
+        self.out.line("Self { transport }");
+        self.out.close("}");
+        for (method, variant, function, _) in &methods {
+            self.out.line("");
+            self.out.open(&format!(
+                "pub async fn {function}(&self, request: &{}) -> Result<{}, RpcError> {{",
+                self.path(&method.input),
+                self.path(&method.output)
+            ));
+            self.out.line("let mut b = Cursor::new(Vec::new());");
+            self.out
+                .line("request.encode(&mut b).map_err(|e| RpcError::Encoding(e.to_string()))?;");
+            self.out.line(&format!(
+                "let mut response = self.transport.call({name}Method::{variant} as u32, b.into_inner()).await?;"
+            ));
+            self.out.line(&format!(
+                "{}::decode(&mut Cursor::new(&mut response)).map_err(|e| RpcError::Decoding(e.to_string()))?.ok_or_else(|| RpcError::Decoding(DecodingError::InvalidStruct.to_string()))",
+                self.path(&method.output)
+            ));
+            self.out.close("}");
+        }
+        self.out.close("}");
+        self.out.line("");
+
+        self.out.open(&format!("pub struct {name}Server<S> {{"));
+        if methods.is_empty() {
+            self.out.line("#[allow(dead_code)]");
+        }
+        self.out.line("service: S,");
+        self.out.close("}");
+        self.out.line("");
+        self.out
+            .open(&format!("impl<S: {name}> {name}Server<S> {{"));
+        self.out.open("pub fn new(service: S) -> Self {");
+        self.out.line("Self { service }");
+        self.out.close("}");
+        self.out.close("}");
+        self.out.line("");
+        self.out
+            .open(&format!("impl<S: {name}> Handler for {name}Server<S> {{"));
+        if methods.is_empty() {
+            self.out.open("async fn handle(&self, method: u32, _request: Vec<u8>) -> Result<Vec<u8>, RpcError> {");
+            self.out.line("Err(RpcError::UnknownMethod(method))");
+            self.out.close("}");
+            self.out.close("}");
+            self.out.line("");
+            return;
+        }
+        self.out.open("async fn handle(&self, method: u32, mut request: Vec<u8>) -> Result<Vec<u8>, RpcError> {");
+        self.out.line("let mut b = Cursor::new(Vec::new());");
+        self.out
+            .open(&format!("match {name}Method::try_from(method) {{"));
+        for (method, variant, function, _) in &methods {
+            self.out.open(&format!("Ok({name}Method::{variant}) => {{"));
+            self.out.line(&format!(
+                "let request = {}::decode(&mut Cursor::new(&mut request)).map_err(|e| RpcError::Decoding(e.to_string()))?.ok_or_else(|| RpcError::Decoding(DecodingError::InvalidStruct.to_string()))?;",
+                self.path(&method.input)
+            ));
+            self.out.line(&format!(
+                "let response = self.service.{function}(request).await?;"
+            ));
+            self.out
+                .line("response.encode(&mut b).map_err(|e| RpcError::Encoding(e.to_string()))?;");
+            self.out.close("}");
+        }
+        self.out
+            .line("Err(_) => return Err(RpcError::UnknownMethod(method)),");
+        self.out.close("}");
+        self.out.line("Ok(b.into_inner())");
+        self.out.close("}");
+        self.out.close("}");
+        self.out.line("");
+    }
+}
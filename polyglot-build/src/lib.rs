@@ -0,0 +1,409 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Generates polyglot-rs bindings for `.proto` files from a `build.rs`, without
+//! needing Go, `protoc` or `rustfmt`.
+//!
+//! ```no_run
+//! // build.rs
+//! polyglot_build::compile_protos(&["proto/service.proto"], &["proto"]).unwrap();
+//! ```
+//!
+//! The generated file for `service.proto` is written to `$OUT_DIR/service.rs`
+//! and can be pulled into the crate with
+//! `include!(concat!(env!("OUT_DIR"), "/service.rs"));`.
+//!
+//! Generated files refer to types from the files they import through sibling
+//! modules named after those files, so files that import each other should be
+//! included together with [`Config::include_file`].
+//!
+//! The Rust types of maps, bytes and strings are set with [`Config::map_type`],
+//! [`Config::bytes_type`] and [`Config::string_type`], and per field with the
+//! options of `polyglot/options.proto`, which can be imported without being
+//! in the include paths:
+//!
+//! ```proto
+//! import "polyglot/options.proto";
+//!
+//! message Index {
+//!   map<string, bytes> chunks = 1 [(polyglot.map_type) = BTREE_MAP, (polyglot.bytes_type) = BYTES];
+//! }
+//! ```
+//!
+//! Messages with `option (polyglot.delimited) = true;` are written with their
+//! length in front of them and their fields in field number order, so fields
+//! can be added to the end of them without breaking decoders built from an
+//! older version of the file, and the other way around.
+//!
+//! Absent message fields and `optional` scalars are written as `None`, the same
+//! as a nil message, so a message that starts with one of them or has no fields
+//! at all reads as nil when it is nested in another one. Such messages should be
+//! delimited, since their length in front of them keeps the two apart without
+//! changing how any other message is written.
+//!
+//! Repeated numbers with `[(polyglot.array_encoding) = PACKED]` are written as
+//! packed arrays, with the kind of their elements written once instead of in
+//! front of every element, and repeated bools with it take up a bit each.
+//! `RUN_LENGTH` writes repeated values once with the number of times they
+//! repeat, and `DELTA` writes 64-bit integers as the differences between them,
+//! which suits sorted ids and timestamps. Generated decoders read arrays of
+//! numbers and bools whichever way they were written.
+//!
+//! [`DescriptorPool::schema`] describes the messages of a pool as a runtime
+//! [`polyglot_rs::Schema`], which [`polyglot_rs::check_compatibility`] can
+//! compare against the schema of another version of the same files. The
+//! `polyglot-compat` binary does this for two `.proto` files:
+//!
+//! ```text
+//! polyglot-compat [-I dir]... [--root package.Message] old.proto new.proto
+//! ```
+
+pub mod ast;
+mod generator;
+mod names;
+mod parser;
+mod pool;
+mod schema;
+mod types;
+
+pub use parser::parse;
+pub use pool::DescriptorPool;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+#[derive(Debug)]
+pub enum BuildError {
+    Io(io::Error),
+    Parse {
+        file: String,
+        line: usize,
+        message: String,
+    },
+    MissingImport(String),
+    UnresolvedType {
+        file: String,
+        name: String,
+    },
+    InvalidOption {
+        file: String,
+        field: String,
+        option: String,
+    },
+    /// A message or enum has the same full name as another one.
+    DuplicateSymbol {
+        file: String,
+        name: String,
+    },
+    /// A field reuses the number of an earlier field of its message.
+    DuplicateFieldNumber {
+        file: String,
+        field: String,
+        number: u32,
+    },
+    MissingOutDir,
+    /// Two requested files would be generated to the same `<file stem>.rs`.
+    DuplicateOutput {
+        file: String,
+        other: String,
+    },
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for BuildError {}
+
+impl From<io::Error> for BuildError {
+    fn from(err: io::Error) -> Self {
+        BuildError::Io(err)
+    }
+}
+
+/// Privacy of generated struct fields, equivalent to the `privacy` option of
+/// `protoc-gen-rs-polyglot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Privacy {
+    #[default]
+    Private,
+    Public,
+    Crate,
+}
+
+/// Rust type of map fields, equivalent to the `map_type` option of
+/// `protoc-gen-rs-polyglot`. `IndexMap` needs the `indexmap` crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MapType {
+    #[default]
+    HashMap,
+    BTreeMap,
+    IndexMap,
+}
+
+/// Rust type of bytes fields, equivalent to the `bytes_type` option of
+/// `protoc-gen-rs-polyglot`. `Bytes` needs the `bytes` crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BytesType {
+    #[default]
+    Vec,
+    Bytes,
+}
+
+/// Rust type of string fields, equivalent to the `string_type` option of
+/// `protoc-gen-rs-polyglot`. `SmolStr` needs the `smol_str` crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringType {
+    #[default]
+    String,
+    ArcStr,
+    SmolStr,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    privacy: Privacy,
+    out_dir: Option<PathBuf>,
+    derives: Vec<String>,
+    attributes: Vec<String>,
+    open_enums: bool,
+    include_file: Option<String>,
+    map_type: MapType,
+    bytes_type: BytesType,
+    string_type: StringType,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn privacy(&mut self, privacy: Privacy) -> &mut Self {
+        self.privacy = privacy;
+        self
+    }
+
+    /// Generates enums with an `Unknown(u32)` variant holding values that aren't
+    /// in the schema, instead of failing to decode messages that contain them.
+    pub fn open_enums(&mut self, open: bool) -> &mut Self {
+        self.open_enums = open;
+        self
+    }
+
+    /// Adds a derive to every generated struct and enum, on top of the standard
+    /// ones, for example `serde::Serialize`.
+    pub fn derive(&mut self, path: impl Into<String>) -> &mut Self {
+        self.derives.push(path.into());
+        self
+    }
+
+    /// Adds an attribute to every generated struct and enum, for example
+    /// `#[serde(rename_all = "camelCase")]`.
+    pub fn attribute(&mut self, attribute: impl Into<String>) -> &mut Self {
+        self.attributes.push(attribute.into());
+        self
+    }
+
+    /// Sets the Rust type of map fields. Fields can override it with the
+    /// `(polyglot.map_type)` option declared in `polyglot/options.proto`.
+    pub fn map_type(&mut self, ty: MapType) -> &mut Self {
+        self.map_type = ty;
+        self
+    }
+
+    /// Sets the Rust type of bytes fields. Fields can override it with the
+    /// `(polyglot.bytes_type)` option declared in `polyglot/options.proto`.
+    pub fn bytes_type(&mut self, ty: BytesType) -> &mut Self {
+        self.bytes_type = ty;
+        self
+    }
+
+    /// Sets the Rust type of string fields. Fields can override it with the
+    /// `(polyglot.string_type)` option declared in `polyglot/options.proto`.
+    pub fn string_type(&mut self, ty: StringType) -> &mut Self {
+        self.string_type = ty;
+        self
+    }
+
+    /// Also writes a file to the output directory that includes every generated
+    /// file as a module named after it, which can be pulled into the crate with
+    /// `include!(concat!(env!("OUT_DIR"), "/<name>"));`.
+    pub fn include_file(&mut self, name: impl Into<String>) -> &mut Self {
+        self.include_file = Some(name.into());
+        self
+    }
+
+    /// Sets the directory generated files are written to. Defaults to `$OUT_DIR`.
+    pub fn out_dir(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.out_dir = Some(path.into());
+        self
+    }
+
+    /// Parses `protos` and everything they import, looking files up in `includes`,
+    /// and writes one `<file stem>.rs` per entry of `protos` to the output directory.
+    /// Fails with [`BuildError::DuplicateOutput`] when two entries share a stem.
+    ///
+    /// Inside a build script, every file read is printed as
+    /// `cargo:rerun-if-changed`, imports included.
+    pub fn compile_protos(
+        &self,
+        protos: &[impl AsRef<Path>],
+        includes: &[impl AsRef<Path>],
+    ) -> Result<(), BuildError> {
+        let out_dir = match &self.out_dir {
+            Some(out_dir) => out_dir.clone(),
+            None => PathBuf::from(env::var_os("OUT_DIR").ok_or(BuildError::MissingOutDir)?),
+        };
+
+        let mut sources = Vec::new();
+        let (pool, files) = load_sources(protos, includes, &mut sources)?;
+        if env::var_os("OUT_DIR").is_some() {
+            for path in &sources {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+
+        let mut outputs: HashMap<String, &str> = HashMap::new();
+        let mut modules = Vec::new();
+        for (name, _) in &files {
+            let file = pool
+                .file(name)
+                .ok_or_else(|| BuildError::MissingImport(name.clone()))?;
+            let stem = Path::new(name)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| name.clone());
+            // Both the generated file and the module `include_file` puts it in
+            // are named after the stem, so they have to be unique.
+            for key in [format!("{stem}.rs"), names::file_module(name)] {
+                if let Some(other) = outputs.insert(key, name) {
+                    if other != name {
+                        return Err(BuildError::DuplicateOutput {
+                            file: name.clone(),
+                            other: other.to_owned(),
+                        });
+                    }
+                }
+            }
+            fs::write(
+                out_dir.join(format!("{stem}.rs")),
+                self.generate(&pool, file),
+            )?;
+            modules.push((file, format!("{stem}.rs")));
+        }
+        if let Some(include_file) = &self.include_file {
+            fs::write(
+                out_dir.join(include_file),
+                generator::include_file(&modules),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Generates the Rust source for a single file of a resolved pool. Types from
+    /// other files of the pool are referred to through the modules that
+    /// [`Config::include_file`] puts their generated files in.
+    pub fn generate(&self, pool: &DescriptorPool, file: &ast::FileDescriptor) -> String {
+        generator::Generator::new(self).generate(pool, file)
+    }
+}
+
+/// Compiles `protos` with the default configuration.
+pub fn compile_protos(
+    protos: &[impl AsRef<Path>],
+    includes: &[impl AsRef<Path>],
+) -> Result<(), BuildError> {
+    Config::new().compile_protos(protos, includes)
+}
+
+/// Parses `protos` and their imports into a pool. Returns the pool along with the
+/// pool name and on-disk path of each requested file, in the order given.
+pub fn load(
+    protos: &[impl AsRef<Path>],
+    includes: &[impl AsRef<Path>],
+) -> Result<(DescriptorPool, Vec<(String, PathBuf)>), BuildError> {
+    load_sources(protos, includes, &mut Vec::new())
+}
+
+/// Same as [`load`], also pushing the on-disk path of every file read to
+/// `sources`, imports included.
+fn load_sources(
+    protos: &[impl AsRef<Path>],
+    includes: &[impl AsRef<Path>],
+    sources: &mut Vec<PathBuf>,
+) -> Result<(DescriptorPool, Vec<(String, PathBuf)>), BuildError> {
+    let includes: Vec<&Path> = includes.iter().map(|i| i.as_ref()).collect();
+    let mut pool = DescriptorPool::new();
+    let mut files = Vec::new();
+    for proto in protos {
+        let proto = proto.as_ref();
+        let name = includes
+            .iter()
+            .find_map(|include| proto.strip_prefix(include).ok())
+            .unwrap_or(proto)
+            .to_string_lossy()
+            .replace('\\', "/");
+        load_file(&mut pool, &includes, &name, &mut Vec::new(), sources)?;
+        files.push((name, proto.to_path_buf()));
+    }
+    Ok((pool, files))
+}
+
+fn find_file(includes: &[&Path], name: &str) -> Option<PathBuf> {
+    if includes.is_empty() {
+        let path = PathBuf::from(name);
+        return path.exists().then_some(path);
+    }
+    includes
+        .iter()
+        .map(|include| include.join(name))
+        .find(|path| path.exists())
+}
+
+fn load_file(
+    pool: &mut DescriptorPool,
+    includes: &[&Path],
+    name: &str,
+    stack: &mut Vec<String>,
+    sources: &mut Vec<PathBuf>,
+) -> Result<(), BuildError> {
+    if pool.file(name).is_some() || stack.iter().any(|n| n == name) {
+        return Ok(());
+    }
+    let source = match find_file(includes, name) {
+        Some(path) => {
+            let source = fs::read_to_string(&path)?;
+            sources.push(path);
+            source
+        }
+        None => types::builtin(name)
+            .ok_or_else(|| BuildError::MissingImport(name.to_owned()))?
+            .to_owned(),
+    };
+    let file = parse(name, &source)?;
+
+    stack.push(name.to_owned());
+    for import in &file.imports {
+        load_file(pool, includes, import, stack, sources)?;
+    }
+    stack.pop();
+    pool.add(file)
+}
@@ -0,0 +1,400 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::ast::{FieldDescriptor, FileDescriptor, MessageDescriptor};
+use crate::DescriptorPool;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// These mirror `CamelCase` and `SnakeCase` in `pkg/utils/utils.go` so that both
+// generators produce the same identifiers for the same `.proto` input.
+
+/// Returns the CamelCased name. Underscores followed by a lower case letter and
+/// dots are dropped, and the letter following them is upper cased, so
+/// `SearchResponse.Result` becomes `SearchResponseResult`.
+pub fn camel_case(s: &str) -> String {
+    let s = s.as_bytes();
+    let mut t = Vec::with_capacity(32);
+    let mut i = 0;
+    if s.is_empty() {
+        return String::new();
+    }
+    if s[0] == b'_' {
+        i += 1;
+    }
+    while i < s.len() {
+        let mut c = s[i];
+        if c == b'_' && i + 1 < s.len() && s[i + 1].is_ascii_lowercase() {
+            i += 1;
+            continue;
+        }
+        if c == b'.' {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() {
+            t.push(c);
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_lowercase() {
+            c ^= b' ';
+        }
+        t.push(c);
+        while i + 1 < s.len() && s[i + 1].is_ascii_lowercase() {
+            i += 1;
+            t.push(s[i]);
+        }
+        i += 1;
+    }
+    String::from_utf8(t).unwrap_or_default()
+}
+
+pub fn snake_case(s: &str) -> String {
+    let mut b = String::with_capacity(s.len());
+    let mut consc = false;
+    for c in s.chars() {
+        if c.is_ascii_uppercase() {
+            if !b.is_empty() && !consc {
+                b.push('_');
+            }
+            b.push(c.to_ascii_lowercase());
+            consc = true;
+        } else {
+            b.push(c);
+            consc = false;
+        }
+    }
+    b
+}
+
+/// Keywords that can't be used as identifiers, including the reserved ones.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Names the generated code relies on in every module, either through its
+/// imports, the traits it defines or the prelude, which generated types can't
+/// shadow.
+const RESERVED: &[&str] = &[
+    "Encode",
+    "Decode",
+    "Cursor",
+    "DecodingError",
+    "Encoder",
+    "Decoder",
+    "DecoderBuffer",
+    "Kind",
+    "HashMap",
+    "TryFromPrimitive",
+    "TryFrom",
+    "FromPrimitive",
+    "IntoPrimitive",
+    "Future",
+    "Handler",
+    "RpcError",
+    "Transport",
+    "Box",
+    "Default",
+    "From",
+    "Into",
+    "Option",
+    "Result",
+    "Send",
+    "String",
+    "Sync",
+    "Vec",
+    "std",
+    "num_enum",
+    "polyglot_rs",
+    "bytes",
+    "indexmap",
+    "smol_str",
+];
+
+/// Methods of generated messages, including the ones they get from `Encode` and
+/// `Decode`, which accessors can't be named after.
+const METHODS: &[&str] = &[
+    "new",
+    "builder",
+    "encode",
+    "encode_into",
+    "encode_to_vec",
+    "encode_interned_to_vec",
+    "encode_compressed_to_vec",
+    "decode",
+    "decode_with_depth",
+    "decode_from_slice",
+    "decode_compressed_from_slice",
+    "decode_from_reader",
+    "decode_into",
+    "decode_into_with_depth",
+];
+
+/// Escapes keywords as raw identifiers. `self`, `Self`, `super` and `crate` can't
+/// be raw identifiers and get a trailing underscore instead.
+pub fn rust_ident(name: &str) -> String {
+    match name {
+        "self" | "Self" | "super" | "crate" => format!("{name}_"),
+        _ if KEYWORDS.contains(&name) => format!("r#{name}"),
+        _ => name.to_owned(),
+    }
+}
+
+/// Reverses [`rust_ident`], for names that get a prefix or suffix added.
+pub fn unescape(ident: &str) -> &str {
+    match ident {
+        "self_" | "Self_" | "super_" | "crate_" => &ident[..ident.len() - 1],
+        _ => ident.strip_prefix("r#").unwrap_or(ident),
+    }
+}
+
+/// The names taken in a Rust module or `impl` block. Names are claimed in
+/// declaration order, and a name that is already taken gets the lowest number
+/// that makes it unique appended, so the same input always produces the same
+/// identifiers.
+pub struct Scope {
+    used: HashSet<String>,
+}
+
+impl Scope {
+    /// A module, where the names the generated code relies on are taken.
+    pub fn module() -> Self {
+        Self::with(RESERVED)
+    }
+
+    pub fn with(reserved: &[&str]) -> Self {
+        Scope {
+            used: reserved.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Claims `name`, or `name` followed by `separator` and a number, such that
+    /// every name produced by `forms` for it is free, and returns it.
+    pub fn claim(
+        &mut self,
+        name: &str,
+        separator: &str,
+        forms: impl Fn(&str) -> Vec<String>,
+    ) -> String {
+        let mut candidate = name.to_owned();
+        let mut n = 1;
+        while forms(&candidate)
+            .iter()
+            .any(|form| self.used.contains(form))
+        {
+            n += 1;
+            candidate = format!("{name}{separator}{n}");
+        }
+        self.used.extend(forms(&candidate));
+        candidate
+    }
+}
+
+/// The module a generated file is included as, named after the file. Generated
+/// files refer to types from the files they import through these modules, so
+/// they have to be included side by side.
+pub fn file_module(name: &str) -> String {
+    let stem = Path::new(name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| name.to_owned());
+    let mut module: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if module.starts_with(|c: char| c.is_ascii_digit()) {
+        module.insert(0, '_');
+    }
+    rust_ident(&snake_case(&module))
+}
+
+/// The modules a file's types are declared in, mirroring its package.
+pub fn package(file: &FileDescriptor) -> Vec<String> {
+    file.package
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .map(|s| rust_ident(&snake_case(s)))
+        .collect()
+}
+
+/// The Rust names of everything generated for a file.
+#[derive(Default)]
+pub struct Names {
+    /// The module path, starting with the module of the file, and identifier of
+    /// every message, enum, oneof (by the message's full name followed by the
+    /// oneof name) and service.
+    types: HashMap<String, (Vec<String>, String)>,
+    /// The module holding the nested types and oneofs of a message.
+    modules: HashMap<String, String>,
+    /// The struct field of every field and oneof, by message and proto name.
+    members: HashMap<(String, String), String>,
+    /// The variant of every oneof member, by message and field name.
+    variants: HashMap<(String, String), String>,
+}
+
+impl Names {
+    /// Nested types live in a module named after their message, and the file
+    /// itself lives in modules mirroring its package. Types from the files it
+    /// imports, directly or not, are named the same way they are in their own
+    /// generated file.
+    pub fn new(pool: &DescriptorPool, file: &FileDescriptor) -> Self {
+        let mut names = Names::default();
+        let mut imports = file.imports.clone();
+        let mut seen = HashSet::new();
+        while let Some(name) = imports.pop() {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            if let Some(import) = pool.file(&name) {
+                names.add_file(import);
+                imports.extend(import.imports.iter().cloned());
+            }
+        }
+        names.add_file(file);
+        names
+    }
+
+    fn add_file(&mut self, file: &FileDescriptor) {
+        let package = [vec![file_module(&file.name)], package(file)].concat();
+
+        let mut scope = Scope::module();
+        for e in &file.enums {
+            self.add_type(&mut scope, &package, &e.full_name, &e.name, &[""]);
+        }
+        for m in &file.messages {
+            self.add_message(&mut scope, &package, m);
+        }
+        for s in &file.services {
+            let forms = ["", "Method", "Client", "Server"];
+            self.add_type(&mut scope, &package, &s.full_name, &s.name, &forms);
+        }
+    }
+
+    fn add_type(
+        &mut self,
+        scope: &mut Scope,
+        module: &[String],
+        full_name: &str,
+        name: &str,
+        suffixes: &[&str],
+    ) {
+        let ident = scope.claim(&camel_case(name), "", |name| {
+            suffixes.iter().map(|s| format!("{name}{s}")).collect()
+        });
+        self.types
+            .insert(full_name.to_owned(), (module.to_vec(), rust_ident(&ident)));
+    }
+
+    fn add_message(&mut self, scope: &mut Scope, module: &[String], message: &MessageDescriptor) {
+        let full_name = &message.full_name;
+        self.add_type(scope, module, full_name, &message.name, &["", "Builder"]);
+
+        let mut members = Scope::with(METHODS);
+        let mut claim = |name: &str| {
+            members.claim(&snake_case(name), "_", |name| {
+                vec![
+                    name.to_owned(),
+                    format!("{name}_mut"),
+                    format!("set_{name}"),
+                ]
+            })
+        };
+        for field in message.fields.iter().filter(|f| f.oneof.is_none()) {
+            let member = claim(&field.name);
+            self.members
+                .insert((full_name.clone(), field.name.clone()), member);
+        }
+        for oneof in &message.oneofs {
+            let member = claim(oneof);
+            self.members
+                .insert((full_name.clone(), oneof.clone()), member);
+        }
+
+        if message.messages.is_empty() && message.enums.is_empty() && message.oneofs.is_empty() {
+            return;
+        }
+        let name = scope.claim(
+            &snake_case(&message.name),
+            "_",
+            |name| vec![name.to_owned()],
+        );
+        let name = rust_ident(&name);
+        self.modules.insert(full_name.clone(), name.clone());
+        let module = [module, &[name]].concat();
+
+        let mut nested = Scope::module();
+        for e in &message.enums {
+            self.add_type(&mut nested, &module, &e.full_name, &e.name, &[""]);
+        }
+        for m in &message.messages {
+            self.add_message(&mut nested, &module, m);
+        }
+        for (index, oneof) in message.oneofs.iter().enumerate() {
+            let oneof_name = format!("{full_name}.{oneof}");
+            self.add_type(&mut nested, &module, &oneof_name, oneof, &[""]);
+            let mut variants = Scope::with(&[]);
+            for field in message.fields.iter().filter(|f| f.oneof == Some(index)) {
+                let variant =
+                    variants.claim(&camel_case(&field.name), "", |name| vec![name.to_owned()]);
+                self.variants.insert(
+                    (full_name.clone(), field.name.clone()),
+                    rust_ident(&variant),
+                );
+            }
+        }
+    }
+
+    /// The identifier a type is declared with. Types from other files keep the
+    /// flattened name of their full name.
+    pub fn ident(&self, full_name: &str) -> String {
+        match self.types.get(full_name) {
+            Some((_, ident)) => ident.clone(),
+            None => camel_case(full_name),
+        }
+    }
+
+    /// The path to a type from the module `from`.
+    pub fn path(&self, full_name: &str, from: &[String]) -> String {
+        let Some((module, ident)) = self.types.get(full_name) else {
+            return camel_case(full_name);
+        };
+        let common = module.iter().zip(from).take_while(|(a, b)| a == b).count();
+        let mut path: Vec<&str> = vec!["super"; from.len() - common];
+        path.extend(module[common..].iter().map(String::as_str));
+        path.push(ident);
+        path.join("::")
+    }
+
+    /// The module holding the nested types and oneofs of a message, if it has any.
+    pub fn module(&self, message: &MessageDescriptor) -> Option<&str> {
+        self.modules.get(&message.full_name).map(String::as_str)
+    }
+
+    /// The struct field for a field or oneof of a message, before escaping, so
+    /// that it can also be used to build other names.
+    pub fn member(&self, message: &MessageDescriptor, name: &str) -> &str {
+        &self.members[&(message.full_name.clone(), name.to_owned())]
+    }
+
+    pub fn variant(&self, message: &MessageDescriptor, field: &FieldDescriptor) -> &str {
+        &self.variants[&(message.full_name.clone(), field.name.clone())]
+    }
+}
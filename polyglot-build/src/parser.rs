@@ -0,0 +1,799 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::ast::{
+    EnumDescriptor, EnumValueDescriptor, FieldDescriptor, FieldType, FileDescriptor, Label,
+    MessageDescriptor, MethodDescriptor, OptionDescriptor, OptionValue, ScalarType,
+    ServiceDescriptor,
+};
+use crate::BuildError;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(String),
+    Float(String),
+    Str(String),
+    Symbol(char),
+}
+
+struct Lexer<'a> {
+    src: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn skip_whitespace_and_comments(&mut self) {
+        while self.pos < self.src.len() {
+            let c = self.src[self.pos];
+            if c == b'\n' {
+                self.line += 1;
+                self.pos += 1;
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else if self.src[self.pos..].starts_with(b"//") {
+                while self.pos < self.src.len() && self.src[self.pos] != b'\n' {
+                    self.pos += 1;
+                }
+            } else if self.src[self.pos..].starts_with(b"/*") {
+                self.pos += 2;
+                while self.pos < self.src.len() && !self.src[self.pos..].starts_with(b"*/") {
+                    if self.src[self.pos] == b'\n' {
+                        self.line += 1;
+                    }
+                    self.pos += 1;
+                }
+                self.pos = (self.pos + 2).min(self.src.len());
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<(Token, usize)>, String> {
+        self.skip_whitespace_and_comments();
+        if self.pos >= self.src.len() {
+            return Ok(None);
+        }
+        let line = self.line;
+        let start = self.pos;
+        let c = self.src[self.pos];
+
+        if c.is_ascii_alphabetic() || c == b'_' {
+            while self.pos < self.src.len()
+                && (self.src[self.pos].is_ascii_alphanumeric() || self.src[self.pos] == b'_')
+            {
+                self.pos += 1;
+            }
+            return Ok(Some((Token::Ident(self.slice(start)), line)));
+        }
+
+        if c.is_ascii_digit()
+            || (c == b'.' && self.peek_byte(1).is_some_and(|b| b.is_ascii_digit()))
+        {
+            let mut float = false;
+            if self.src[self.pos..].starts_with(b"0x") || self.src[self.pos..].starts_with(b"0X") {
+                self.pos += 2;
+                while self.pos < self.src.len() && self.src[self.pos].is_ascii_hexdigit() {
+                    self.pos += 1;
+                }
+            } else {
+                while self.pos < self.src.len() {
+                    let b = self.src[self.pos];
+                    if b.is_ascii_digit() {
+                        self.pos += 1;
+                    } else if b == b'.' {
+                        float = true;
+                        self.pos += 1;
+                    } else if b == b'e' || b == b'E' {
+                        float = true;
+                        self.pos += 1;
+                        if self.pos < self.src.len()
+                            && (self.src[self.pos] == b'-' || self.src[self.pos] == b'+')
+                        {
+                            self.pos += 1;
+                        }
+                    } else {
+                        break;
+                    }
+                }
+            }
+            let text = self.slice(start);
+            return Ok(Some((
+                if float {
+                    Token::Float(text)
+                } else {
+                    Token::Int(text)
+                },
+                line,
+            )));
+        }
+
+        if c == b'"' || c == b'\'' {
+            self.pos += 1;
+            let mut out = Vec::new();
+            loop {
+                let b = *self
+                    .src
+                    .get(self.pos)
+                    .ok_or_else(|| "unterminated string".to_string())?;
+                self.pos += 1;
+                if b == c {
+                    break;
+                }
+                if b == b'\n' {
+                    return Err("unterminated string".to_string());
+                }
+                if b != b'\\' {
+                    out.push(b);
+                    continue;
+                }
+                let escaped = *self
+                    .src
+                    .get(self.pos)
+                    .ok_or_else(|| "unterminated string".to_string())?;
+                self.pos += 1;
+                match escaped {
+                    b'n' => out.push(b'\n'),
+                    b'r' => out.push(b'\r'),
+                    b't' => out.push(b'\t'),
+                    b'0'..=b'7' => {
+                        let mut val = (escaped - b'0') as u32;
+                        for _ in 0..2 {
+                            match self.src.get(self.pos) {
+                                Some(d @ b'0'..=b'7') => {
+                                    val = val * 8 + (d - b'0') as u32;
+                                    self.pos += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        out.push(val as u8);
+                    }
+                    b'x' | b'X' => {
+                        let mut val = 0u32;
+                        for _ in 0..2 {
+                            match self.src.get(self.pos) {
+                                Some(d) if d.is_ascii_hexdigit() => {
+                                    val = val * 16 + (*d as char).to_digit(16).unwrap_or(0);
+                                    self.pos += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        out.push(val as u8);
+                    }
+                    other => out.push(other),
+                }
+            }
+            return Ok(Some((
+                Token::Str(String::from_utf8_lossy(&out).into_owned()),
+                line,
+            )));
+        }
+
+        self.pos += 1;
+        Ok(Some((Token::Symbol(c as char), line)))
+    }
+
+    fn peek_byte(&self, offset: usize) -> Option<u8> {
+        self.src.get(self.pos + offset).copied()
+    }
+
+    fn slice(&self, start: usize) -> String {
+        String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()
+    }
+}
+
+struct Parser {
+    file: String,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    package: String,
+}
+
+/// Parses the contents of a single `.proto` file. Type references are left
+/// unresolved as `FieldType::Named` until the file is added to a pool.
+pub fn parse(file: &str, src: &str) -> Result<FileDescriptor, BuildError> {
+    let mut lexer = Lexer {
+        src: src.as_bytes(),
+        pos: 0,
+        line: 1,
+    };
+    let mut tokens = Vec::new();
+    loop {
+        match lexer.next() {
+            Ok(Some(token)) => tokens.push(token),
+            Ok(None) => break,
+            Err(message) => {
+                return Err(BuildError::Parse {
+                    file: file.to_owned(),
+                    line: lexer.line,
+                    message,
+                })
+            }
+        }
+    }
+
+    let mut parser = Parser {
+        file: file.to_owned(),
+        tokens,
+        pos: 0,
+        package: String::new(),
+    };
+    parser.file_descriptor()
+}
+
+impl Parser {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, BuildError> {
+        let line = self
+            .tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map(|(_, line)| *line)
+            .unwrap_or(1);
+        Err(BuildError::Parse {
+            file: self.file.clone(),
+            line,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, BuildError> {
+        match self.tokens.get(self.pos) {
+            Some((token, _)) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => self.error("unexpected end of file"),
+        }
+    }
+
+    fn is_symbol(&self, c: char) -> bool {
+        self.peek() == Some(&Token::Symbol(c))
+    }
+
+    fn is_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(i)) if i == ident)
+    }
+
+    fn eat_symbol(&mut self, c: char) -> bool {
+        if self.is_symbol(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        if self.is_ident(ident) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_symbol(&mut self, c: char) -> Result<(), BuildError> {
+        if self.eat_symbol(c) {
+            return Ok(());
+        }
+        self.unexpected(&format!("'{c}'"))
+    }
+
+    /// Fails on the next token, saying what was `expected` there instead if
+    /// anything in particular.
+    fn unexpected<T>(&self, expected: &str) -> Result<T, BuildError> {
+        match self.peek() {
+            None => self.error("unexpected end of file"),
+            Some(token) if expected.is_empty() => self.error(format!("unexpected {token:?}")),
+            Some(token) => self.error(format!("expected {expected}, found {token:?}")),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, BuildError> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            other => {
+                self.pos -= 1;
+                self.error(format!("expected identifier, found {other:?}"))
+            }
+        }
+    }
+
+    fn full_ident(&mut self) -> Result<String, BuildError> {
+        let mut name = String::new();
+        if self.eat_symbol('.') {
+            name.push('.');
+        }
+        name.push_str(&self.ident()?);
+        while self.eat_symbol('.') {
+            name.push('.');
+            name.push_str(&self.ident()?);
+        }
+        Ok(name)
+    }
+
+    fn string(&mut self) -> Result<String, BuildError> {
+        let mut out = match self.next()? {
+            Token::Str(s) => s,
+            other => {
+                self.pos -= 1;
+                return self.error(format!("expected string, found {other:?}"));
+            }
+        };
+        while let Some(Token::Str(s)) = self.peek() {
+            out.push_str(s);
+            self.pos += 1;
+        }
+        Ok(out)
+    }
+
+    fn int(&mut self) -> Result<i64, BuildError> {
+        let negative = self.eat_symbol('-');
+        let text = match self.next()? {
+            Token::Int(text) => text,
+            other => {
+                self.pos -= 1;
+                return self.error(format!("expected integer, found {other:?}"));
+            }
+        };
+        let value = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            i64::from_str_radix(hex, 16)
+        } else if text.len() > 1 && text.starts_with('0') {
+            i64::from_str_radix(&text[1..], 8)
+        } else {
+            text.parse::<i64>()
+        };
+        match value {
+            Ok(value) if negative => Ok(-value),
+            Ok(value) => Ok(value),
+            Err(_) => self.error(format!("invalid integer {text}")),
+        }
+    }
+
+    fn scoped(&self, scope: &str, name: &str) -> String {
+        if scope.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{scope}.{name}")
+        }
+    }
+
+    fn file_descriptor(&mut self) -> Result<FileDescriptor, BuildError> {
+        let mut file = FileDescriptor {
+            name: self.file.clone(),
+            package: String::new(),
+            imports: Vec::new(),
+            messages: Vec::new(),
+            enums: Vec::new(),
+            services: Vec::new(),
+            options: Vec::new(),
+        };
+
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Symbol(';') => self.pos += 1,
+                Token::Ident(ref keyword) => match keyword.as_str() {
+                    "syntax" | "edition" => {
+                        self.pos += 1;
+                        self.expect_symbol('=')?;
+                        self.string()?;
+                        self.expect_symbol(';')?;
+                    }
+                    "package" => {
+                        self.pos += 1;
+                        self.package = self.full_ident()?;
+                        file.package = self.package.clone();
+                        self.expect_symbol(';')?;
+                    }
+                    "import" => {
+                        self.pos += 1;
+                        if !self.eat_ident("public") {
+                            self.eat_ident("weak");
+                        }
+                        file.imports.push(self.string()?);
+                        self.expect_symbol(';')?;
+                    }
+                    "option" => {
+                        self.pos += 1;
+                        file.options.push(self.option()?);
+                        self.expect_symbol(';')?;
+                    }
+                    "message" => {
+                        self.pos += 1;
+                        let scope = self.package.clone();
+                        file.messages.push(self.message(&scope)?);
+                    }
+                    "enum" => {
+                        self.pos += 1;
+                        let scope = self.package.clone();
+                        file.enums.push(self.enumeration(&scope)?);
+                    }
+                    "service" => {
+                        self.pos += 1;
+                        file.services.push(self.service()?);
+                    }
+                    "extend" => {
+                        self.pos += 1;
+                        self.full_ident()?;
+                        self.skip_block()?;
+                    }
+                    other => return self.error(format!("unexpected '{other}'")),
+                },
+                other => return self.error(format!("unexpected {other:?}")),
+            }
+        }
+        Ok(file)
+    }
+
+    fn skip_block(&mut self) -> Result<(), BuildError> {
+        self.expect_symbol('{')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_statement(&mut self) -> Result<(), BuildError> {
+        while self.next()? != Token::Symbol(';') {}
+        Ok(())
+    }
+
+    fn option_name(&mut self) -> Result<String, BuildError> {
+        let mut name = String::new();
+        loop {
+            if self.eat_symbol('(') {
+                name.push('(');
+                name.push_str(self.full_ident()?.trim_start_matches('.'));
+                self.expect_symbol(')')?;
+                name.push(')');
+            } else {
+                name.push_str(&self.ident()?);
+            }
+            if !self.eat_symbol('.') {
+                return Ok(name);
+            }
+            name.push('.');
+        }
+    }
+
+    fn option(&mut self) -> Result<OptionDescriptor, BuildError> {
+        let name = self.option_name()?;
+        self.expect_symbol('=')?;
+        let value = self.option_value()?;
+        Ok(OptionDescriptor { name, value })
+    }
+
+    fn option_value(&mut self) -> Result<OptionValue, BuildError> {
+        match self.peek().cloned() {
+            Some(Token::Str(_)) => Ok(OptionValue::String(self.string()?)),
+            Some(Token::Ident(ident)) => {
+                self.pos += 1;
+                Ok(OptionValue::Ident(ident))
+            }
+            Some(Token::Symbol('{')) => {
+                let start = self.pos;
+                self.skip_block()?;
+                let raw = self.tokens[start..self.pos]
+                    .iter()
+                    .map(|(token, _)| match token {
+                        Token::Ident(s) | Token::Int(s) | Token::Float(s) => s.clone(),
+                        Token::Str(s) => format!("{s:?}"),
+                        Token::Symbol(c) => c.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Ok(OptionValue::Aggregate(raw))
+            }
+            Some(Token::Float(_)) => self.float(false),
+            Some(Token::Symbol('-')) | Some(Token::Symbol('+')) => {
+                let negative = self.is_symbol('-');
+                match self.peek_at(1) {
+                    Some(Token::Float(_)) => {
+                        self.pos += 1;
+                        self.float(negative)
+                    }
+                    Some(Token::Ident(ident)) => {
+                        let value = format!("{}{ident}", if negative { "-" } else { "" });
+                        self.pos += 2;
+                        Ok(OptionValue::Ident(value))
+                    }
+                    _ => {
+                        self.eat_symbol('+');
+                        Ok(OptionValue::Int(self.int()?))
+                    }
+                }
+            }
+            _ => Ok(OptionValue::Int(self.int()?)),
+        }
+    }
+
+    fn float(&mut self, negative: bool) -> Result<OptionValue, BuildError> {
+        match self.next()? {
+            Token::Float(text) => match text.parse::<f64>() {
+                Ok(value) if negative => Ok(OptionValue::Float(-value)),
+                Ok(value) => Ok(OptionValue::Float(value)),
+                Err(_) => self.error(format!("invalid float {text}")),
+            },
+            other => self.error(format!("expected float, found {other:?}")),
+        }
+    }
+
+    fn field_options(&mut self) -> Result<Vec<OptionDescriptor>, BuildError> {
+        let mut options = Vec::new();
+        if self.eat_symbol('[') {
+            loop {
+                options.push(self.option()?);
+                if !self.eat_symbol(',') {
+                    break;
+                }
+            }
+            self.expect_symbol(']')?;
+        }
+        Ok(options)
+    }
+
+    fn field_number(&mut self) -> Result<u32, BuildError> {
+        let number = self.int()?;
+        match u32::try_from(number) {
+            Ok(number) if number > 0 => Ok(number),
+            _ => self.error(format!("invalid field number {number}")),
+        }
+    }
+
+    fn field_type(&mut self) -> Result<FieldType, BuildError> {
+        let name = self.full_ident()?;
+        Ok(match ScalarType::from_name(&name) {
+            Some(scalar) => FieldType::Scalar(scalar),
+            None => FieldType::Named(name),
+        })
+    }
+
+    fn field(&mut self, label: Label, oneof: Option<usize>) -> Result<FieldDescriptor, BuildError> {
+        if self.is_ident("group") {
+            return self.error("groups are not supported");
+        }
+        let ty = self.field_type()?;
+        let name = self.ident()?;
+        self.expect_symbol('=')?;
+        let number = self.field_number()?;
+        let options = self.field_options()?;
+        self.expect_symbol(';')?;
+        Ok(FieldDescriptor {
+            name,
+            number,
+            label,
+            ty,
+            oneof,
+            options,
+        })
+    }
+
+    fn map_field(&mut self) -> Result<FieldDescriptor, BuildError> {
+        self.expect_symbol('<')?;
+        let key = self.field_type()?;
+        self.expect_symbol(',')?;
+        let value = self.field_type()?;
+        self.expect_symbol('>')?;
+        let name = self.ident()?;
+        self.expect_symbol('=')?;
+        let number = self.field_number()?;
+        let options = self.field_options()?;
+        self.expect_symbol(';')?;
+        Ok(FieldDescriptor {
+            name,
+            number,
+            label: Label::Singular,
+            ty: FieldType::Map(Box::new(key), Box::new(value)),
+            oneof: None,
+            options,
+        })
+    }
+
+    fn message(&mut self, scope: &str) -> Result<MessageDescriptor, BuildError> {
+        let name = self.ident()?;
+        let full_name = self.scoped(scope, &name);
+        let mut message = MessageDescriptor {
+            name,
+            full_name: full_name.clone(),
+            fields: Vec::new(),
+            oneofs: Vec::new(),
+            messages: Vec::new(),
+            enums: Vec::new(),
+            options: Vec::new(),
+        };
+
+        self.expect_symbol('{')?;
+        while !self.eat_symbol('}') {
+            let keyword = match self.peek() {
+                Some(Token::Ident(ident)) => ident.clone(),
+                Some(Token::Symbol(';')) => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ => return self.unexpected(""),
+            };
+            match keyword.as_str() {
+                "message" if matches!(self.peek_at(1), Some(Token::Ident(_))) => {
+                    self.pos += 1;
+                    message.messages.push(self.message(&full_name)?);
+                }
+                "enum" if matches!(self.peek_at(1), Some(Token::Ident(_))) => {
+                    self.pos += 1;
+                    message.enums.push(self.enumeration(&full_name)?);
+                }
+                "oneof" if matches!(self.peek_at(1), Some(Token::Ident(_))) => {
+                    self.pos += 1;
+                    let index = message.oneofs.len();
+                    message.oneofs.push(self.ident()?);
+                    self.expect_symbol('{')?;
+                    while !self.eat_symbol('}') {
+                        if self.eat_symbol(';') {
+                            continue;
+                        }
+                        if self.eat_ident("option") {
+                            self.skip_statement()?;
+                            continue;
+                        }
+                        message
+                            .fields
+                            .push(self.field(Label::Singular, Some(index))?);
+                    }
+                }
+                "map" if self.peek_at(1) == Some(&Token::Symbol('<')) => {
+                    self.pos += 1;
+                    message.fields.push(self.map_field()?);
+                }
+                "option" => {
+                    self.pos += 1;
+                    message.options.push(self.option()?);
+                    self.expect_symbol(';')?;
+                }
+                "reserved" | "extensions" => {
+                    self.pos += 1;
+                    self.skip_statement()?;
+                }
+                "extend" => {
+                    self.pos += 1;
+                    self.full_ident()?;
+                    self.skip_block()?;
+                }
+                "repeated" | "optional" | "required"
+                    if !matches!(self.peek_at(1), Some(Token::Symbol('='))) =>
+                {
+                    self.pos += 1;
+                    let label = match keyword.as_str() {
+                        "repeated" => Label::Repeated,
+                        "optional" => Label::Optional,
+                        _ => Label::Required,
+                    };
+                    message.fields.push(self.field(label, None)?);
+                }
+                _ => message.fields.push(self.field(Label::Singular, None)?),
+            }
+        }
+        Ok(message)
+    }
+
+    fn enumeration(&mut self, scope: &str) -> Result<EnumDescriptor, BuildError> {
+        let name = self.ident()?;
+        let mut descriptor = EnumDescriptor {
+            full_name: self.scoped(scope, &name),
+            name,
+            values: Vec::new(),
+            options: Vec::new(),
+        };
+
+        self.expect_symbol('{')?;
+        while !self.eat_symbol('}') {
+            if self.eat_symbol(';') {
+                continue;
+            }
+            if self.is_ident("option") && !matches!(self.peek_at(1), Some(Token::Symbol('='))) {
+                self.pos += 1;
+                descriptor.options.push(self.option()?);
+                self.expect_symbol(';')?;
+                continue;
+            }
+            if self.is_ident("reserved") && !matches!(self.peek_at(1), Some(Token::Symbol('='))) {
+                self.skip_statement()?;
+                continue;
+            }
+            let name = self.ident()?;
+            self.expect_symbol('=')?;
+            let number = self.int()?;
+            let number = match i32::try_from(number) {
+                Ok(number) => number,
+                Err(_) => return self.error(format!("invalid enum value {number}")),
+            };
+            self.field_options()?;
+            self.expect_symbol(';')?;
+            descriptor.values.push(EnumValueDescriptor { name, number });
+        }
+        Ok(descriptor)
+    }
+
+    fn method_type(&mut self) -> Result<(String, bool), BuildError> {
+        self.expect_symbol('(')?;
+        let streaming = self.is_ident("stream")
+            && !matches!(
+                self.peek_at(1),
+                Some(Token::Symbol(')')) | Some(Token::Symbol('.'))
+            );
+        if streaming {
+            self.pos += 1;
+        }
+        let name = self.full_ident()?;
+        self.expect_symbol(')')?;
+        Ok((name, streaming))
+    }
+
+    fn service(&mut self) -> Result<ServiceDescriptor, BuildError> {
+        let name = self.ident()?;
+        let mut service = ServiceDescriptor {
+            full_name: self.scoped(&self.package, &name),
+            name,
+            methods: Vec::new(),
+        };
+
+        self.expect_symbol('{')?;
+        while !self.eat_symbol('}') {
+            if self.eat_symbol(';') {
+                continue;
+            }
+            if self.eat_ident("option") {
+                self.skip_statement()?;
+                continue;
+            }
+            if !self.eat_ident("rpc") {
+                return self.unexpected("");
+            }
+            let name = self.ident()?;
+            let (input, client_streaming) = self.method_type()?;
+            if !self.eat_ident("returns") {
+                return self.unexpected("'returns'");
+            }
+            let (output, server_streaming) = self.method_type()?;
+            if self.is_symbol('{') {
+                self.skip_block()?;
+            } else {
+                self.expect_symbol(';')?;
+            }
+            service.methods.push(MethodDescriptor {
+                name,
+                input,
+                output,
+                client_streaming,
+                server_streaming,
+            });
+        }
+        Ok(service)
+    }
+}
@@ -0,0 +1,192 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::ast::{EnumDescriptor, FieldType, FileDescriptor, MessageDescriptor};
+use crate::{types, BuildError};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Symbol {
+    Message,
+    Enum,
+}
+
+/// A set of parsed `.proto` files with all type references resolved to full names.
+#[derive(Debug, Default)]
+pub struct DescriptorPool {
+    files: Vec<FileDescriptor>,
+    symbols: HashMap<String, Symbol>,
+}
+
+impl DescriptorPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn files(&self) -> &[FileDescriptor] {
+        &self.files
+    }
+
+    pub fn file(&self, name: &str) -> Option<&FileDescriptor> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    pub fn message(&self, full_name: &str) -> Option<&MessageDescriptor> {
+        let mut found = None;
+        for file in &self.files {
+            file.walk_messages(&mut |m| {
+                if m.full_name == full_name {
+                    found = Some(m);
+                }
+            });
+        }
+        found
+    }
+
+    pub fn enumeration(&self, full_name: &str) -> Option<&EnumDescriptor> {
+        let mut found = None;
+        for file in &self.files {
+            file.walk_enums(&mut |e| {
+                if e.full_name == full_name {
+                    found = Some(e);
+                }
+            });
+        }
+        found
+    }
+
+    /// Adds a file to the pool. Every file it imports must already have been added.
+    pub fn add(&mut self, mut file: FileDescriptor) -> Result<(), BuildError> {
+        let mut symbols = Vec::new();
+        file.walk_messages(&mut |m| symbols.push((m.full_name.clone(), Symbol::Message)));
+        file.walk_enums(&mut |e| symbols.push((e.full_name.clone(), Symbol::Enum)));
+        let mut names = HashSet::new();
+        for (name, _) in &symbols {
+            if self.symbols.contains_key(name) || !names.insert(name) {
+                return Err(BuildError::DuplicateSymbol {
+                    file: file.name.clone(),
+                    name: name.clone(),
+                });
+            }
+        }
+        self.symbols.extend(symbols);
+
+        let name = file.name.clone();
+        for message in &mut file.messages {
+            self.resolve_message(&name, message)?;
+        }
+        for service in &mut file.services {
+            for method in &mut service.methods {
+                method.input = self.resolve_message_name(&name, &file.package, &method.input)?;
+                method.output = self.resolve_message_name(&name, &file.package, &method.output)?;
+            }
+        }
+        self.files.push(file);
+        Ok(())
+    }
+
+    fn resolve_message(
+        &self,
+        file: &str,
+        message: &mut MessageDescriptor,
+    ) -> Result<(), BuildError> {
+        types::check_message(file, message)?;
+        for field in &mut message.fields {
+            types::check(
+                file,
+                &format!("{}.{}", message.full_name, field.name),
+                field,
+            )?;
+            field.ty = self.resolve_type(file, &message.full_name, &field.ty)?;
+        }
+        for nested in &mut message.messages {
+            self.resolve_message(file, nested)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_type(
+        &self,
+        file: &str,
+        scope: &str,
+        ty: &FieldType,
+    ) -> Result<FieldType, BuildError> {
+        Ok(match ty {
+            FieldType::Named(name) => {
+                let full_name =
+                    self.lookup(scope, name)
+                        .ok_or_else(|| BuildError::UnresolvedType {
+                            file: file.to_owned(),
+                            name: name.clone(),
+                        })?;
+                match self.symbols[&full_name] {
+                    Symbol::Message => FieldType::Message(full_name),
+                    Symbol::Enum => FieldType::Enum(full_name),
+                }
+            }
+            FieldType::Map(key, value) => FieldType::Map(
+                Box::new(self.resolve_type(file, scope, key)?),
+                Box::new(self.resolve_type(file, scope, value)?),
+            ),
+            other => other.clone(),
+        })
+    }
+
+    fn resolve_message_name(
+        &self,
+        file: &str,
+        scope: &str,
+        name: &str,
+    ) -> Result<String, BuildError> {
+        match self.lookup(scope, name) {
+            Some(full_name) if self.symbols[&full_name] == Symbol::Message => Ok(full_name),
+            _ => Err(BuildError::UnresolvedType {
+                file: file.to_owned(),
+                name: name.to_owned(),
+            }),
+        }
+    }
+
+    /// Resolves `name` following protobuf scoping rules: fully qualified names
+    /// start with a dot, anything else is searched from the innermost scope outwards.
+    fn lookup(&self, scope: &str, name: &str) -> Option<String> {
+        if let Some(full_name) = name.strip_prefix('.') {
+            return self
+                .symbols
+                .contains_key(full_name)
+                .then(|| full_name.to_owned());
+        }
+
+        let mut scope = scope;
+        loop {
+            let candidate = if scope.is_empty() {
+                name.to_owned()
+            } else {
+                format!("{scope}.{name}")
+            };
+            if self.symbols.contains_key(&candidate) {
+                return Some(candidate);
+            }
+            if scope.is_empty() {
+                return None;
+            }
+            scope = match scope.rfind('.') {
+                Some(i) => &scope[..i],
+                None => "",
+            };
+        }
+    }
+}
@@ -0,0 +1,113 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::ast::{FieldDescriptor, FieldType, Label, MessageDescriptor, ScalarType};
+use crate::generator::{members_by_number, oneof_fields, plain_fields, Member};
+use crate::types::is_delimited;
+use crate::DescriptorPool;
+use polyglot_rs::{EnumSchema, Field, MessageSchema, Schema};
+
+impl DescriptorPool {
+    /// Describes every message and enum of the pool, by full name, as a runtime
+    /// [`Schema`] with `root` as its root message. Fields are listed in the order
+    /// generated code writes them, so the schema can decode, validate and check
+    /// the compatibility of messages from the generated types.
+    pub fn schema(&self, root: &str) -> Schema {
+        let mut schema = Schema::new(root);
+        for file in self.files() {
+            file.walk_messages(&mut |m| schema.messages.push(message_schema(m)));
+            file.walk_enums(&mut |e| {
+                let mut values = EnumSchema::new(&e.full_name);
+                for value in &e.values {
+                    values = values.with_value(&value.name, value.number as u32);
+                }
+                schema.enums.push(values);
+            });
+        }
+        schema
+    }
+}
+
+fn message_schema(message: &MessageDescriptor) -> MessageSchema {
+    let mut schema = MessageSchema::new(&message.full_name);
+    if is_delimited(message) {
+        schema = schema.delimited();
+        for member in members_by_number(message) {
+            schema.fields.push(match member {
+                Member::Field(field) => field_schema(field),
+                Member::Oneof(index) => oneof_schema(message, index),
+            });
+        }
+        return schema;
+    }
+    for field in plain_fields(message) {
+        schema.fields.push(field_schema(field));
+    }
+    for index in 0..message.oneofs.len() {
+        schema.fields.push(oneof_schema(message, index));
+    }
+    schema
+}
+
+fn field_schema(field: &FieldDescriptor) -> Field {
+    let ty = field_type(&field.ty);
+    Field {
+        name: field.name.clone(),
+        number: field.number,
+        ty: match field.label {
+            Label::Repeated => polyglot_rs::FieldType::array(ty),
+            Label::Optional if !matches!(field.ty, FieldType::Message(_)) => {
+                polyglot_rs::FieldType::optional(ty)
+            }
+            _ => ty,
+        },
+    }
+}
+
+fn oneof_schema(message: &MessageDescriptor, index: usize) -> Field {
+    Field {
+        name: message.oneofs[index].clone(),
+        number: oneof_fields(message, index)
+            .map(|f| f.number)
+            .min()
+            .unwrap_or(u32::MAX),
+        ty: polyglot_rs::FieldType::Oneof(
+            oneof_fields(message, index)
+                .map(|f| (f.number, field_type(&f.ty)))
+                .collect(),
+        ),
+    }
+}
+
+fn field_type(ty: &FieldType) -> polyglot_rs::FieldType {
+    use polyglot_rs::FieldType as Runtime;
+    match ty {
+        FieldType::Scalar(scalar) => match scalar {
+            ScalarType::Double => Runtime::F64,
+            ScalarType::Float => Runtime::F32,
+            ScalarType::Int32 | ScalarType::Sint32 | ScalarType::Sfixed32 => Runtime::I32,
+            ScalarType::Int64 | ScalarType::Sint64 | ScalarType::Sfixed64 => Runtime::I64,
+            ScalarType::Uint32 | ScalarType::Fixed32 => Runtime::U32,
+            ScalarType::Uint64 | ScalarType::Fixed64 => Runtime::U64,
+            ScalarType::Bool => Runtime::Bool,
+            ScalarType::String => Runtime::String,
+            ScalarType::Bytes => Runtime::Bytes,
+        },
+        FieldType::Enum(name) => Runtime::Enum(name.clone()),
+        FieldType::Message(name) | FieldType::Named(name) => Runtime::Message(name.clone()),
+        FieldType::Map(key, value) => Runtime::map(field_type(key), field_type(value)),
+    }
+}
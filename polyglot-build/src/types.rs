@@ -0,0 +1,257 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::ast::{
+    find_option, FieldDescriptor, FieldType, MessageDescriptor, OptionValue, ScalarType,
+};
+use crate::{BuildError, BytesType, Config, MapType, StringType};
+use std::collections::HashSet;
+
+const MAP_TYPE: &str = "(polyglot.map_type)";
+const BYTES_TYPE: &str = "(polyglot.bytes_type)";
+const STRING_TYPE: &str = "(polyglot.string_type)";
+const ARRAY_ENCODING: &str = "(polyglot.array_encoding)";
+const DELIMITED: &str = "(polyglot.delimited)";
+
+/// The built-in files that can be imported without being found in the include
+/// paths. `descriptor.proto` is only imported for the options it declares, which
+/// are only referred to from `extend` blocks the parser skips, so it is empty.
+pub(crate) fn builtin(name: &str) -> Option<&'static str> {
+    match name {
+        "polyglot/options.proto" => Some(include_str!("../proto/polyglot/options.proto")),
+        "google/protobuf/descriptor.proto" => {
+            Some("syntax = \"proto2\";\n\npackage google.protobuf;\n")
+        }
+        _ => None,
+    }
+}
+
+impl MapType {
+    fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "HASH_MAP" => Some(MapType::HashMap),
+            "BTREE_MAP" => Some(MapType::BTreeMap),
+            "INDEX_MAP" => Some(MapType::IndexMap),
+            _ => None,
+        }
+    }
+}
+
+impl BytesType {
+    fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "VEC" => Some(BytesType::Vec),
+            "BYTES" => Some(BytesType::Bytes),
+            _ => None,
+        }
+    }
+}
+
+impl StringType {
+    fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "STRING" => Some(StringType::String),
+            "ARC_STR" => Some(StringType::ArcStr),
+            "SMOL_STR" => Some(StringType::SmolStr),
+            _ => None,
+        }
+    }
+}
+
+/// How a repeated number is written, set with `(polyglot.array_encoding)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArrayEncoding {
+    Unpacked,
+    Packed,
+    Delta,
+    RunLength,
+}
+
+impl ArrayEncoding {
+    fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "UNPACKED" => Some(ArrayEncoding::Unpacked),
+            "PACKED" => Some(ArrayEncoding::Packed),
+            "DELTA" => Some(ArrayEncoding::Delta),
+            "RUN_LENGTH" => Some(ArrayEncoding::RunLength),
+            _ => None,
+        }
+    }
+}
+
+/// The Rust types the maps, bytes and strings of a field are generated as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Types {
+    pub(crate) map: MapType,
+    pub(crate) bytes: BytesType,
+    pub(crate) string: StringType,
+}
+
+impl Types {
+    /// The types set through the `Config`, overridden by the options of `field`.
+    pub(crate) fn of(config: &Config, field: &FieldDescriptor) -> Types {
+        Types {
+            map: option(field, MAP_TYPE)
+                .and_then(MapType::from_option)
+                .unwrap_or(config.map_type),
+            bytes: option(field, BYTES_TYPE)
+                .and_then(BytesType::from_option)
+                .unwrap_or(config.bytes_type),
+            string: option(field, STRING_TYPE)
+                .and_then(StringType::from_option)
+                .unwrap_or(config.string_type),
+        }
+    }
+}
+
+fn option<'a>(field: &'a FieldDescriptor, name: &str) -> Option<&'a str> {
+    match find_option(&field.options, name)? {
+        OptionValue::Ident(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Fails when `field` sets one of the options of `polyglot/options.proto` to
+/// something that isn't one of its values.
+pub(crate) fn check(
+    file: &str,
+    full_name: &str,
+    field: &FieldDescriptor,
+) -> Result<(), BuildError> {
+    for name in [MAP_TYPE, BYTES_TYPE, STRING_TYPE] {
+        let valid = match find_option(&field.options, name) {
+            None => true,
+            Some(OptionValue::Ident(value)) => match name {
+                MAP_TYPE => MapType::from_option(value).is_some(),
+                BYTES_TYPE => BytesType::from_option(value).is_some(),
+                _ => StringType::from_option(value).is_some(),
+            },
+            Some(_) => false,
+        };
+        if !valid {
+            return Err(BuildError::InvalidOption {
+                file: file.to_owned(),
+                field: full_name.to_owned(),
+                option: name.to_owned(),
+            });
+        }
+    }
+    let valid = match find_option(&field.options, ARRAY_ENCODING) {
+        None => true,
+        Some(OptionValue::Ident(value)) => match ArrayEncoding::from_option(value) {
+            None => false,
+            Some(ArrayEncoding::Unpacked) => true,
+            Some(ArrayEncoding::Delta) => field.is_repeated() && deltable(&field.ty),
+            Some(ArrayEncoding::Packed) => field.is_repeated() && packable(&field.ty),
+            Some(ArrayEncoding::RunLength) => {
+                field.is_repeated()
+                    && packable(&field.ty)
+                    && field.ty != FieldType::Scalar(ScalarType::Bool)
+            }
+        },
+        Some(_) => false,
+    };
+    if !valid {
+        return Err(BuildError::InvalidOption {
+            file: file.to_owned(),
+            field: full_name.to_owned(),
+            option: ARRAY_ENCODING.to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Whether arrays of `ty` can be packed, which is the case for numbers and
+/// bools.
+pub(crate) fn packable(ty: &FieldType) -> bool {
+    matches!(
+        ty,
+        FieldType::Scalar(
+            ScalarType::Bool
+                | ScalarType::Double
+                | ScalarType::Float
+                | ScalarType::Int32
+                | ScalarType::Int64
+                | ScalarType::Uint32
+                | ScalarType::Uint64
+                | ScalarType::Sint32
+                | ScalarType::Sint64
+                | ScalarType::Fixed32
+                | ScalarType::Fixed64
+                | ScalarType::Sfixed32
+                | ScalarType::Sfixed64
+        )
+    )
+}
+
+/// Whether arrays of `ty` can be delta encoded, which is the case for 64-bit
+/// integers.
+pub(crate) fn deltable(ty: &FieldType) -> bool {
+    matches!(
+        ty,
+        FieldType::Scalar(
+            ScalarType::Int64
+                | ScalarType::Uint64
+                | ScalarType::Sint64
+                | ScalarType::Fixed64
+                | ScalarType::Sfixed64
+        )
+    )
+}
+
+/// How the repeated `field` is written. Arrays of numbers decode the same
+/// whichever way they were written.
+pub(crate) fn array_encoding(field: &FieldDescriptor) -> ArrayEncoding {
+    match find_option(&field.options, ARRAY_ENCODING) {
+        Some(OptionValue::Ident(value)) => {
+            ArrayEncoding::from_option(value).unwrap_or(ArrayEncoding::Unpacked)
+        }
+        _ => ArrayEncoding::Unpacked,
+    }
+}
+
+/// Whether `message` is written with its length in front of it, which lets
+/// fields be added to the end of it without breaking older decoders.
+pub(crate) fn is_delimited(message: &MessageDescriptor) -> bool {
+    matches!(
+        find_option(&message.options, DELIMITED),
+        Some(OptionValue::Ident(value)) if value == "true"
+    )
+}
+
+/// Fails when two fields of `message` share a number or when it sets
+/// `delimited` to something other than a bool.
+pub(crate) fn check_message(file: &str, message: &MessageDescriptor) -> Result<(), BuildError> {
+    let mut numbers = HashSet::new();
+    for field in &message.fields {
+        if !numbers.insert(field.number) {
+            return Err(BuildError::DuplicateFieldNumber {
+                file: file.to_owned(),
+                field: format!("{}.{}", message.full_name, field.name),
+                number: field.number,
+            });
+        }
+    }
+    match find_option(&message.options, DELIMITED) {
+        None => Ok(()),
+        Some(OptionValue::Ident(value)) if value == "true" || value == "false" => Ok(()),
+        Some(_) => Err(BuildError::InvalidOption {
+            file: file.to_owned(),
+            field: message.full_name.clone(),
+            option: DELIMITED.to_owned(),
+        }),
+    }
+}
@@ -0,0 +1,32 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+#![allow(dead_code)]
+
+use polyglot_build::{parse, Config, DescriptorPool};
+
+/// Resolves a single file into a pool, as `test.proto`.
+pub fn pool(src: &str) -> DescriptorPool {
+    let mut pool = DescriptorPool::new();
+    pool.add(parse("test.proto", src).unwrap()).unwrap();
+    pool
+}
+
+/// Generates the Rust source for a single file.
+pub fn generate(config: &Config, src: &str) -> String {
+    let pool = pool(src);
+    config.generate(&pool, pool.file("test.proto").unwrap())
+}
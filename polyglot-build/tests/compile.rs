@@ -0,0 +1,48 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_build::{BuildError, Config};
+use std::env;
+use std::fs;
+
+#[test]
+fn test_duplicate_output() {
+    let dir = env::temp_dir().join(format!("polyglot-build-{}", std::process::id()));
+    for package in ["a", "b"] {
+        fs::create_dir_all(dir.join(package)).unwrap();
+        fs::write(
+            dir.join(package).join("common.proto"),
+            format!("syntax = \"proto3\";\npackage {package};\nmessage Common {{}}\n"),
+        )
+        .unwrap();
+    }
+    let out = dir.join("out");
+    fs::create_dir_all(&out).unwrap();
+
+    let result = Config::new().out_dir(&out).compile_protos(
+        &[dir.join("a/common.proto"), dir.join("b/common.proto")],
+        &[&dir],
+    );
+    fs::remove_dir_all(&dir).unwrap();
+
+    match result {
+        Err(BuildError::DuplicateOutput { file, other }) => {
+            assert_eq!(file, "b/common.proto");
+            assert_eq!(other, "a/common.proto");
+        }
+        result => panic!("unexpected result: {result:?}"),
+    }
+}
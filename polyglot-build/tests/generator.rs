@@ -0,0 +1,384 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+mod common;
+
+use common::generate;
+use polyglot_build::{parse, BytesType, Config, DescriptorPool, MapType, StringType};
+
+#[test]
+fn test_derives() {
+    let src = r#"
+        syntax = "proto3";
+
+        enum Level {
+          LEVEL_LOW = 1;
+          LEVEL_NONE = 0;
+        }
+
+        message Exact {
+          string name = 1;
+          Level level = 2;
+        }
+
+        message Approximate {
+          double value = 1;
+        }
+
+        message Indexed {
+          map<string, Exact> entries = 1;
+        }
+
+        // Contains a float through the cycle, so neither side gets `Eq`.
+        message Left {
+          Right right = 1;
+        }
+
+        message Right {
+          Left left = 1;
+          float weight = 2;
+        }
+    "#;
+    let out = generate(&Config::new(), src);
+
+    assert!(out.contains(
+        "#[derive(Debug, Eq, PartialEq, TryFromPrimitive, Copy, Clone, Hash)]\n#[repr(u32)]\npub enum Level {\n    LEVEL_LOW = 1,\n    LEVEL_NONE = 0,\n}"
+    ));
+    assert!(out.contains(
+        "impl Default for Level {\n    fn default() -> Self {\n        Level::LEVEL_NONE\n    }\n}"
+    ));
+    assert!(
+        out.contains("#[derive(Debug, Clone, PartialEq, Default, Eq, Hash)]\npub struct Exact {")
+    );
+    assert!(out.contains("#[derive(Debug, Clone, PartialEq, Default)]\npub struct Approximate {"));
+    assert!(out.contains("#[derive(Debug, Clone, PartialEq, Default, Eq)]\npub struct Indexed {"));
+    assert!(out.contains("#[derive(Debug, Clone, PartialEq, Default)]\npub struct Left {"));
+    assert!(out.contains("#[derive(Debug, Clone, PartialEq, Default)]\npub struct Right {"));
+}
+
+#[test]
+fn test_extra_derives() {
+    let src = r#"
+        syntax = "proto3";
+
+        enum Level {
+          LEVEL_NONE = 0;
+        }
+
+        message Exact {
+          oneof choice {
+            string name = 1;
+            Level level = 2;
+          }
+        }
+    "#;
+    let out = generate(
+        Config::new()
+            .derive("serde::Serialize")
+            .derive("serde::Deserialize")
+            .attribute("#[serde(rename_all = \"camelCase\")]"),
+        src,
+    );
+
+    let extra = ", serde::Serialize, serde::Deserialize)]\n#[serde(rename_all = \"camelCase\")]\n";
+    assert!(out.contains(&format!("Hash{extra}#[repr(u32)]\npub enum Level {{")));
+    let nested = extra.replace('\n', "\n    ");
+    assert!(out.contains(&format!("Eq, Hash{nested}pub enum Choice {{")));
+    assert!(out.contains(&format!("Default, Eq, Hash{extra}pub struct Exact {{")));
+}
+
+#[test]
+fn test_accessors() {
+    let src = r#"
+        syntax = "proto3";
+
+        message Wrapper {
+          repeated string values = 1;
+        }
+
+        message Pair {
+          string key = 1;
+          optional bytes value = 2;
+          Wrapper wrapper = 3;
+        }
+    "#;
+    let out = generate(&Config::new(), src);
+
+    assert!(out.contains("    values: Vec<String>,\n"));
+    assert!(out.contains("pub fn new(key: String, value: Option<Vec<u8>>, wrapper: Option<Wrapper>) -> Self {\n        Self { key, value, wrapper }\n    }"));
+    assert!(out.contains("pub fn builder() -> PairBuilder {"));
+    assert!(out.contains("pub fn key(&self) -> &str {\n        &self.key\n    }"));
+    assert!(out
+        .contains("pub fn value(&self) -> Option<&[u8]> {\n        self.value.as_deref()\n    }"));
+    assert!(out.contains(
+        "pub fn wrapper(&self) -> Option<&Wrapper> {\n        self.wrapper.as_ref()\n    }"
+    ));
+    assert!(out.contains("pub fn values(&self) -> &[String] {"));
+    assert!(out.contains("pub fn key_mut(&mut self) -> &mut String {"));
+    assert!(out.contains("pub fn set_key(&mut self, value: String) {"));
+    assert!(out.contains("pub fn key(mut self, value: impl Into<String>) -> Self {"));
+    assert!(out.contains("impl From<Vec<String>> for Wrapper {"));
+    assert!(out.contains("impl From<Wrapper> for Vec<String> {"));
+    assert!(!out.contains("impl From<Pair>"));
+}
+
+#[test]
+fn test_open_enums() {
+    let src = r#"
+        syntax = "proto3";
+
+        enum Level {
+          LEVEL_NONE = 0;
+          Unknown = 1;
+        }
+
+        message Exact {
+          Level level = 1;
+        }
+    "#;
+    let out = generate(Config::new().open_enums(true), src);
+
+    assert!(out.contains("use num_enum::{FromPrimitive, IntoPrimitive};"));
+    assert!(!out.contains("TryFromPrimitive"));
+    assert!(out.contains("    Unknown = 1,\n    #[num_enum(catch_all)]\n    Unknown_(u32) = 2,\n}"));
+    assert!(out.contains(".encode_u32(u32::from(self.level))"));
+    assert!(out.contains("level: Level::from(b.decode_u32()?),"));
+}
+
+#[test]
+fn test_enum_numbers() {
+    let src = r#"
+        syntax = "proto3";
+
+        enum Level {
+          option allow_alias = true;
+          LEVEL_NONE = 0;
+          LEVEL_HIGH = 10;
+          LEVEL_MAX = 10;
+          LEVEL_LOW = -1;
+        }
+    "#;
+    let out = generate(&Config::new(), src);
+
+    assert!(out.contains(
+        "pub enum Level {\n    LEVEL_NONE = 0,\n    LEVEL_HIGH = 10,\n    LEVEL_LOW = 4294967295,\n}"
+    ));
+    assert!(out.contains("impl Level {\n    pub const LEVEL_MAX: Level = Level::LEVEL_HIGH;\n}"));
+}
+
+#[test]
+fn test_names() {
+    let src = r#"
+        syntax = "proto3";
+
+        package my.pkg;
+
+        message Message {
+          message Kind {
+            string self = 1;
+          }
+
+          string type = 1;
+          string fooBar = 2;
+          string foo_bar = 3;
+          string new = 4;
+          oneof kind {
+            Kind value = 5;
+          }
+        }
+
+        message Other {
+          Message.Kind kind = 1;
+        }
+    "#;
+    let out = generate(&Config::new(), src);
+
+    assert!(out.contains("pub mod my {\n    #[allow(unused_imports)]\n    use super::*;\n"));
+    assert!(out.contains("\n    pub mod pkg {\n"));
+    assert!(out.contains("\n            r#type: String,\n            foo_bar: String,\n            foo_bar_2: String,\n            new_2: String,\n            kind: Option<message::Kind3>,\n"));
+    assert!(out.contains("pub fn r#type(&self) -> &str {"));
+    assert!(out.contains("pub fn type_mut(&mut self) -> &mut String {"));
+    assert!(out.contains("pub fn set_type(&mut self, value: String) {"));
+    assert!(out.contains("\n        pub mod message {\n"));
+    assert!(out.contains("pub struct Kind2 {\n                self_: String,\n"));
+    assert!(out.contains("pub fn self_mut(&mut self) -> &mut String {"));
+    assert!(out.contains("pub enum Kind3 {\n                Value(Kind2),\n"));
+    assert!(out.contains("kind: Option<message::Kind2>,"));
+}
+
+#[test]
+fn test_method_names() {
+    let src = r#"
+        syntax = "proto3";
+
+        message Message {
+          string encode_interned_to_vec = 1;
+          string encode_compressed_to_vec = 2;
+          string decode_compressed_from_slice = 3;
+        }
+    "#;
+    let out = generate(&Config::new(), src);
+
+    assert!(out.contains("encode_interned_to_vec_2: String,"));
+    assert!(out.contains("pub fn encode_interned_to_vec_2(&self) -> &str {"));
+    assert!(out.contains("encode_compressed_to_vec_2: String,"));
+    assert!(out.contains("decode_compressed_from_slice_2: String,"));
+}
+
+#[test]
+fn test_imports() {
+    let common = r#"
+        syntax = "proto3";
+
+        package common;
+
+        message Shared {
+          enum Level {
+            LOW = 0;
+          }
+
+          Level level = 1;
+        }
+    "#;
+    let app = r#"
+        syntax = "proto3";
+
+        package app;
+
+        import "protos/common.proto";
+
+        message Local {
+          common.Shared shared = 1;
+          common.Shared.Level level = 2;
+        }
+    "#;
+    let mut pool = DescriptorPool::new();
+    pool.add(parse("protos/common.proto", common).unwrap())
+        .unwrap();
+    pool.add(parse("app-v1.proto", app).unwrap()).unwrap();
+    let out = Config::new().generate(&pool, pool.file("app-v1.proto").unwrap());
+
+    assert!(out.contains("pub use polyglot_rs::{Decode, Encode};"));
+    assert!(out.contains("shared: Option<super::super::common::common::Shared>,"));
+    assert!(out.contains("level: super::super::common::common::shared::Level,"));
+}
+
+#[test]
+fn test_types() {
+    let src = r#"
+        syntax = "proto3";
+
+        message Blob {
+          string name = 1;
+          bytes data = 2 [(polyglot.bytes_type) = VEC];
+          map<string, bytes> parts = 3;
+          map<int32, string> labels = 4 [(polyglot.map_type) = INDEX_MAP, (polyglot.string_type) = SMOL_STR];
+        }
+    "#;
+    let out = generate(
+        Config::new()
+            .map_type(MapType::BTreeMap)
+            .bytes_type(BytesType::Bytes)
+            .string_type(StringType::ArcStr),
+        src,
+    );
+
+    assert!(!out.contains("use std::collections::HashMap;"));
+    assert!(out.contains("name: std::sync::Arc<str>,"));
+    assert!(out.contains("data: Vec<u8>,"));
+    assert!(out.contains("parts: std::collections::BTreeMap<std::sync::Arc<str>, bytes::Bytes>,"));
+    assert!(out.contains("labels: indexmap::IndexMap<i32, smol_str::SmolStr>,"));
+    assert!(out.contains("b.encode_str(&self.name)?.encode_bytes(&self.data)?;"));
+    assert!(out.contains("name: std::sync::Arc::from(b.decode_str()?),"));
+    assert!(out.contains("let v = bytes::Bytes::copy_from_slice(b.decode_byte_slice()?);"));
+    assert!(out.contains("let mut map = indexmap::IndexMap::new();"));
+}
+
+#[test]
+fn test_delimited() {
+    let src = r#"
+        syntax = "proto3";
+
+        message User {
+          option (polyglot.delimited) = true;
+
+          repeated string tags = 2;
+          string name = 1;
+          oneof contact {
+            string email = 4;
+          }
+          uint32 age = 3;
+        }
+    "#;
+    let out = generate(&Config::new(), src);
+
+    assert!(out.contains("let start = b.reserve_delimited()?;"));
+    assert!(out.contains("b.encode_delimited(start)?;"));
+    assert!(out.contains("let end = b.decode_delimited()?;"));
+    assert!(out.contains("b.skip_value()?;"));
+    let name = out.find("b.encode_string(&self.name)?;").unwrap();
+    let tags = out
+        .find("b.encode_array(self.tags.len(), Kind::String)?;")
+        .unwrap();
+    let age = out.find("b.encode_u32(self.age)?;").unwrap();
+    let contact = out.find("match &self.contact {").unwrap();
+    assert!(name < tags && tags < age && age < contact);
+}
+
+#[test]
+fn test_service_ids() {
+    let src = r#"
+        syntax = "proto3";
+
+        message Ping {}
+
+        service Pinger {
+          rpc Ping(Ping) returns (Ping);
+          rpc Watch(Ping) returns (stream Ping);
+          rpc Upload(stream Ping) returns (Ping);
+          rpc Pong(Ping) returns (Ping);
+        }
+    "#;
+    let out = generate(&Config::new(), src);
+
+    assert!(out.contains("pub enum PingerMethod {\n    Ping = 0,\n    Pong = 3,\n}"));
+    assert!(!out.contains("fn watch"));
+    assert!(!out.contains("fn upload"));
+}
+
+#[test]
+fn test_service_without_unary_methods() {
+    let src = r#"
+        syntax = "proto3";
+
+        message Ping {}
+
+        service Watcher {
+          rpc Watch(Ping) returns (stream Ping);
+        }
+
+        service lower {
+          rpc new(Ping) returns (Ping);
+          rpc get_ping(Ping) returns (Ping);
+        }
+    "#;
+    let out = generate(&Config::new(), src);
+
+    assert!(!out.contains("pub enum WatcherMethod"));
+    assert!(out.contains("Err(RpcError::UnknownMethod(method))\n    }\n}"));
+    assert!(out.contains("pub enum LowerMethod {\n    New = 0,\n    GetPing = 1,\n}"));
+    assert!(out.contains("pub async fn new_2(&self"));
+}
@@ -0,0 +1,268 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+mod common;
+
+use common::pool;
+use polyglot_build::ast::{FieldType, Label, ScalarType};
+use polyglot_build::{parse, BuildError, DescriptorPool};
+use polyglot_rs::Field;
+
+const PROTO: &str = r#"
+syntax = "proto3";
+
+package test.v1;
+
+// A message with a nested enum and message.
+message Outer {
+  enum Kind {
+    KIND_UNSPECIFIED = 0;
+    KIND_OTHER = 2;
+  }
+
+  message Inner {
+    string value = 1 [deprecated = true];
+  }
+
+  Kind kind = 1;
+  repeated Inner inners = 2;
+  map<string, Inner> by_name = 3;
+  optional uint64 count = 4;
+  reserved 5, 6 to 8;
+}
+
+service Outers {
+  rpc Get(Outer) returns (Outer.Inner);
+}
+"#;
+
+#[test]
+fn test_parse() {
+    let file = parse("test.proto", PROTO).unwrap();
+    assert_eq!(file.package, "test.v1");
+    assert_eq!(file.messages.len(), 1);
+
+    let outer = &file.messages[0];
+    assert_eq!(outer.full_name, "test.v1.Outer");
+    assert_eq!(outer.enums[0].full_name, "test.v1.Outer.Kind");
+    assert_eq!(outer.enums[0].values[1].number, 2);
+    assert_eq!(outer.messages[0].full_name, "test.v1.Outer.Inner");
+    assert_eq!(outer.fields.len(), 4);
+    assert_eq!(outer.fields[1].label, Label::Repeated);
+    assert_eq!(outer.fields[3].label, Label::Optional);
+    assert_eq!(outer.fields[3].ty, FieldType::Scalar(ScalarType::Uint64));
+    assert_eq!(file.services[0].methods[0].name, "Get");
+}
+
+#[test]
+fn test_resolve() {
+    let pool = pool(PROTO);
+
+    let outer = pool.message("test.v1.Outer").unwrap();
+    assert_eq!(
+        outer.fields[0].ty,
+        FieldType::Enum("test.v1.Outer.Kind".into())
+    );
+    assert_eq!(
+        outer.fields[1].ty,
+        FieldType::Message("test.v1.Outer.Inner".into())
+    );
+    assert_eq!(
+        outer.fields[2].ty,
+        FieldType::Map(
+            Box::new(FieldType::Scalar(ScalarType::String)),
+            Box::new(FieldType::Message("test.v1.Outer.Inner".into()))
+        )
+    );
+    let method = &pool.file("test.proto").unwrap().services[0].methods[0];
+    assert_eq!(method.output, "test.v1.Outer.Inner");
+}
+
+#[test]
+fn test_schema() {
+    let pool = pool(PROTO);
+
+    let schema = pool.schema("test.v1.Outer");
+    let outer = schema.get_message("test.v1.Outer").unwrap();
+    assert_eq!(
+        outer.fields,
+        [
+            Field {
+                name: "kind".to_string(),
+                number: 1,
+                ty: polyglot_rs::FieldType::Enum("test.v1.Outer.Kind".to_string()),
+            },
+            Field {
+                name: "inners".to_string(),
+                number: 2,
+                ty: polyglot_rs::FieldType::array(polyglot_rs::FieldType::Message(
+                    "test.v1.Outer.Inner".to_string()
+                )),
+            },
+            Field {
+                name: "by_name".to_string(),
+                number: 3,
+                ty: polyglot_rs::FieldType::map(
+                    polyglot_rs::FieldType::String,
+                    polyglot_rs::FieldType::Message("test.v1.Outer.Inner".to_string()),
+                ),
+            },
+            Field {
+                name: "count".to_string(),
+                number: 4,
+                ty: polyglot_rs::FieldType::optional(polyglot_rs::FieldType::U64),
+            },
+        ]
+    );
+    assert_eq!(
+        schema.get_enum("test.v1.Outer.Kind").unwrap().values[1],
+        ("KIND_OTHER".to_string(), 2)
+    );
+
+    let file = parse(
+        "delimited.proto",
+        r#"
+        message A {
+          option (polyglot.delimited) = true;
+          oneof value {
+            string text = 3;
+            int64 number = 1;
+          }
+          repeated bool flags = 2;
+        }
+        "#,
+    )
+    .unwrap();
+    let mut pool = DescriptorPool::new();
+    pool.add(file).unwrap();
+    let a = pool.schema("A").messages.pop().unwrap();
+    assert!(a.delimited);
+    assert_eq!(
+        a.fields,
+        [
+            Field {
+                name: "value".to_string(),
+                number: 1,
+                ty: polyglot_rs::FieldType::Oneof(vec![
+                    (3, polyglot_rs::FieldType::String),
+                    (1, polyglot_rs::FieldType::I64),
+                ]),
+            },
+            Field {
+                name: "flags".to_string(),
+                number: 2,
+                ty: polyglot_rs::FieldType::array(polyglot_rs::FieldType::Bool),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_errors() {
+    let err = parse("bad.proto", "syntax = \"proto3\";\nmessage {}\n").unwrap_err();
+    assert!(matches!(err, BuildError::Parse { line: 2, .. }));
+
+    for src in [
+        "message A {",
+        "message A { string b = 1",
+        "service S { rpc Get(A)",
+        "service S {",
+    ] {
+        let err = parse("bad.proto", src).unwrap_err();
+        assert!(
+            matches!(err, BuildError::Parse { ref message, .. } if message == "unexpected end of file"),
+            "{src}: {err}"
+        );
+    }
+
+    for src in [
+        "message A {} message A {}",
+        "message A {} enum A { B = 0; }",
+        "message A { message B {} enum B { C = 0; } }",
+    ] {
+        let file = parse("bad.proto", src).unwrap();
+        let err = DescriptorPool::new().add(file).unwrap_err();
+        assert!(matches!(err, BuildError::DuplicateSymbol { .. }), "{src}");
+    }
+
+    let mut pool = DescriptorPool::new();
+    pool.add(parse("a.proto", "message A {}").unwrap()).unwrap();
+    let err = pool
+        .add(parse("b.proto", "message A {}").unwrap())
+        .unwrap_err();
+    assert!(
+        matches!(err, BuildError::DuplicateSymbol { ref file, ref name } if file == "b.proto" && name == "A")
+    );
+
+    for src in [
+        "message A { string b = 1; string c = 1; }",
+        "message A { string b = 1; oneof d { string c = 1; } }",
+    ] {
+        let file = parse("bad.proto", src).unwrap();
+        let err = DescriptorPool::new().add(file).unwrap_err();
+        assert!(
+            matches!(err, BuildError::DuplicateFieldNumber { ref field, number: 1, .. } if field == "A.c"),
+            "{src}"
+        );
+    }
+
+    let file = parse("bad.proto", "message A { Missing b = 1; }").unwrap();
+    let err = DescriptorPool::new().add(file).unwrap_err();
+    assert!(matches!(err, BuildError::UnresolvedType { ref name, .. } if name == "Missing"));
+
+    let file = parse(
+        "bad.proto",
+        "message A { string b = 1 [(polyglot.string_type) = BTREE_MAP]; }",
+    )
+    .unwrap();
+    let err = DescriptorPool::new().add(file).unwrap_err();
+    assert!(matches!(err, BuildError::InvalidOption { ref field, .. } if field == "A.b"));
+
+    let file = parse(
+        "bad.proto",
+        "message A { option (polyglot.delimited) = 1; }",
+    )
+    .unwrap();
+    let err = DescriptorPool::new().add(file).unwrap_err();
+    assert!(matches!(err, BuildError::InvalidOption { ref field, .. } if field == "A"));
+
+    for field in [
+        "repeated string b = 1 [(polyglot.array_encoding) = PACKED];",
+        "double b = 1 [(polyglot.array_encoding) = PACKED];",
+        "repeated int32 b = 1 [(polyglot.array_encoding) = 1];",
+        "repeated int32 b = 1 [(polyglot.array_encoding) = DELTA];",
+        "repeated double b = 1 [(polyglot.array_encoding) = DELTA];",
+        "repeated bytes b = 1 [(polyglot.array_encoding) = RUN_LENGTH];",
+        "repeated bool b = 1 [(polyglot.array_encoding) = RUN_LENGTH];",
+        "repeated int64 b = 1 [(polyglot.array_encoding) = ZIGZAG];",
+    ] {
+        let file = parse("bad.proto", &format!("message A {{ {field} }}")).unwrap();
+        let err = DescriptorPool::new().add(file).unwrap_err();
+        assert!(
+            matches!(err, BuildError::InvalidOption { ref option, .. } if option == "(polyglot.array_encoding)")
+        );
+    }
+
+    for field in [
+        "string b = 1 [(polyglot.array_encoding) = UNPACKED];",
+        "repeated sfixed64 b = 1 [(polyglot.array_encoding) = DELTA];",
+        "repeated float b = 1 [(polyglot.array_encoding) = RUN_LENGTH];",
+        "repeated bool b = 1 [(polyglot.array_encoding) = PACKED];",
+    ] {
+        let file = parse("good.proto", &format!("message A {{ {field} }}")).unwrap();
+        assert!(DescriptorPool::new().add(file).is_ok());
+    }
+}
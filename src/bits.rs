@@ -0,0 +1,229 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::decoder::{read_slice, Decoder, DecoderBuffer, DecodingError};
+use crate::kind::Kind;
+use byteorder::ReadBytesExt;
+use std::io::Cursor;
+use std::iter::FusedIterator;
+
+/// A growable array of bits, which is how arrays of bools are packed: one bit
+/// per element with the first element in the lowest bit of the first byte.
+/// Bits past the end of the last byte are always zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Bits {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bits {
+    pub fn new() -> Self {
+        Bits::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Bits {
+            bytes: Vec::with_capacity(capacity.div_ceil(8)),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len {
+            return None;
+        }
+        Some(self.bytes[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    /// Sets the bit at `index`, panicking when it is out of bounds like indexing
+    /// a slice would.
+    pub fn set(&mut self, index: usize, val: bool) {
+        assert!(
+            index < self.len,
+            "index {index} out of range for bits of length {}",
+            self.len
+        );
+        if val {
+            self.bytes[index / 8] |= 1 << (index % 8);
+        } else {
+            self.bytes[index / 8] &= !(1 << (index % 8));
+        }
+    }
+
+    pub fn push(&mut self, val: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, val);
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.len = 0;
+    }
+
+    /// The number of bits that are set.
+    pub fn count_ones(&self) -> usize {
+        self.bytes.iter().map(|b| b.count_ones() as usize).sum()
+    }
+
+    pub fn iter(&self) -> BitsIter<'_> {
+        BitsIter {
+            bits: self,
+            front: 0,
+            back: self.len,
+        }
+    }
+
+    /// The packed bytes, as they are written after the header of the array.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn from_bytes(bytes: &[u8], len: usize) -> Self {
+        let mut bytes = bytes.to_vec();
+        if let Some(last) = bytes.last_mut() {
+            if !len.is_multiple_of(8) {
+                *last &= (1 << (len % 8)) - 1;
+            }
+        }
+        Bits { bytes, len }
+    }
+}
+
+impl From<&[bool]> for Bits {
+    fn from(vals: &[bool]) -> Self {
+        vals.iter().copied().collect()
+    }
+}
+
+impl FromIterator<bool> for Bits {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut bits = Bits::with_capacity(iter.size_hint().0);
+        for val in iter {
+            bits.push(val);
+        }
+        bits
+    }
+}
+
+impl Extend<bool> for Bits {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        for val in iter {
+            self.push(val);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Bits {
+    type Item = bool;
+    type IntoIter = BitsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterates over the bits of a [`Bits`] in order.
+#[derive(Debug, Clone)]
+pub struct BitsIter<'a> {
+    bits: &'a Bits,
+    front: usize,
+    back: usize,
+}
+
+impl Iterator for BitsIter<'_> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        self.bits.get(self.front - 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for BitsIter<'_> {
+    fn next_back(&mut self) -> Option<bool> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.bits.get(self.back)
+    }
+}
+
+impl ExactSizeIterator for BitsIter<'_> {}
+
+impl FusedIterator for BitsIter<'_> {}
+
+/// Writes an array of bools as a packed array of bits.
+pub(crate) fn write_bits(vals: &[bool]) -> Vec<u8> {
+    let mut buf = vec![0; vals.len().div_ceil(8)];
+    for (i, val) in vals.iter().enumerate() {
+        if *val {
+            buf[i / 8] |= 1 << (i % 8);
+        }
+    }
+    buf
+}
+
+/// Decodes an array of bools, whether it was packed into bits or written with
+/// the kind of every element.
+pub(crate) fn decode_bits<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Result<Bits, DecodingError> {
+    let start = cursor.position();
+    let kind = cursor.read_u8().ok().ok_or(DecodingError::InvalidArray)?;
+    let val_kind = cursor.read_u8().ok().ok_or(DecodingError::InvalidArray)?;
+    let kind = Kind::from(kind);
+    if val_kind != Kind::Bool as u8 || !matches!(kind, Kind::Array | Kind::PackedArray) {
+        cursor.set_position(start);
+        return Err(DecodingError::InvalidArray);
+    }
+    let size = cursor.decode_u32()? as usize;
+    if kind == Kind::PackedArray {
+        let bytes = read_slice(cursor, size.div_ceil(8)).ok_or(DecodingError::InvalidArray)?;
+        return Ok(Bits::from_bytes(bytes, size));
+    }
+
+    // Every element takes up two bytes, which keeps a corrupt size from
+    // allocating more than the buffer could hold.
+    let len = cursor.get_ref().as_ref().len() as u64;
+    let remaining = len.saturating_sub(cursor.position());
+    if size as u64 > remaining / 2 {
+        return Err(DecodingError::InvalidArray);
+    }
+    let mut bits = Bits::with_capacity(size);
+    for _ in 0..size {
+        bits.push(cursor.decode_bool()?);
+    }
+    Ok(bits)
+}
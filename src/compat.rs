@@ -0,0 +1,356 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::kind::Kind;
+use crate::schema::{Field, FieldType, Schema};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+/// The readers that can no longer decode what the other side writes after a
+/// schema change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readers {
+    /// Readers built from the old schema, reading messages written with the new one.
+    Old,
+    /// Readers built from the new schema, reading messages written with the old one.
+    New,
+    Both,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    AddedField,
+    RemovedField,
+    /// The field moved relative to the other fields of its message, by wire
+    /// position, so readers put its value into another field.
+    ReorderedField {
+        old_position: usize,
+        new_position: usize,
+    },
+    ChangedKind {
+        old: Kind,
+        new: Kind,
+    },
+    ChangedMapKey {
+        old: Kind,
+        new: Kind,
+    },
+    ChangedMapValue {
+        old: Kind,
+        new: Kind,
+    },
+    /// The field became optional, or stopped being optional.
+    ChangedOptional,
+    /// The message started or stopped being delimited.
+    ChangedDelimited,
+    AddedOneofMember,
+    RemovedOneofMember,
+    AddedEnumValue,
+    RemovedEnumValue,
+    RenumberedEnumValue {
+        old: u32,
+        new: u32,
+    },
+}
+
+/// A change between two versions of a schema that breaks decoding for some readers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakingChange {
+    /// The message and field, or the enum and value, that changed. Array items
+    /// are suffixed with `[]` and oneof members with `#` and their field number.
+    pub path: String,
+    pub change: Change,
+    pub breaks: Readers,
+}
+
+impl Display for BreakingChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let breaks = match self.breaks {
+            Readers::Old => "old readers",
+            Readers::New => "new readers",
+            Readers::Both => "old and new readers",
+        };
+        write!(f, "{}: {:?} breaks {breaks}", self.path, self.change)
+    }
+}
+
+/// Compares two versions of a schema and returns the changes that keep readers
+/// built from one of them from decoding messages written with the other.
+///
+/// Since values are identified by their position on the wire, adding, removing
+/// or moving a field breaks every reader, unless the message is delimited and
+/// the field is at the end of it. Fields are matched by number, so renaming one
+/// doesn't break anything, while oneofs, messages and enums are matched by name.
+/// Messages that a field refers to under another name are compared with each
+/// other.
+pub fn check_compatibility(old: &Schema, new: &Schema) -> Vec<BreakingChange> {
+    let mut checker = Checker {
+        old,
+        new,
+        compared: HashSet::new(),
+        changes: Vec::new(),
+    };
+    checker.message(&old.root, &new.root);
+    for message in &old.messages {
+        checker.message(&message.name, &message.name);
+    }
+    for e in &old.enums {
+        checker.enumeration(&e.name, &e.name);
+    }
+    checker.changes
+}
+
+struct Checker<'a> {
+    old: &'a Schema,
+    new: &'a Schema,
+    compared: HashSet<(String, String, bool)>,
+    changes: Vec<BreakingChange>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, path: String, change: Change, breaks: Readers) {
+        self.changes.push(BreakingChange {
+            path,
+            change,
+            breaks,
+        });
+    }
+
+    /// Whether the pair hasn't been compared yet, marking it as compared.
+    fn first_visit(&mut self, old: &str, new: &str, message: bool) -> bool {
+        self.compared
+            .insert((old.to_owned(), new.to_owned(), message))
+    }
+
+    fn message(&mut self, old_name: &str, new_name: &str) {
+        let (Some(old), Some(new)) = (
+            self.old.get_message(old_name),
+            self.new.get_message(new_name),
+        ) else {
+            return;
+        };
+        if !self.first_visit(old_name, new_name, true) {
+            return;
+        }
+        if old.delimited != new.delimited {
+            self.report(new_name.to_owned(), Change::ChangedDelimited, Readers::Both);
+            return;
+        }
+
+        let old_fields = old.wire_fields();
+        let new_fields = new.wire_fields();
+        let common: Vec<&Field> = old_fields
+            .iter()
+            .filter(|f| position(&new_fields, f).is_some())
+            .copied()
+            .collect();
+
+        for (index, field) in old_fields.iter().enumerate() {
+            if position(&new_fields, field).is_none() {
+                let trailing = old_fields[index..]
+                    .iter()
+                    .all(|f| position(&new_fields, f).is_none());
+                if !(old.delimited && trailing) {
+                    self.report(
+                        format!("{new_name}.{}", field.name),
+                        Change::RemovedField,
+                        Readers::Both,
+                    );
+                }
+            }
+        }
+        for (index, field) in new_fields.iter().enumerate() {
+            if position(&old_fields, field).is_none() {
+                let trailing = new_fields[index..]
+                    .iter()
+                    .all(|f| position(&old_fields, f).is_none());
+                if !(new.delimited && trailing) {
+                    self.report(
+                        format!("{new_name}.{}", field.name),
+                        Change::AddedField,
+                        Readers::Both,
+                    );
+                }
+            }
+        }
+        // The fields that kept their relative order on the wire are the longest
+        // run of common fields that is in order in both versions, the others
+        // moved and are read into each other's place.
+        let order: Vec<usize> = common
+            .iter()
+            .filter_map(|field| position(&new_fields, field))
+            .collect();
+        let kept = longest_increasing(&order);
+        for (index, field) in common.iter().enumerate() {
+            let (Some(old_position), Some(new_position)) =
+                (position(&old_fields, field), position(&new_fields, field))
+            else {
+                continue;
+            };
+            let path = format!("{new_name}.{}", new_fields[new_position].name);
+            if !kept.contains(&index) {
+                let change = Change::ReorderedField {
+                    old_position,
+                    new_position,
+                };
+                self.report(path.clone(), change, Readers::Both);
+            }
+            let (old_ty, new_ty) = (&old_fields[old_position].ty, &new_fields[new_position].ty);
+            self.field(path, old_ty, new_ty);
+        }
+    }
+
+    fn field(&mut self, path: String, old: &FieldType, new: &FieldType) {
+        match (old, new) {
+            (FieldType::Optional(old), FieldType::Optional(new)) => self.field(path, old, new),
+            (FieldType::Optional(_), _) | (_, FieldType::Optional(_)) => {
+                self.report(path, Change::ChangedOptional, Readers::Both)
+            }
+            (FieldType::Message(old), FieldType::Message(new)) => self.message(old, new),
+            (FieldType::Enum(old), FieldType::Enum(new)) => self.enumeration(old, new),
+            (FieldType::Array(old), FieldType::Array(new)) => {
+                self.field(format!("{path}[]"), old, new)
+            }
+            (FieldType::Map(old_key, old_val), FieldType::Map(new_key, new_val)) => {
+                if old_key.kind() != new_key.kind() {
+                    let change = Change::ChangedMapKey {
+                        old: old_key.kind(),
+                        new: new_key.kind(),
+                    };
+                    self.report(path.clone(), change, Readers::Both);
+                } else {
+                    self.nested(&path, old_key, new_key);
+                }
+                if old_val.kind() != new_val.kind() {
+                    let change = Change::ChangedMapValue {
+                        old: old_val.kind(),
+                        new: new_val.kind(),
+                    };
+                    self.report(path, change, Readers::Both);
+                } else {
+                    self.nested(&path, old_val, new_val);
+                }
+            }
+            (FieldType::Oneof(old), FieldType::Oneof(new)) => {
+                for (number, old_ty) in old {
+                    let member = format!("{path}#{number}");
+                    match new.iter().find(|(n, _)| n == number) {
+                        Some((_, new_ty)) => self.field(member, old_ty, new_ty),
+                        None => self.report(member, Change::RemovedOneofMember, Readers::New),
+                    }
+                }
+                for (number, _) in new {
+                    if !old.iter().any(|(n, _)| n == number) {
+                        self.report(
+                            format!("{path}#{number}"),
+                            Change::AddedOneofMember,
+                            Readers::Old,
+                        );
+                    }
+                }
+            }
+            _ if old.kind() != new.kind() => self.report(
+                path,
+                Change::ChangedKind {
+                    old: old.kind(),
+                    new: new.kind(),
+                },
+                Readers::Both,
+            ),
+            _ => {}
+        }
+    }
+
+    /// Compares the keys or values of a map whose kinds already match.
+    fn nested(&mut self, path: &str, old: &FieldType, new: &FieldType) {
+        match (old, new) {
+            (FieldType::Message(old), FieldType::Message(new)) => self.message(old, new),
+            (FieldType::Enum(old), FieldType::Enum(new)) => self.enumeration(old, new),
+            _ => self.field(path.to_owned(), old, new),
+        }
+    }
+
+    fn enumeration(&mut self, old_name: &str, new_name: &str) {
+        let (Some(old), Some(new)) = (self.old.get_enum(old_name), self.new.get_enum(new_name))
+        else {
+            return;
+        };
+        if !self.first_visit(old_name, new_name, false) {
+            return;
+        }
+        // Enums are written as their numbers, so values can be renamed but not
+        // renumbered, and readers fail on numbers they don't know.
+        for (name, number) in &old.values {
+            let path = format!("{new_name}.{name}");
+            match new.values.iter().find(|(n, _)| n == name) {
+                Some((_, new_number)) if new_number != number => self.report(
+                    path,
+                    Change::RenumberedEnumValue {
+                        old: *number,
+                        new: *new_number,
+                    },
+                    Readers::Both,
+                ),
+                Some(_) => {}
+                None if !new.values.iter().any(|(_, n)| n == number) => {
+                    self.report(path, Change::RemovedEnumValue, Readers::New)
+                }
+                None => {}
+            }
+        }
+        for (name, number) in &new.values {
+            let known = old.values.iter().any(|(n, m)| n == name || m == number);
+            if !known {
+                self.report(
+                    format!("{new_name}.{name}"),
+                    Change::AddedEnumValue,
+                    Readers::Old,
+                );
+            }
+        }
+    }
+}
+
+/// The wire position of the other version of `field`, which has the same
+/// number or, for a oneof, the same name.
+fn position(fields: &[&Field], field: &Field) -> Option<usize> {
+    fields.iter().position(|f| match (&f.ty, &field.ty) {
+        (FieldType::Oneof(_), FieldType::Oneof(_)) => f.name == field.name,
+        (FieldType::Oneof(_), _) | (_, FieldType::Oneof(_)) => false,
+        _ => f.number == field.number,
+    })
+}
+
+/// The indices of a longest strictly increasing subsequence of `values`.
+fn longest_increasing(values: &[usize]) -> HashSet<usize> {
+    let mut lengths = vec![1; values.len()];
+    let mut previous = vec![None; values.len()];
+    for i in 0..values.len() {
+        for j in 0..i {
+            if values[j] < values[i] && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+    let mut kept = HashSet::new();
+    let mut index = (0..values.len()).max_by_key(|&i| (lengths[i], std::cmp::Reverse(i)));
+    while let Some(i) = index {
+        kept.insert(i);
+        index = previous[i];
+    }
+    kept
+}
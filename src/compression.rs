@@ -0,0 +1,208 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::decoder::Decoder;
+use crate::kind::Kind;
+use crate::packed::write_varint;
+use byteorder::ReadBytesExt;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{Cursor, Read, Write};
+
+/// The default limit on how large a compressed message may claim to be when
+/// decoding, which keeps a small payload from allocating gigabytes.
+pub const MAX_DECOMPRESSED_LEN: usize = 1 << 26;
+
+/// The algorithms a payload can be compressed with. The value is written in the
+/// header of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Lz4 = 0x01,
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = CompressionError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(Compression::Lz4),
+            _ => Err(CompressionError::UnknownAlgorithm(value)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CompressionError {
+    /// The payload doesn't start with a compression header.
+    InvalidHeader,
+    UnknownAlgorithm(u8),
+    /// The payload would decompress to more than the given maximum.
+    TooLarge(usize),
+    /// The compressed bytes are corrupt or don't decompress to the length in the
+    /// header.
+    InvalidPayload,
+    Io(String),
+}
+
+impl Display for CompressionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for CompressionError {}
+
+impl From<io::Error> for CompressionError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            return CompressionError::InvalidHeader;
+        }
+        CompressionError::Io(err.to_string())
+    }
+}
+
+/// The header written in front of a compressed payload: `Kind::Compressed`, the
+/// algorithm, and the lengths of the payload before and after compressing it.
+struct Header {
+    algorithm: Compression,
+    len: usize,
+    compressed_len: usize,
+}
+
+impl Header {
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.push(Kind::Compressed as u8);
+        buf.push(self.algorithm as u8);
+        for len in [self.len, self.compressed_len] {
+            buf.push(Kind::U32 as u8);
+            write_varint(buf, len as u64);
+        }
+    }
+
+    /// Reads a header, checking its lengths against `max_len` before anything
+    /// is allocated for the payload.
+    fn read(reader: &mut impl Read, max_len: usize) -> Result<Self, CompressionError> {
+        if reader.read_u8()? != Kind::Compressed as u8 {
+            return Err(CompressionError::InvalidHeader);
+        }
+        let algorithm = Compression::try_from(reader.read_u8()?)?;
+        let len = read_len(reader)?;
+        let compressed_len = read_len(reader)?;
+        if len > max_len {
+            return Err(CompressionError::TooLarge(len));
+        }
+        if compressed_len > max_compressed_len(algorithm, len) {
+            return Err(CompressionError::InvalidPayload);
+        }
+        Ok(Header {
+            algorithm,
+            len,
+            compressed_len,
+        })
+    }
+}
+
+/// Reads a length written with `Encoder::encode_u32`, which is at most 5 bytes.
+fn read_len(reader: &mut impl Read) -> Result<usize, CompressionError> {
+    let mut buf = vec![reader.read_u8()?];
+    while buf.len() < 6 {
+        let byte = reader.read_u8()?;
+        buf.push(byte);
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Cursor::new(buf.as_slice())
+        .decode_u32()
+        .map(|len| len as usize)
+        .map_err(|_| CompressionError::InvalidHeader)
+}
+
+fn max_compressed_len(algorithm: Compression, len: usize) -> usize {
+    match algorithm {
+        Compression::Lz4 => lz4_flex::block::get_maximum_output_size(len),
+    }
+}
+
+/// Compresses an encoded message or any other buffer, writing the header the
+/// decompressing side needs in front of it.
+pub fn compress(buf: &[u8], algorithm: Compression) -> Vec<u8> {
+    let compressed = match algorithm {
+        Compression::Lz4 => lz4_flex::block::compress(buf),
+    };
+    let header = Header {
+        algorithm,
+        len: buf.len(),
+        compressed_len: compressed.len(),
+    };
+    let mut out = Vec::with_capacity(compressed.len() + 16);
+    header.write(&mut out);
+    out.extend_from_slice(&compressed);
+    out
+}
+
+/// Decompresses a buffer written by `compress`, failing with `TooLarge` without
+/// decompressing anything when it would grow past `max_len` bytes.
+pub fn decompress(buf: &[u8], max_len: usize) -> Result<Vec<u8>, CompressionError> {
+    let mut reader = Cursor::new(buf);
+    let header = Header::read(&mut reader, max_len)?;
+    let payload = &buf[reader.position() as usize..];
+    if payload.len() != header.compressed_len {
+        return Err(CompressionError::InvalidPayload);
+    }
+    decompress_payload(&header, payload)
+}
+
+fn decompress_payload(header: &Header, payload: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let mut out = vec![0; header.len];
+    let len = match header.algorithm {
+        Compression::Lz4 => lz4_flex::block::decompress_into(payload, &mut out)
+            .map_err(|_| CompressionError::InvalidPayload)?,
+    };
+    if len != header.len {
+        return Err(CompressionError::InvalidPayload);
+    }
+    Ok(out)
+}
+
+/// Writes `buf` to a stream as a compressed frame, which `read_compressed`
+/// reads back one at a time.
+pub fn write_compressed(
+    writer: &mut impl Write,
+    buf: &[u8],
+    algorithm: Compression,
+) -> io::Result<()> {
+    writer.write_all(&compress(buf, algorithm))
+}
+
+/// Reads a frame written by `write_compressed` from a stream, failing with
+/// `TooLarge` before reading its payload when it would grow past `max_len`
+/// bytes.
+pub fn read_compressed(
+    reader: &mut impl Read,
+    max_len: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    let header = Header::read(reader, max_len)?;
+    let mut payload = vec![0; header.compressed_len];
+    reader.read_exact(&mut payload).map_err(|err| {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            return CompressionError::InvalidPayload;
+        }
+        CompressionError::from(err)
+    })?;
+    decompress_payload(&header, &payload)
+}
@@ -14,12 +14,16 @@
     limitations under the License.
 */
 
+use crate::bits::{decode_bits, Bits};
+use crate::intern::StringTable;
 use crate::kind::Kind;
+use crate::packed::{decode_packed, skip_packed};
 use byteorder::{BigEndian, ReadBytesExt};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::io::{Cursor, Read};
 use std::str;
+use std::sync::Arc;
 
 #[derive(Debug, PartialEq)]
 pub enum DecodingError {
@@ -40,6 +44,10 @@ pub enum DecodingError {
     InvalidF64,
     InvalidEnum,
     InvalidStruct,
+    InvalidOneof,
+    MaxDepthExceeded,
+    TrailingBytes,
+    InvalidKind,
 }
 
 impl Display for DecodingError {
@@ -61,6 +69,17 @@ pub trait Decoder {
     fn decode_map(&mut self, key_kind: Kind, val_kind: Kind) -> Result<usize, DecodingError>;
     fn decode_bytes(&mut self) -> Result<Vec<u8>, DecodingError>;
     fn decode_string(&mut self) -> Result<String, DecodingError>;
+    /// Decodes bytes without copying them out of the buffer, for decoding into
+    /// types other than `Vec<u8>`.
+    fn decode_byte_slice(&mut self) -> Result<&[u8], DecodingError>;
+    /// Decodes a string without copying it out of the buffer, for decoding into
+    /// types other than `String`.
+    fn decode_str(&mut self) -> Result<&str, DecodingError>;
+    /// Decodes bytes into `buf`, replacing its contents but keeping its allocation.
+    fn decode_bytes_into(&mut self, buf: &mut Vec<u8>) -> Result<(), DecodingError>;
+    /// Decodes a string into `buf`, replacing its contents but keeping its
+    /// allocation.
+    fn decode_string_into(&mut self, buf: &mut String) -> Result<(), DecodingError>;
     fn decode_error(&mut self) -> Result<Box<dyn Error>, DecodingError>;
     fn decode_bool(&mut self) -> Result<bool, DecodingError>;
     fn decode_u8(&mut self) -> Result<u8, DecodingError>;
@@ -71,16 +90,153 @@ pub trait Decoder {
     fn decode_i64(&mut self) -> Result<i64, DecodingError>;
     fn decode_f32(&mut self) -> Result<f32, DecodingError>;
     fn decode_f64(&mut self) -> Result<f64, DecodingError>;
+    /// Decodes an array of numbers, whether it was written with the kind of every
+    /// element, packed, delta encoded or run-length encoded. The `_into` variants
+    /// reuse the allocation of `buf`.
+    fn decode_u32_vec(&mut self) -> Result<Vec<u32>, DecodingError>;
+    fn decode_u32_vec_into(&mut self, buf: &mut Vec<u32>) -> Result<(), DecodingError>;
+    fn decode_u64_vec(&mut self) -> Result<Vec<u64>, DecodingError>;
+    fn decode_u64_vec_into(&mut self, buf: &mut Vec<u64>) -> Result<(), DecodingError>;
+    fn decode_i32_vec(&mut self) -> Result<Vec<i32>, DecodingError>;
+    fn decode_i32_vec_into(&mut self, buf: &mut Vec<i32>) -> Result<(), DecodingError>;
+    fn decode_i64_vec(&mut self) -> Result<Vec<i64>, DecodingError>;
+    fn decode_i64_vec_into(&mut self, buf: &mut Vec<i64>) -> Result<(), DecodingError>;
+    fn decode_f32_vec(&mut self) -> Result<Vec<f32>, DecodingError>;
+    fn decode_f32_vec_into(&mut self, buf: &mut Vec<f32>) -> Result<(), DecodingError>;
+    fn decode_f64_vec(&mut self) -> Result<Vec<f64>, DecodingError>;
+    fn decode_f64_vec_into(&mut self, buf: &mut Vec<f64>) -> Result<(), DecodingError>;
+    /// Decodes an array of bools, whether it was packed into bits or written
+    /// with the kind of every element.
+    fn decode_bool_vec(&mut self) -> Result<Vec<bool>, DecodingError>;
+    fn decode_bool_vec_into(&mut self, buf: &mut Vec<bool>) -> Result<(), DecodingError>;
+    fn decode_bits(&mut self) -> Result<Bits, DecodingError>;
+    /// Decodes the string table written in front of a message by `intern`,
+    /// returning an empty table when there isn't one.
+    fn decode_string_table(&mut self) -> Result<StringTable, DecodingError>;
+    /// Decodes a string that may be a reference into `table`, sharing the
+    /// strings of the table rather than copying them.
+    fn decode_interned_str(&mut self, table: &StringTable) -> Result<Arc<str>, DecodingError>;
+    /// Decodes the header of a value written with `Encoder::encode_delimited` and
+    /// returns the position it ends at.
+    fn decode_delimited(&mut self) -> Result<u64, DecodingError>;
+    /// Moves past the next value whatever its kind, including arrays and maps
+    /// along with their items. A nested message that isn't delimited is a series
+    /// of values rather than a single one.
+    fn skip_value(&mut self) -> Result<(), DecodingError>;
 }
 
-impl Decoder for Cursor<&mut Vec<u8>> {
+/// The default limit on how deeply messages may be nested when decoding, which
+/// keeps hostile payloads from overflowing the stack.
+pub const MAX_DEPTH: usize = 100;
+
+/// The limit on how many elements a run-length encoded array may hold when
+/// decoding, which keeps a few bytes of runs from allocating gigabytes.
+pub const MAX_RUN_LENGTH: usize = 1 << 24;
+
+/// Buffers that a `Cursor` can decode from.
+pub trait DecoderBuffer: AsRef<[u8]> {}
+
+impl DecoderBuffer for &mut Vec<u8> {}
+
+impl DecoderBuffer for &[u8] {}
+
+/// Borrows the next `size` bytes of the buffer and moves past them.
+pub(crate) fn read_slice<T: DecoderBuffer>(cursor: &mut Cursor<T>, size: usize) -> Option<&[u8]> {
+    let start = usize::try_from(cursor.position()).ok()?;
+    let end = start.checked_add(size)?;
+    if end > cursor.get_ref().as_ref().len() {
+        return None;
+    }
+    cursor.set_position(end as u64);
+    Some(&cursor.get_ref().as_ref()[start..end])
+}
+
+/// Moves past the next value, giving up on arrays and maps nested more than
+/// `depth` levels deep.
+fn skip<T: DecoderBuffer>(cursor: &mut Cursor<T>, depth: usize) -> Result<(), DecodingError> {
+    if depth == 0 {
+        return Err(DecodingError::MaxDepthExceeded);
+    }
+    let kind = cursor.read_u8().ok().ok_or(DecodingError::InvalidKind)?;
+    match Kind::from(kind) {
+        Kind::None => {}
+        Kind::Array => {
+            cursor.read_u8().ok().ok_or(DecodingError::InvalidArray)?;
+            for _ in 0..cursor.decode_u32()? {
+                skip(cursor, depth - 1)?;
+            }
+        }
+        kind @ (Kind::PackedArray | Kind::DeltaArray | Kind::RunLengthArray) => {
+            let val_kind = cursor.read_u8().ok().ok_or(DecodingError::InvalidArray)?;
+            let size = cursor.decode_u32()? as usize;
+            skip_packed(cursor, kind, Kind::from(val_kind), size)?;
+        }
+        Kind::Map => {
+            cursor
+                .read_u16::<BigEndian>()
+                .ok()
+                .ok_or(DecodingError::InvalidMap)?;
+            for _ in 0..cursor.decode_u32()? {
+                skip(cursor, depth - 1)?;
+                skip(cursor, depth - 1)?;
+            }
+        }
+        Kind::StringTable => {
+            for _ in 0..cursor.decode_u32()? {
+                skip(cursor, depth - 1)?;
+            }
+        }
+        Kind::StringRef => {
+            cursor.decode_u32()?;
+        }
+        Kind::Compressed => {
+            read_slice(cursor, 1).ok_or(DecodingError::InvalidBytes)?;
+            cursor.decode_u32()?;
+            let size = cursor.decode_u32()? as usize;
+            read_slice(cursor, size).ok_or(DecodingError::InvalidBytes)?;
+        }
+        Kind::Any | Kind::Bytes | Kind::String => {
+            let size = cursor.decode_u32()? as usize;
+            read_slice(cursor, size).ok_or(DecodingError::InvalidBytes)?;
+        }
+        Kind::Error => {
+            cursor.set_position(cursor.position() - 1);
+            cursor.decode_error()?;
+        }
+        Kind::Bool | Kind::U8 => {
+            read_slice(cursor, 1).ok_or(DecodingError::InvalidU8)?;
+        }
+        Kind::U16 | Kind::U32 | Kind::U64 | Kind::I32 | Kind::I64 => {
+            for _ in 0..VARINT_LEN64 {
+                let byte = cursor.read_u8().ok().ok_or(DecodingError::InvalidU64)?;
+                if byte < CONTINUATION {
+                    return Ok(());
+                }
+            }
+            return Err(DecodingError::InvalidU64);
+        }
+        Kind::F32 => {
+            read_slice(cursor, 4).ok_or(DecodingError::InvalidF32)?;
+        }
+        Kind::F64 => {
+            read_slice(cursor, 8).ok_or(DecodingError::InvalidF64)?;
+        }
+        Kind::Unknown => {
+            cursor.set_position(cursor.position() - 1);
+            return Err(DecodingError::InvalidKind);
+        }
+    }
+    Ok(())
+}
+
+impl<T: DecoderBuffer> Decoder for Cursor<T> {
     fn decode_none(&mut self) -> bool {
         if let Ok(kind) = self.read_u8() {
             if kind == Kind::None as u8 {
                 return true;
             }
+            self.set_position(self.position() - 1);
         }
-        self.set_position(self.position() - 1);
         false
     }
 
@@ -115,38 +271,48 @@ impl Decoder for Cursor<&mut Vec<u8>> {
     }
 
     fn decode_bytes(&mut self) -> Result<Vec<u8>, DecodingError> {
+        self.decode_byte_slice().map(<[u8]>::to_vec)
+    }
+
+    fn decode_string(&mut self) -> Result<String, DecodingError> {
+        self.decode_str().map(str::to_owned)
+    }
+
+    fn decode_byte_slice(&mut self) -> Result<&[u8], DecodingError> {
         let kind = self.read_u8().ok().ok_or(DecodingError::InvalidBytes)?;
         if kind == Kind::Bytes as u8 {
             let size = self.decode_u32()? as usize;
-            let mut buf = vec![0u8; size];
-            self.read_exact(&mut buf)
-                .ok()
-                .ok_or(DecodingError::InvalidBytes)?;
-            return Ok(buf);
+            return read_slice(self, size).ok_or(DecodingError::InvalidBytes);
         }
         self.set_position(self.position() - 1);
         Err(DecodingError::InvalidBytes)
     }
 
-    fn decode_string(&mut self) -> Result<String, DecodingError> {
+    fn decode_str(&mut self) -> Result<&str, DecodingError> {
         let kind = self.read_u8().ok().ok_or(DecodingError::InvalidString)?;
-
         if kind == Kind::String as u8 {
             let size = self.decode_u32()? as usize;
-            let mut str_buf = vec![0u8; size];
-            self.read_exact(&mut str_buf)
-                .ok()
-                .ok_or(DecodingError::InvalidString)?;
-
-            let result = str::from_utf8(&str_buf)
-                .ok()
-                .ok_or(DecodingError::InvalidString)?;
-            return Ok(result.to_owned());
+            let buf = read_slice(self, size).ok_or(DecodingError::InvalidString)?;
+            return str::from_utf8(buf).ok().ok_or(DecodingError::InvalidString);
         }
         self.set_position(self.position() - 1);
         Err(DecodingError::InvalidString)
     }
 
+    fn decode_bytes_into(&mut self, buf: &mut Vec<u8>) -> Result<(), DecodingError> {
+        let val = self.decode_byte_slice()?;
+        buf.clear();
+        buf.extend_from_slice(val);
+        Ok(())
+    }
+
+    fn decode_string_into(&mut self, buf: &mut String) -> Result<(), DecodingError> {
+        let val = self.decode_str()?;
+        buf.clear();
+        buf.push_str(val);
+        Ok(())
+    }
+
     fn decode_error(&mut self) -> Result<Box<dyn Error>, DecodingError> {
         let kind = self.read_u8().ok().ok_or(DecodingError::InvalidError)?;
         let nested_kind = self.read_u8().ok().ok_or(DecodingError::InvalidError)?;
@@ -251,7 +417,8 @@ impl Decoder for Cursor<&mut Vec<u8>> {
             for _ in 0..VARINT_LEN32 {
                 let byte = self.read_u8().ok().ok_or(DecodingError::InvalidI32)?;
                 if byte < CONTINUATION {
-                    let mut x = ((ux | (byte as u32) << s) >> 1) as i32;
+                    ux |= (byte as u32) << s;
+                    let mut x = (ux >> 1) as i32;
                     if ux & 1 != 0 {
                         x = !x
                     }
@@ -274,7 +441,8 @@ impl Decoder for Cursor<&mut Vec<u8>> {
             for _ in 0..VARINT_LEN64 {
                 let byte = self.read_u8().ok().ok_or(DecodingError::InvalidI64)?;
                 if byte < CONTINUATION {
-                    let mut x = ((ux | (byte as u64) << s) >> 1) as i64;
+                    ux |= (byte as u64) << s;
+                    let mut x = (ux >> 1) as i64;
                     if ux & 1 != 0 {
                         x = !x
                     }
@@ -311,4 +479,126 @@ impl Decoder for Cursor<&mut Vec<u8>> {
         self.set_position(self.position() - 1);
         Err(DecodingError::InvalidF64)
     }
+
+    fn decode_u32_vec(&mut self) -> Result<Vec<u32>, DecodingError> {
+        let mut buf = Vec::new();
+        decode_packed(self, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn decode_u32_vec_into(&mut self, buf: &mut Vec<u32>) -> Result<(), DecodingError> {
+        decode_packed(self, buf)
+    }
+
+    fn decode_u64_vec(&mut self) -> Result<Vec<u64>, DecodingError> {
+        let mut buf = Vec::new();
+        decode_packed(self, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn decode_u64_vec_into(&mut self, buf: &mut Vec<u64>) -> Result<(), DecodingError> {
+        decode_packed(self, buf)
+    }
+
+    fn decode_i32_vec(&mut self) -> Result<Vec<i32>, DecodingError> {
+        let mut buf = Vec::new();
+        decode_packed(self, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn decode_i32_vec_into(&mut self, buf: &mut Vec<i32>) -> Result<(), DecodingError> {
+        decode_packed(self, buf)
+    }
+
+    fn decode_i64_vec(&mut self) -> Result<Vec<i64>, DecodingError> {
+        let mut buf = Vec::new();
+        decode_packed(self, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn decode_i64_vec_into(&mut self, buf: &mut Vec<i64>) -> Result<(), DecodingError> {
+        decode_packed(self, buf)
+    }
+
+    fn decode_f32_vec(&mut self) -> Result<Vec<f32>, DecodingError> {
+        let mut buf = Vec::new();
+        decode_packed(self, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn decode_f32_vec_into(&mut self, buf: &mut Vec<f32>) -> Result<(), DecodingError> {
+        decode_packed(self, buf)
+    }
+
+    fn decode_f64_vec(&mut self) -> Result<Vec<f64>, DecodingError> {
+        let mut buf = Vec::new();
+        decode_packed(self, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn decode_f64_vec_into(&mut self, buf: &mut Vec<f64>) -> Result<(), DecodingError> {
+        decode_packed(self, buf)
+    }
+
+    fn decode_bool_vec(&mut self) -> Result<Vec<bool>, DecodingError> {
+        Ok(decode_bits(self)?.iter().collect())
+    }
+
+    fn decode_bool_vec_into(&mut self, buf: &mut Vec<bool>) -> Result<(), DecodingError> {
+        let bits = decode_bits(self)?;
+        buf.clear();
+        buf.extend(bits.iter());
+        Ok(())
+    }
+
+    fn decode_bits(&mut self) -> Result<Bits, DecodingError> {
+        decode_bits(self)
+    }
+
+    fn decode_string_table(&mut self) -> Result<StringTable, DecodingError> {
+        let mut table = StringTable::new();
+        if self.get_ref().as_ref().get(self.position() as usize) != Some(&(Kind::StringTable as u8))
+        {
+            return Ok(table);
+        }
+        self.set_position(self.position() + 1);
+        let size = self.decode_u32()? as usize;
+        // Every string takes up at least three bytes.
+        let len = self.get_ref().as_ref().len() as u64;
+        let remaining = len.saturating_sub(self.position());
+        if size as u64 > remaining / 3 {
+            return Err(DecodingError::InvalidString);
+        }
+        for _ in 0..size {
+            table.push(self.decode_str()?);
+        }
+        Ok(table)
+    }
+
+    fn decode_interned_str(&mut self, table: &StringTable) -> Result<Arc<str>, DecodingError> {
+        if self.get_ref().as_ref().get(self.position() as usize) == Some(&(Kind::StringRef as u8)) {
+            self.set_position(self.position() + 1);
+            let id = self.decode_u32()?;
+            return table.get(id).cloned().ok_or(DecodingError::InvalidString);
+        }
+        self.decode_str().map(Arc::from)
+    }
+
+    fn decode_delimited(&mut self) -> Result<u64, DecodingError> {
+        let kind = self.read_u8().ok().ok_or(DecodingError::InvalidStruct)?;
+        if kind == Kind::Any as u8 {
+            let size = self.decode_u32()? as u64;
+            let end = self.position() + size;
+            if end > self.get_ref().as_ref().len() as u64 {
+                return Err(DecodingError::InvalidStruct);
+            }
+            return Ok(end);
+        }
+        self.set_position(self.position() - 1);
+        Err(DecodingError::InvalidStruct)
+    }
+
+    fn skip_value(&mut self) -> Result<(), DecodingError> {
+        skip(self, MAX_DEPTH)
+    }
 }
@@ -0,0 +1,477 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::decoder::{Decoder, DecoderBuffer, DecodingError, MAX_DEPTH};
+use crate::encoder::{Encoder, EncodingError};
+use crate::kind::Kind;
+use crate::schema::{skip_to, FieldType, MessageSchema, Schema};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Cursor;
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq)]
+pub enum DynamicError {
+    UnknownMessage(String),
+    UnknownField(String),
+    InvalidValue(String),
+    Decoding(DecodingError),
+    Encoding(EncodingError),
+    Remote(String),
+}
+
+impl Display for DynamicError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for DynamicError {}
+
+impl From<DecodingError> for DynamicError {
+    fn from(err: DecodingError) -> Self {
+        DynamicError::Decoding(err)
+    }
+}
+
+impl From<EncodingError> for DynamicError {
+    fn from(err: EncodingError) -> Self {
+        DynamicError::Encoding(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    None,
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Enum(u32),
+    Message(DynamicMessage),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    /// The member of a oneof that is set, by field number. Unset oneofs are
+    /// `Value::None`.
+    Oneof(u32, Box<Value>),
+}
+
+impl Value {
+    /// The zero value for a field of type `ty`. Nested messages, optional values and
+    /// oneofs default to `Value::None`.
+    pub fn default_for(ty: &FieldType) -> Self {
+        match ty {
+            FieldType::Bool => Value::Bool(false),
+            FieldType::U8 => Value::U8(0),
+            FieldType::U16 => Value::U16(0),
+            FieldType::U32 => Value::U32(0),
+            FieldType::U64 => Value::U64(0),
+            FieldType::I32 => Value::I32(0),
+            FieldType::I64 => Value::I64(0),
+            FieldType::F32 => Value::F32(0.0),
+            FieldType::F64 => Value::F64(0.0),
+            FieldType::String => Value::String(String::new()),
+            FieldType::Bytes => Value::Bytes(Vec::new()),
+            FieldType::Enum(_) => Value::Enum(0),
+            FieldType::Message(_) => Value::None,
+            FieldType::Array(_) => Value::Array(Vec::new()),
+            FieldType::Map(_, _) => Value::Map(Vec::new()),
+            FieldType::Optional(_) | FieldType::Oneof(_) => Value::None,
+        }
+    }
+
+    /// Returns true if this value can be encoded as a field of type `ty`.
+    pub fn is_valid_for(&self, ty: &FieldType) -> bool {
+        match (self, ty) {
+            (Value::Bool(_), FieldType::Bool)
+            | (Value::U8(_), FieldType::U8)
+            | (Value::U16(_), FieldType::U16)
+            | (Value::U32(_), FieldType::U32)
+            | (Value::U64(_), FieldType::U64)
+            | (Value::I32(_), FieldType::I32)
+            | (Value::I64(_), FieldType::I64)
+            | (Value::F32(_), FieldType::F32)
+            | (Value::F64(_), FieldType::F64)
+            | (Value::String(_), FieldType::String)
+            | (Value::Bytes(_), FieldType::Bytes)
+            | (Value::Enum(_), FieldType::Enum(_))
+            | (Value::None, FieldType::Message(_))
+            | (Value::None, FieldType::Optional(_))
+            | (Value::None, FieldType::Oneof(_)) => true,
+            (value, FieldType::Optional(val)) => value.is_valid_for(val),
+            (Value::Oneof(number, value), FieldType::Oneof(members)) => members
+                .iter()
+                .any(|(n, ty)| n == number && value.is_valid_for(ty)),
+            (Value::Message(m), FieldType::Message(name)) => m.name == *name,
+            (Value::Array(items), FieldType::Array(val)) => {
+                items.iter().all(|item| item.is_valid_for(val))
+            }
+            (Value::Map(entries), FieldType::Map(key, val)) => entries
+                .iter()
+                .all(|(k, v)| k.is_valid_for(key) && v.is_valid_for(val)),
+            _ => false,
+        }
+    }
+
+    pub fn as_message(&self) -> Option<&DynamicMessage> {
+        match self {
+            Value::Message(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// A message whose layout is only known at runtime through a `Schema`.
+///
+/// Fields are addressed by name and kept in declaration order; encoding and
+/// decoding follow the same wire order as generated code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynamicMessage {
+    schema: Arc<Schema>,
+    name: String,
+    fields: Vec<(String, Value)>,
+}
+
+impl DynamicMessage {
+    /// Creates a message of type `name` with every field set to its default value.
+    pub fn new(schema: Arc<Schema>, name: &str) -> Result<Self, DynamicError> {
+        let fields = message_schema(&schema, name)?
+            .fields
+            .iter()
+            .map(|f| (f.name.clone(), Value::default_for(&f.ty)))
+            .collect();
+        Ok(DynamicMessage {
+            schema,
+            name: name.to_owned(),
+            fields,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema(&self) -> &Arc<Schema> {
+        &self.schema
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.fields.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.fields
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    /// Replaces the value of a field, checking it against the field's declared type.
+    pub fn set(&mut self, name: &str, value: Value) -> Result<(), DynamicError> {
+        let field = message_schema(&self.schema, &self.name)?
+            .get_field(name)
+            .ok_or_else(|| DynamicError::UnknownField(name.to_owned()))?;
+        if !value.is_valid_for(&field.ty) {
+            return Err(DynamicError::InvalidValue(name.to_owned()));
+        }
+        if let Some(v) = self.get_mut(name) {
+            *v = value;
+        }
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.fields.iter().map(|(n, v)| (n.as_str(), v))
+    }
+
+    /// Decodes the schema's root message.
+    pub fn decode<T: DecoderBuffer>(
+        schema: Arc<Schema>,
+        b: &mut Cursor<T>,
+    ) -> Result<Option<Self>, DynamicError> {
+        let root = schema.root.clone();
+        Self::decode_message(schema, &root, b)
+    }
+
+    pub fn decode_message<T: DecoderBuffer>(
+        schema: Arc<Schema>,
+        name: &str,
+        b: &mut Cursor<T>,
+    ) -> Result<Option<Self>, DynamicError> {
+        Self::decode_with_depth(schema, name, b, MAX_DEPTH)
+    }
+
+    fn decode_with_depth<T: DecoderBuffer>(
+        schema: Arc<Schema>,
+        name: &str,
+        b: &mut Cursor<T>,
+        depth: usize,
+    ) -> Result<Option<Self>, DynamicError> {
+        match peek(b) {
+            Some(Kind::None) => {
+                b.decode_none();
+                return Ok(None);
+            }
+            Some(Kind::Error) => {
+                return Err(DynamicError::Remote(b.decode_error()?.to_string()));
+            }
+            _ => {}
+        }
+        if depth == 0 {
+            return Err(DecodingError::MaxDepthExceeded.into());
+        }
+
+        let mut message = DynamicMessage::new(schema.clone(), name)?;
+        let message_schema = message_schema(&schema, name)?;
+        let end = if message_schema.delimited {
+            Some(b.decode_delimited()?)
+        } else {
+            None
+        };
+        for field in message_schema.wire_fields() {
+            if end.is_some_and(|end| b.position() >= end) {
+                break;
+            }
+            let value = decode_value(&schema, &field.ty, b, depth)?;
+            if let Some(v) = message.get_mut(&field.name) {
+                *v = value;
+            }
+        }
+        if let Some(end) = end {
+            skip_to(b, end)?;
+        }
+        Ok(Some(message))
+    }
+
+    pub fn encode<'a>(
+        &'a self,
+        b: &'a mut Cursor<Vec<u8>>,
+    ) -> Result<&'a mut Cursor<Vec<u8>>, DynamicError> {
+        let message_schema = message_schema(&self.schema, &self.name)?;
+        let start = if message_schema.delimited {
+            Some(b.reserve_delimited()?)
+        } else {
+            None
+        };
+        for field in message_schema.wire_fields() {
+            let value = self
+                .get(&field.name)
+                .ok_or_else(|| DynamicError::UnknownField(field.name.clone()))?;
+            if !value.is_valid_for(&field.ty) {
+                return Err(DynamicError::InvalidValue(field.name.clone()));
+            }
+            encode_value(value, &field.ty, b)?;
+        }
+        if let Some(start) = start {
+            b.encode_delimited(start)?;
+        }
+        Ok(b)
+    }
+}
+
+fn message_schema<'a>(schema: &'a Schema, name: &str) -> Result<&'a MessageSchema, DynamicError> {
+    schema
+        .get_message(name)
+        .ok_or_else(|| DynamicError::UnknownMessage(name.to_owned()))
+}
+
+fn peek<T: DecoderBuffer>(b: &Cursor<T>) -> Option<Kind> {
+    b.get_ref()
+        .as_ref()
+        .get(b.position() as usize)
+        .map(|kind| Kind::from(*kind))
+}
+
+/// The number of bytes left in the buffer, which bounds how many values can
+/// follow since every value takes up at least one.
+fn remaining<T: DecoderBuffer>(b: &Cursor<T>) -> usize {
+    (b.get_ref().as_ref().len() as u64).saturating_sub(b.position()) as usize
+}
+
+fn decode_value<T: DecoderBuffer>(
+    schema: &Arc<Schema>,
+    ty: &FieldType,
+    b: &mut Cursor<T>,
+    depth: usize,
+) -> Result<Value, DynamicError> {
+    Ok(match ty {
+        FieldType::Bool => Value::Bool(b.decode_bool()?),
+        FieldType::U8 => Value::U8(b.decode_u8()?),
+        FieldType::U16 => Value::U16(b.decode_u16()?),
+        FieldType::U32 => Value::U32(b.decode_u32()?),
+        FieldType::U64 => Value::U64(b.decode_u64()?),
+        FieldType::I32 => Value::I32(b.decode_i32()?),
+        FieldType::I64 => Value::I64(b.decode_i64()?),
+        FieldType::F32 => Value::F32(b.decode_f32()?),
+        FieldType::F64 => Value::F64(b.decode_f64()?),
+        FieldType::String => Value::String(b.decode_string()?),
+        FieldType::Bytes => Value::Bytes(b.decode_bytes()?),
+        FieldType::Enum(_) => Value::Enum(b.decode_u32()?),
+        FieldType::Message(name) => {
+            match DynamicMessage::decode_with_depth(schema.clone(), name, b, depth - 1)? {
+                Some(message) => Value::Message(message),
+                None => Value::None,
+            }
+        }
+        FieldType::Array(val) => match **val {
+            // Arrays of numbers may be packed, delta encoded or run-length encoded,
+            // and arrays of bools packed into bits.
+            FieldType::Bool => Value::Array(b.decode_bits()?.iter().map(Value::Bool).collect()),
+            FieldType::U32 => {
+                Value::Array(b.decode_u32_vec()?.into_iter().map(Value::U32).collect())
+            }
+            FieldType::U64 => {
+                Value::Array(b.decode_u64_vec()?.into_iter().map(Value::U64).collect())
+            }
+            FieldType::I32 => {
+                Value::Array(b.decode_i32_vec()?.into_iter().map(Value::I32).collect())
+            }
+            FieldType::I64 => {
+                Value::Array(b.decode_i64_vec()?.into_iter().map(Value::I64).collect())
+            }
+            FieldType::F32 => {
+                Value::Array(b.decode_f32_vec()?.into_iter().map(Value::F32).collect())
+            }
+            FieldType::F64 => {
+                Value::Array(b.decode_f64_vec()?.into_iter().map(Value::F64).collect())
+            }
+            _ => {
+                let size = b.decode_array(val.kind())?;
+                let mut items = Vec::with_capacity(size.min(remaining(b)));
+                for _ in 0..size {
+                    items.push(decode_value(schema, val, b, depth)?);
+                }
+                Value::Array(items)
+            }
+        },
+        FieldType::Optional(val) => {
+            if b.decode_none() {
+                Value::None
+            } else {
+                decode_value(schema, val, b, depth)?
+            }
+        }
+        FieldType::Oneof(members) => match b.decode_u32()? {
+            0 => Value::None,
+            number => {
+                let (_, ty) = members
+                    .iter()
+                    .find(|(n, _)| *n == number)
+                    .ok_or(DecodingError::InvalidOneof)?;
+                Value::Oneof(number, Box::new(decode_value(schema, ty, b, depth)?))
+            }
+        },
+        FieldType::Map(key, val) => {
+            let size = b.decode_map(key.kind(), val.kind())?;
+            let mut entries = Vec::with_capacity(size.min(remaining(b)));
+            for _ in 0..size {
+                let k = decode_value(schema, key, b, depth)?;
+                let v = decode_value(schema, val, b, depth)?;
+                entries.push((k, v));
+            }
+            Value::Map(entries)
+        }
+    })
+}
+
+fn encode_value(
+    value: &Value,
+    ty: &FieldType,
+    b: &mut Cursor<Vec<u8>>,
+) -> Result<(), DynamicError> {
+    match (value, ty) {
+        (Value::None, FieldType::Oneof(_)) => {
+            b.encode_u32(0)?;
+        }
+        (Value::Oneof(number, value), FieldType::Oneof(members)) => {
+            let (_, ty) = members
+                .iter()
+                .find(|(n, _)| n == number)
+                .ok_or_else(|| DynamicError::InvalidValue(format!("{value:?}")))?;
+            b.encode_u32(*number)?;
+            encode_value(value, ty, b)?;
+        }
+        (Value::None, _) => {
+            b.encode_none()?;
+        }
+        (Value::Bool(v), _) => {
+            b.encode_bool(*v)?;
+        }
+        (Value::U8(v), _) => {
+            b.encode_u8(*v)?;
+        }
+        (Value::U16(v), _) => {
+            b.encode_u16(*v)?;
+        }
+        (Value::U32(v), _) | (Value::Enum(v), _) => {
+            b.encode_u32(*v)?;
+        }
+        (Value::U64(v), _) => {
+            b.encode_u64(*v)?;
+        }
+        (Value::I32(v), _) => {
+            b.encode_i32(*v)?;
+        }
+        (Value::I64(v), _) => {
+            b.encode_i64(*v)?;
+        }
+        (Value::F32(v), _) => {
+            b.encode_f32(*v)?;
+        }
+        (Value::F64(v), _) => {
+            b.encode_f64(*v)?;
+        }
+        (Value::String(v), _) => {
+            b.encode_str(v)?;
+        }
+        (Value::Bytes(v), _) => {
+            b.encode_bytes(v)?;
+        }
+        (Value::Message(m), _) => {
+            m.encode(b)?;
+        }
+        (Value::Array(items), FieldType::Array(val)) => {
+            b.encode_array(items.len(), val.kind())?;
+            for item in items {
+                encode_value(item, val, b)?;
+            }
+        }
+        (value, FieldType::Optional(val)) => encode_value(value, val, b)?,
+        (Value::Map(entries), FieldType::Map(key, val)) => {
+            b.encode_map(entries.len(), key.kind(), val.kind())?;
+            for (k, v) in entries {
+                encode_value(k, key, b)?;
+                encode_value(v, val, b)?;
+            }
+        }
+        _ => return Err(DynamicError::InvalidValue(format!("{value:?}"))),
+    }
+    Ok(())
+}
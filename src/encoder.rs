@@ -14,7 +14,9 @@
     limitations under the License.
 bv c */
 
+use crate::bits::{write_bits, Bits};
 use crate::kind::Kind;
+use crate::packed::{write_delta, write_packed, write_run_length, Packed};
 use byteorder::{BigEndian, WriteBytesExt};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
@@ -23,9 +25,13 @@ use std::io::{Cursor, Write};
 
 const CONTINUATION: u8 = 0x80;
 
+/// The length of a `Kind::Any` header holding a size padded to five bytes.
+const DELIMITED_HEADER_LEN: usize = 7;
+
 #[derive(Debug, PartialEq)]
 pub enum EncodingError {
     WriteFailed,
+    TooLarge,
 }
 
 impl Display for EncodingError {
@@ -91,6 +97,73 @@ pub trait Encoder {
     fn encode_f64(self, val: f64) -> Result<Self, EncodingError>
     where
         Self: Sized;
+    /// Encodes `vals` as a packed array, which only readers that understand
+    /// `Kind::PackedArray` can decode.
+    fn encode_u32_slice(self, vals: &[u32]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_u64_slice(self, vals: &[u64]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_i32_slice(self, vals: &[i32]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_i64_slice(self, vals: &[i64]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_f32_slice(self, vals: &[f32]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_f64_slice(self, vals: &[f64]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    /// Encodes `vals` as a packed array of bits, which only readers that
+    /// understand `Kind::PackedArray` can decode.
+    fn encode_bool_slice(self, vals: &[bool]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_bits(self, bits: &Bits) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    /// Encodes `vals` as the differences between them, which takes up less room
+    /// than a packed array when they are sorted or close together. Only readers
+    /// that understand `Kind::DeltaArray` can decode it.
+    fn encode_i64_delta_slice(self, vals: &[i64]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_u64_delta_slice(self, vals: &[u64]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    /// Encodes `vals` as runs of the same value, which takes up less room than a
+    /// packed array when values repeat. Only readers that understand
+    /// `Kind::RunLengthArray` can decode it.
+    fn encode_u32_run_length_slice(self, vals: &[u32]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_u64_run_length_slice(self, vals: &[u64]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_i32_run_length_slice(self, vals: &[i32]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_i64_run_length_slice(self, vals: &[i64]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_f32_run_length_slice(self, vals: &[f32]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    fn encode_f64_run_length_slice(self, vals: &[f64]) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
+    /// Reserves a `Kind::Any` header for a delimited value and returns the
+    /// position to pass to `encode_delimited` once the value is encoded.
+    fn reserve_delimited(self) -> Result<u64, EncodingError>;
+    /// Turns everything encoded since the header reserved at `start` into a
+    /// single value by writing its length into the header, failing with
+    /// `TooLarge` when it doesn't fit in a `u32`.
+    fn encode_delimited(self, start: u64) -> Result<Self, EncodingError>
+    where
+        Self: Sized;
 }
 
 impl Encoder for &mut Cursor<Vec<u8>> {
@@ -243,4 +316,130 @@ impl Encoder for &mut Cursor<Vec<u8>> {
         self.write_f64::<BigEndian>(val)?;
         Ok(self)
     }
+
+    // The length is written as a varint padded to its largest size, so that the
+    // header can be filled in without moving the value behind it.
+    fn reserve_delimited(self) -> Result<u64, EncodingError> {
+        let start = self.position();
+        self.write_u8(Kind::Any as u8)?;
+        self.write_u8(Kind::U32 as u8)?;
+        self.write_all(&[CONTINUATION, CONTINUATION, CONTINUATION, CONTINUATION, 0])?;
+        Ok(start)
+    }
+
+    fn encode_delimited(self, start: u64) -> Result<Self, EncodingError> {
+        let start = usize::try_from(start)
+            .ok()
+            .filter(|&start| self.get_ref().get(start) == Some(&(Kind::Any as u8)))
+            .ok_or(EncodingError::WriteFailed)?;
+        let size = (self.position() as usize)
+            .checked_sub(start + DELIMITED_HEADER_LEN)
+            .ok_or(EncodingError::WriteFailed)?;
+        let mut size = u32::try_from(size).ok().ok_or(EncodingError::TooLarge)?;
+        let (last, padded) = self.get_mut()[start + 2..start + DELIMITED_HEADER_LEN]
+            .split_last_mut()
+            .unwrap();
+        for byte in padded {
+            *byte = size as u8 | CONTINUATION;
+            size >>= 7;
+        }
+        *last = size as u8;
+        Ok(self)
+    }
+
+    fn encode_u32_slice(self, vals: &[u32]) -> Result<Self, EncodingError> {
+        encode_packed(self, vals)
+    }
+
+    fn encode_u64_slice(self, vals: &[u64]) -> Result<Self, EncodingError> {
+        encode_packed(self, vals)
+    }
+
+    fn encode_i32_slice(self, vals: &[i32]) -> Result<Self, EncodingError> {
+        encode_packed(self, vals)
+    }
+
+    fn encode_i64_slice(self, vals: &[i64]) -> Result<Self, EncodingError> {
+        encode_packed(self, vals)
+    }
+
+    fn encode_f32_slice(self, vals: &[f32]) -> Result<Self, EncodingError> {
+        encode_packed(self, vals)
+    }
+
+    fn encode_f64_slice(self, vals: &[f64]) -> Result<Self, EncodingError> {
+        encode_packed(self, vals)
+    }
+
+    fn encode_bool_slice(self, vals: &[bool]) -> Result<Self, EncodingError> {
+        encode_bits(self, vals.len(), &write_bits(vals))
+    }
+
+    fn encode_bits(self, bits: &Bits) -> Result<Self, EncodingError> {
+        encode_bits(self, bits.len(), bits.as_bytes())
+    }
+
+    fn encode_i64_delta_slice(self, vals: &[i64]) -> Result<Self, EncodingError> {
+        encode_array(self, Kind::DeltaArray, vals, write_delta(vals))
+    }
+
+    fn encode_u64_delta_slice(self, vals: &[u64]) -> Result<Self, EncodingError> {
+        encode_array(self, Kind::DeltaArray, vals, write_delta(vals))
+    }
+
+    fn encode_u32_run_length_slice(self, vals: &[u32]) -> Result<Self, EncodingError> {
+        encode_array(self, Kind::RunLengthArray, vals, write_run_length(vals))
+    }
+
+    fn encode_u64_run_length_slice(self, vals: &[u64]) -> Result<Self, EncodingError> {
+        encode_array(self, Kind::RunLengthArray, vals, write_run_length(vals))
+    }
+
+    fn encode_i32_run_length_slice(self, vals: &[i32]) -> Result<Self, EncodingError> {
+        encode_array(self, Kind::RunLengthArray, vals, write_run_length(vals))
+    }
+
+    fn encode_i64_run_length_slice(self, vals: &[i64]) -> Result<Self, EncodingError> {
+        encode_array(self, Kind::RunLengthArray, vals, write_run_length(vals))
+    }
+
+    fn encode_f32_run_length_slice(self, vals: &[f32]) -> Result<Self, EncodingError> {
+        encode_array(self, Kind::RunLengthArray, vals, write_run_length(vals))
+    }
+
+    fn encode_f64_run_length_slice(self, vals: &[f64]) -> Result<Self, EncodingError> {
+        encode_array(self, Kind::RunLengthArray, vals, write_run_length(vals))
+    }
+}
+
+fn encode_packed<'a, P: Packed>(
+    b: &'a mut Cursor<Vec<u8>>,
+    vals: &[P],
+) -> Result<&'a mut Cursor<Vec<u8>>, EncodingError> {
+    encode_array(b, Kind::PackedArray, vals, write_packed(vals))
+}
+
+fn encode_bits<'a>(
+    b: &'a mut Cursor<Vec<u8>>,
+    len: usize,
+    bytes: &[u8],
+) -> Result<&'a mut Cursor<Vec<u8>>, EncodingError> {
+    b.write_u8(Kind::PackedArray as u8)?;
+    b.write_u8(Kind::Bool as u8)?;
+    b.encode_u32(len as u32)?;
+    b.write_all(bytes)?;
+    Ok(b)
+}
+
+fn encode_array<'a, P: Packed>(
+    b: &'a mut Cursor<Vec<u8>>,
+    kind: Kind,
+    vals: &[P],
+    body: Vec<u8>,
+) -> Result<&'a mut Cursor<Vec<u8>>, EncodingError> {
+    b.write_u8(kind as u8)?;
+    b.write_u8(P::KIND as u8)?;
+    b.encode_u32(vals.len() as u32)?;
+    b.write_all(&body)?;
+    Ok(b)
 }
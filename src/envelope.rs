@@ -0,0 +1,226 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::decoder::{Decoder, DecoderBuffer, DecodingError};
+use crate::encoder::Encoder;
+use crate::kind::Kind;
+use crate::message::{Decode, Encode};
+use std::any::Any;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Cursor;
+
+/// Implemented by generated messages, giving the fully qualified name of the
+/// message in its `.proto` file.
+pub trait Named {
+    const FULL_NAME: &'static str;
+}
+
+/// Identifies the type of the message in an [`Envelope`], either with an ID
+/// agreed on by both sides or with the fully qualified name of the message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MessageType {
+    Id(u32),
+    Name(String),
+}
+
+impl From<u32> for MessageType {
+    fn from(id: u32) -> Self {
+        MessageType::Id(id)
+    }
+}
+
+impl From<&str> for MessageType {
+    fn from(name: &str) -> Self {
+        MessageType::Name(name.to_owned())
+    }
+}
+
+impl From<String> for MessageType {
+    fn from(name: String) -> Self {
+        MessageType::Name(name)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnvelopeError {
+    UnknownType(MessageType),
+}
+
+impl Display for EnvelopeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for EnvelopeError {}
+
+/// An encoded message along with its type, so that messages of different types
+/// can be sent over the same channel. It is written as a `u32` ID or a string
+/// name followed by the encoded message as bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub message_type: MessageType,
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    pub fn new(
+        message_type: impl Into<MessageType>,
+        message: &impl Encode,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Envelope {
+            message_type: message_type.into(),
+            payload: message.encode_to_vec()?,
+        })
+    }
+
+    /// Wraps `message` with its fully qualified name as the type.
+    pub fn named<T: Named + Encode>(message: &T) -> Result<Self, Box<dyn Error>> {
+        Self::new(T::FULL_NAME, message)
+    }
+
+    /// Decodes the payload, which must take up all of it, as a `T`.
+    pub fn open<T: Decode>(&self) -> Result<T, Box<dyn Error>> {
+        T::decode_from_slice(&self.payload)
+    }
+}
+
+impl Encode for Envelope {
+    fn encode<'a>(
+        &'a self,
+        b: &'a mut Cursor<Vec<u8>>,
+    ) -> Result<&'a mut Cursor<Vec<u8>>, Box<dyn Error>> {
+        match &self.message_type {
+            MessageType::Id(id) => b.encode_u32(*id)?,
+            MessageType::Name(name) => b.encode_str(name)?,
+        }
+        .encode_bytes(&self.payload)?;
+        Ok(b)
+    }
+}
+
+impl Decode for Envelope {
+    fn decode_with_depth(
+        b: &mut Cursor<impl DecoderBuffer>,
+        _depth: usize,
+    ) -> Result<Option<Self>, Box<dyn Error>> {
+        if b.decode_none() {
+            return Ok(None);
+        }
+
+        if let Ok(error) = b.decode_error() {
+            return Err(error);
+        }
+
+        let kind = b.get_ref().as_ref().get(b.position() as usize).copied();
+        let message_type = match kind {
+            Some(kind) if kind == Kind::U32 as u8 => MessageType::Id(b.decode_u32()?),
+            Some(kind) if kind == Kind::String as u8 => MessageType::Name(b.decode_string()?),
+            _ => return Err(DecodingError::InvalidStruct.into()),
+        };
+        Ok(Some(Envelope {
+            message_type,
+            payload: b.decode_bytes()?,
+        }))
+    }
+}
+
+type DecodeFn<T> = Box<dyn Fn(&[u8]) -> Result<T, Box<dyn Error>> + Send + Sync>;
+
+/// Maps the types of envelopes to functions decoding their payloads into a `T`,
+/// which is either a boxed message to be downcast or an enum of the messages a
+/// router handles.
+pub struct TypeRegistry<T = Box<dyn Any + Send>> {
+    types: HashMap<MessageType, DecodeFn<T>>,
+}
+
+impl<T> Default for TypeRegistry<T> {
+    fn default() -> Self {
+        TypeRegistry {
+            types: HashMap::new(),
+        }
+    }
+}
+
+impl<T> TypeRegistry<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes payloads of `message_type` as an `M` and wraps them with `wrap`.
+    /// Registering a type again replaces its decoder.
+    pub fn register_with<M, F>(
+        &mut self,
+        message_type: impl Into<MessageType>,
+        wrap: F,
+    ) -> &mut Self
+    where
+        M: Decode,
+        F: Fn(M) -> T + Send + Sync + 'static,
+    {
+        self.types.insert(
+            message_type.into(),
+            Box::new(move |payload| M::decode_from_slice(payload).map(&wrap)),
+        );
+        self
+    }
+
+    /// Registers `M` under its fully qualified name.
+    pub fn register_named_with<M, F>(&mut self, wrap: F) -> &mut Self
+    where
+        M: Named + Decode,
+        F: Fn(M) -> T + Send + Sync + 'static,
+    {
+        self.register_with(M::FULL_NAME, wrap)
+    }
+
+    pub fn contains(&self, message_type: &MessageType) -> bool {
+        self.types.contains_key(message_type)
+    }
+
+    /// Decodes the payload of `envelope` with the decoder registered for its type.
+    pub fn decode(&self, envelope: &Envelope) -> Result<T, Box<dyn Error>> {
+        let decode = self
+            .types
+            .get(&envelope.message_type)
+            .ok_or_else(|| EnvelopeError::UnknownType(envelope.message_type.clone()))?;
+        decode(&envelope.payload)
+    }
+
+    /// Decodes an envelope that takes up all of `buf`, and then its payload.
+    pub fn decode_from_slice(&self, buf: &[u8]) -> Result<T, Box<dyn Error>> {
+        self.decode(&Envelope::decode_from_slice(buf)?)
+    }
+}
+
+impl TypeRegistry<Box<dyn Any + Send>> {
+    /// Decodes payloads of `message_type` as a boxed `M`.
+    pub fn register<M: Decode + Send + 'static>(
+        &mut self,
+        message_type: impl Into<MessageType>,
+    ) -> &mut Self {
+        self.register_with(message_type, |message: M| {
+            Box::new(message) as Box<dyn Any + Send>
+        })
+    }
+
+    /// Registers `M` under its fully qualified name, decoding payloads as a boxed `M`.
+    pub fn register_named<M: Named + Decode + Send + 'static>(&mut self) -> &mut Self {
+        self.register::<M>(M::FULL_NAME)
+    }
+}
@@ -0,0 +1,241 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::decoder::{Decoder, DecodingError, MAX_DEPTH};
+use crate::kind::Kind;
+use crate::packed::write_varint;
+use byteorder::{BigEndian, ReadBytesExt};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::str;
+use std::sync::Arc;
+
+/// The limit on how large a message may grow to when `resolve` expands its
+/// string references, which keeps a long string referenced many times from
+/// allocating gigabytes.
+pub const MAX_RESOLVED_LEN: usize = 1 << 28;
+
+/// The strings of an interned message, in the order of their ids. Decoding the
+/// same id twice returns the same `Arc<str>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringTable {
+    strings: Vec<Arc<str>>,
+}
+
+impl StringTable {
+    pub fn new() -> Self {
+        StringTable::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Arc<str>> {
+        self.strings.get(id as usize)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.strings.iter()
+    }
+
+    pub(crate) fn push(&mut self, val: &str) {
+        self.strings.push(Arc::from(val));
+    }
+}
+
+/// Called by `walk` with every string it finds, to write it to the output.
+type OnFound<'a, 'b> = dyn FnMut(Found<'a>, &mut Vec<u8>) -> Result<(), DecodingError> + 'b;
+
+/// A string as it is found when walking a message.
+enum Found<'a> {
+    Str(&'a str),
+    Ref(u32),
+}
+
+/// Rewrites the encoded message `buf` so that every string it holds more than
+/// once is written once, in a `Kind::StringTable` in front of the message, and
+/// referred to by its id with a `Kind::StringRef` everywhere it is used. The
+/// table only covers `buf`, so every top-level message gets its own. Messages
+/// without repeated strings are returned unchanged.
+///
+/// Strings inside errors, bytes and the payloads of envelopes are left alone.
+pub fn intern(buf: &[u8]) -> Result<Vec<u8>, DecodingError> {
+    let mut found = Vec::new();
+    walk(buf, &mut Vec::new(), MAX_DEPTH, &mut |val, _| match val {
+        Found::Str(val) => {
+            found.push(val);
+            Ok(())
+        }
+        Found::Ref(_) => Err(DecodingError::InvalidString),
+    })?;
+
+    let mut counts = HashMap::<&str, usize>::new();
+    for val in &found {
+        *counts.entry(val).or_default() += 1;
+    }
+    let mut ids = HashMap::new();
+    let mut table = Vec::new();
+    for val in found {
+        if counts[val] > 1 && !ids.contains_key(val) {
+            ids.insert(val, ids.len() as u32);
+            write_str(&mut table, val);
+        }
+    }
+    if ids.is_empty() {
+        return Ok(buf.to_vec());
+    }
+
+    let mut out = Vec::with_capacity(buf.len());
+    out.push(Kind::StringTable as u8);
+    write_u32(&mut out, ids.len() as u32);
+    out.extend_from_slice(&table);
+    walk(buf, &mut out, MAX_DEPTH, &mut |val, out| match val {
+        Found::Str(val) => {
+            match ids.get(val) {
+                Some(id) => write_ref(out, *id),
+                None => write_str(out, val),
+            }
+            Ok(())
+        }
+        Found::Ref(_) => Err(DecodingError::InvalidString),
+    })?;
+    Ok(out)
+}
+
+/// Expands the string references of a message written by `intern`, so that it
+/// can be decoded by decoders that don't know about string tables. Messages
+/// without a table are returned unchanged.
+pub fn resolve(buf: &[u8]) -> Result<Vec<u8>, DecodingError> {
+    let mut cursor = Cursor::new(buf);
+    let table = cursor.decode_string_table()?;
+    let rest = &buf[cursor.position() as usize..];
+    if table.is_empty() {
+        return Ok(rest.to_vec());
+    }
+
+    // References are measured before they are expanded, so a long string
+    // referenced many times fails before anything is allocated for it.
+    let mut len = 0;
+    walk(rest, &mut Vec::new(), MAX_DEPTH, &mut |val, out| {
+        len += match val {
+            Found::Str(val) => val.len(),
+            Found::Ref(id) => table.get(id).ok_or(DecodingError::InvalidString)?.len(),
+        };
+        if out.len() + len > MAX_RESOLVED_LEN {
+            return Err(DecodingError::InvalidString);
+        }
+        Ok(())
+    })?;
+
+    let mut out = Vec::with_capacity(buf.len());
+    walk(rest, &mut out, MAX_DEPTH, &mut |val, out| {
+        match val {
+            Found::Str(val) => write_str(out, val),
+            Found::Ref(id) => write_str(out, table.get(id).ok_or(DecodingError::InvalidString)?),
+        }
+        Ok(())
+    })?;
+    Ok(out)
+}
+
+fn write_u32(out: &mut Vec<u8>, val: u32) {
+    out.push(Kind::U32 as u8);
+    write_varint(out, val as u64);
+}
+
+fn write_str(out: &mut Vec<u8>, val: &str) {
+    out.push(Kind::String as u8);
+    write_u32(out, val.len() as u32);
+    out.extend_from_slice(val.as_bytes());
+}
+
+fn write_ref(out: &mut Vec<u8>, id: u32) {
+    out.push(Kind::StringRef as u8);
+    write_u32(out, id);
+}
+
+/// Copies the values of `buf` to `out`, handing every string and string
+/// reference to `found` to write instead. Delimited values are walked into and
+/// written with their new length.
+fn walk<'a>(
+    buf: &'a [u8],
+    out: &mut Vec<u8>,
+    depth: usize,
+    found: &mut OnFound<'a, '_>,
+) -> Result<(), DecodingError> {
+    if depth == 0 {
+        return Err(DecodingError::MaxDepthExceeded);
+    }
+    let mut cursor = Cursor::new(buf);
+    while (cursor.position() as usize) < buf.len() {
+        let start = cursor.position() as usize;
+        match Kind::from(buf[start]) {
+            Kind::String => {
+                let len = cursor.decode_str()?.len();
+                let end = cursor.position() as usize;
+                let val = str::from_utf8(&buf[end - len..end])
+                    .ok()
+                    .ok_or(DecodingError::InvalidString)?;
+                found(Found::Str(val), out)?;
+            }
+            Kind::StringRef => {
+                cursor.set_position(start as u64 + 1);
+                found(Found::Ref(cursor.decode_u32()?), out)?;
+            }
+            Kind::Any => {
+                let end = cursor.decode_delimited()? as usize;
+                let mut inner = Vec::new();
+                walk(
+                    &buf[cursor.position() as usize..end],
+                    &mut inner,
+                    depth - 1,
+                    found,
+                )?;
+                out.push(Kind::Any as u8);
+                write_u32(out, inner.len() as u32);
+                out.extend_from_slice(&inner);
+                cursor.set_position(end as u64);
+            }
+            // The values of arrays and maps follow their headers with their own
+            // kinds, so only the headers are copied here.
+            Kind::Array => {
+                cursor.set_position(start as u64 + 2);
+                cursor.decode_u32()?;
+                out.extend_from_slice(&buf[start..cursor.position() as usize]);
+            }
+            Kind::Map => {
+                cursor.set_position(start as u64 + 1);
+                cursor
+                    .read_u16::<BigEndian>()
+                    .ok()
+                    .ok_or(DecodingError::InvalidMap)?;
+                cursor.decode_u32()?;
+                out.extend_from_slice(&buf[start..cursor.position() as usize]);
+            }
+            Kind::StringTable => return Err(DecodingError::InvalidString),
+            _ => {
+                cursor.skip_value()?;
+                out.extend_from_slice(&buf[start..cursor.position() as usize]);
+            }
+        }
+    }
+    Ok(())
+}
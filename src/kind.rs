@@ -14,6 +14,7 @@
     limitations under the License.
 */
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Kind {
     None = 0x00,
     Array = 0x01,
@@ -31,6 +32,19 @@ pub enum Kind {
     I64 = 0x0d,
     F32 = 0x0e,
     F64 = 0x0f,
+    /// An array of fixed width or varint values written without their own kinds,
+    /// or of bools written as one bit each.
+    PackedArray = 0x10,
+    /// An array of 64-bit integers written as the differences between them.
+    DeltaArray = 0x11,
+    /// An array of fixed width or varint values written as runs of the same value.
+    RunLengthArray = 0x12,
+    /// The strings an interned message refers to, written in front of it.
+    StringTable = 0x13,
+    /// A string written as its id in the string table of the message.
+    StringRef = 0x14,
+    /// A compressed payload, with the algorithm and lengths in front of it.
+    Compressed = 0x15,
 
     Unknown,
 }
@@ -54,6 +68,12 @@ impl From<u8> for Kind {
             0x0d => Kind::I64,
             0x0e => Kind::F32,
             0x0f => Kind::F64,
+            0x10 => Kind::PackedArray,
+            0x11 => Kind::DeltaArray,
+            0x12 => Kind::RunLengthArray,
+            0x13 => Kind::StringTable,
+            0x14 => Kind::StringRef,
+            0x15 => Kind::Compressed,
 
             _ => Kind::Unknown,
         }
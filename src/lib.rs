@@ -16,12 +16,70 @@
 
 extern crate core;
 
+mod bits;
+mod compat;
+#[cfg(feature = "lz4")]
+mod compression;
 mod decoder;
+mod dynamic;
 mod encoder;
+mod envelope;
+mod intern;
 mod kind;
+mod message;
+mod packed;
+mod rpc;
+mod schema;
 
+pub use bits::Bits;
+pub use bits::BitsIter;
+pub use compat::check_compatibility;
+pub use compat::BreakingChange;
+pub use compat::Change;
+pub use compat::Readers;
+#[cfg(feature = "lz4")]
+pub use compression::compress;
+#[cfg(feature = "lz4")]
+pub use compression::decompress;
+#[cfg(feature = "lz4")]
+pub use compression::read_compressed;
+#[cfg(feature = "lz4")]
+pub use compression::write_compressed;
+#[cfg(feature = "lz4")]
+pub use compression::Compression;
+#[cfg(feature = "lz4")]
+pub use compression::CompressionError;
+#[cfg(feature = "lz4")]
+pub use compression::MAX_DECOMPRESSED_LEN;
 pub use decoder::Decoder;
+pub use decoder::DecoderBuffer;
 pub use decoder::DecodingError;
+pub use decoder::MAX_DEPTH;
+pub use decoder::MAX_RUN_LENGTH;
+pub use dynamic::DynamicError;
+pub use dynamic::DynamicMessage;
+pub use dynamic::Value;
 pub use encoder::Encoder;
 pub use encoder::EncodingError;
+pub use envelope::Envelope;
+pub use envelope::EnvelopeError;
+pub use envelope::MessageType;
+pub use envelope::Named;
+pub use envelope::TypeRegistry;
+pub use intern::intern;
+pub use intern::resolve;
+pub use intern::StringTable;
+pub use intern::MAX_RESOLVED_LEN;
 pub use kind::Kind;
+pub use message::Decode;
+pub use message::Encode;
+pub use rpc::Handler;
+pub use rpc::RpcError;
+pub use rpc::Transport;
+pub use schema::validate;
+pub use schema::EnumSchema;
+pub use schema::Field;
+pub use schema::FieldType;
+pub use schema::MessageSchema;
+pub use schema::Schema;
+pub use schema::ValidationError;
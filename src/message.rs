@@ -0,0 +1,164 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+#[cfg(feature = "lz4")]
+use crate::compression::{compress, decompress, Compression, MAX_DECOMPRESSED_LEN};
+use crate::decoder::{DecoderBuffer, DecodingError, MAX_DEPTH};
+use crate::intern::{intern, resolve};
+use crate::kind::Kind;
+use std::error::Error;
+use std::io::{Cursor, Read};
+use std::mem;
+
+/// Implemented by generated messages. The traits live here rather than in each
+/// generated file so that messages from different files can embed each other.
+pub trait Encode {
+    fn encode<'a>(
+        &'a self,
+        b: &'a mut Cursor<Vec<u8>>,
+    ) -> Result<&'a mut Cursor<Vec<u8>>, Box<dyn std::error::Error>>;
+
+    /// Encodes the message to the end of `buf`. On error `buf` is left as it
+    /// was, without the part of the message written before the failure.
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+        let len = buf.len();
+        let mut cursor = Cursor::new(mem::take(buf));
+        cursor.set_position(len as u64);
+        let result = self.encode(&mut cursor).map(|_| ());
+        *buf = cursor.into_inner();
+        if result.is_err() {
+            buf.truncate(len);
+        }
+        result
+    }
+
+    fn encode_to_vec(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Encodes the message with the strings it holds more than once written
+    /// once, see `intern`. `Decode::decode_from_slice` reads it like any other
+    /// message.
+    fn encode_interned_to_vec(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(intern(&self.encode_to_vec()?)?)
+    }
+
+    /// Encodes the message and compresses it, see `compress`.
+    /// `Decode::decode_from_slice` reads it like any other message as long as
+    /// it decompresses to at most `MAX_DECOMPRESSED_LEN` bytes.
+    #[cfg(feature = "lz4")]
+    fn encode_compressed_to_vec(&self, algorithm: Compression) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(compress(&self.encode_to_vec()?, algorithm))
+    }
+}
+
+pub trait Decode {
+    fn decode(
+        b: &mut Cursor<impl DecoderBuffer>,
+    ) -> Result<Option<Self>, Box<dyn std::error::Error>>
+    where
+        Self: Sized,
+    {
+        Self::decode_with_depth(b, MAX_DEPTH)
+    }
+
+    fn decode_with_depth(
+        b: &mut Cursor<impl DecoderBuffer>,
+        depth: usize,
+    ) -> Result<Option<Self>, Box<dyn std::error::Error>>
+    where
+        Self: Sized;
+    /// Decodes a message into `self`, reusing the strings, vectors and maps it
+    /// already holds. `self` is left partly updated when decoding fails.
+    fn decode_into(&mut self, b: &mut Cursor<impl DecoderBuffer>) -> Result<(), Box<dyn Error>>
+    where
+        Self: Sized,
+    {
+        self.decode_into_with_depth(b, MAX_DEPTH)
+    }
+
+    /// Generated messages reuse their fields, anything else is replaced with a
+    /// newly decoded value.
+    fn decode_into_with_depth(
+        &mut self,
+        b: &mut Cursor<impl DecoderBuffer>,
+        depth: usize,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        Self: Sized,
+    {
+        *self = Self::decode_with_depth(b, depth)?.ok_or(DecodingError::InvalidNone)?;
+        Ok(())
+    }
+
+    /// Decodes a message that takes up all of `buf`.
+    fn decode_from_slice(buf: &[u8]) -> Result<Self, Box<dyn Error>>
+    where
+        Self: Sized,
+    {
+        decode_all(buf)
+    }
+
+    /// Decodes a message that takes up all of `buf` after decompressing it,
+    /// failing when it would grow past `max_len` bytes.
+    #[cfg(feature = "lz4")]
+    fn decode_compressed_from_slice(buf: &[u8], max_len: usize) -> Result<Self, Box<dyn Error>>
+    where
+        Self: Sized,
+    {
+        decode_all(&decompress(buf, max_len)?)
+    }
+
+    /// Reads `reader` to the end and decodes a message that takes up all of it.
+    fn decode_from_reader(mut reader: impl Read) -> Result<Self, Box<dyn Error>>
+    where
+        Self: Sized,
+    {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        decode_all(&buf)
+    }
+}
+
+/// Decodes a message from `buf`, failing with `TrailingBytes` when it doesn't
+/// take up the whole buffer and with `InvalidNone` when it is a nil message.
+/// Compressed messages are decompressed and interned messages have their
+/// strings resolved first.
+fn decode_all<T: Decode>(buf: &[u8]) -> Result<T, Box<dyn Error>> {
+    #[cfg(feature = "lz4")]
+    if buf.first() == Some(&(Kind::Compressed as u8)) {
+        return decode_resolved(&decompress(buf, MAX_DECOMPRESSED_LEN)?);
+    }
+    decode_resolved(buf)
+}
+
+fn decode_resolved<T: Decode>(buf: &[u8]) -> Result<T, Box<dyn Error>> {
+    if buf.first() == Some(&(Kind::StringTable as u8)) {
+        return decode_exact(&resolve(buf)?);
+    }
+    decode_exact(buf)
+}
+
+fn decode_exact<T: Decode>(buf: &[u8]) -> Result<T, Box<dyn Error>> {
+    let mut cursor = Cursor::new(buf);
+    let message = T::decode(&mut cursor)?.ok_or(DecodingError::InvalidNone)?;
+    if cursor.position() != buf.len() as u64 {
+        return Err(DecodingError::TrailingBytes.into());
+    }
+    Ok(message)
+}
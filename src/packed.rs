@@ -0,0 +1,437 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::decoder::{read_slice, Decoder, DecoderBuffer, DecodingError, MAX_RUN_LENGTH};
+use crate::kind::Kind;
+use byteorder::ReadBytesExt;
+use std::io::Cursor;
+
+const CONTINUATION: u8 = 0x80;
+
+/// The kinds that arrays can be packed with: after the `Kind::PackedArray` tag,
+/// the element kind and the number of elements, the elements are written back
+/// to back without their own tags. Floats are written big-endian like
+/// everywhere else, so a packed array of them is copied as is on big-endian
+/// targets, and integers as varints.
+///
+/// The same elements make up the runs of a `Kind::RunLengthArray`, which are
+/// each written as the number of times an element repeats followed by the
+/// element. The elements of a `Kind::DeltaArray` are the differences between
+/// consecutive 64-bit integers, wrapping around on overflow, written as zigzag
+/// varints.
+pub(crate) trait Packed: Sized {
+    const KIND: Kind;
+    /// The width of each element when the kind has a fixed width.
+    const WIDTH: Option<usize>;
+
+    fn write(&self, buf: &mut Vec<u8>);
+    /// Reads an element of a packed array. Fixed width elements are given the
+    /// exact number of bytes.
+    fn read<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Option<Self>;
+    /// Decodes an element of an array that isn't packed.
+    fn decode<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Result<Self, DecodingError>;
+    /// Whether two elements are written the same, which tells apart the zeros
+    /// and NaNs of floats.
+    fn same(&self, other: &Self) -> bool;
+
+    /// Writes the elements of a packed array back to back.
+    fn write_all(vals: &[Self], buf: &mut Vec<u8>) {
+        for val in vals {
+            val.write(buf);
+        }
+    }
+
+    /// Reads the fixed width elements of a packed array out of `bytes`, which
+    /// holds a whole number of them.
+    fn read_all(bytes: &[u8], buf: &mut Vec<Self>) -> Option<()> {
+        let mut items = Cursor::new(bytes);
+        while (items.position() as usize) < bytes.len() {
+            buf.push(Self::read(&mut items)?);
+        }
+        Some(())
+    }
+
+    /// The bits delta encoding works on, for the kinds that can be delta encoded.
+    fn to_delta_bits(&self) -> Option<i64> {
+        None
+    }
+
+    fn from_delta_bits(_bits: i64) -> Option<Self> {
+        None
+    }
+}
+
+pub(crate) fn write_varint(buf: &mut Vec<u8>, mut val: u64) {
+    while val >= CONTINUATION as u64 {
+        buf.push(val as u8 | CONTINUATION);
+        val >>= 7;
+    }
+    buf.push(val as u8);
+}
+
+fn read_varint<T: DecoderBuffer>(cursor: &mut Cursor<T>, max_len: u32) -> Option<u64> {
+    let mut x: u64 = 0;
+    for i in 0..max_len {
+        let byte = cursor.read_u8().ok()?;
+        if byte < CONTINUATION {
+            return Some(x | (byte as u64) << (7 * i));
+        }
+        x |= (byte as u64 & ((CONTINUATION as u64) - 1)) << (7 * i);
+    }
+    None
+}
+
+fn read_fixed<T: DecoderBuffer, const N: usize>(cursor: &mut Cursor<T>) -> Option<[u8; N]> {
+    read_slice(cursor, N)?.try_into().ok()
+}
+
+impl Packed for u32 {
+    const KIND: Kind = Kind::U32;
+    const WIDTH: Option<usize> = None;
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, *self as u64);
+    }
+
+    fn read<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Option<Self> {
+        read_varint(cursor, 5).map(|x| x as u32)
+    }
+
+    fn decode<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Result<Self, DecodingError> {
+        cursor.decode_u32()
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl Packed for u64 {
+    const KIND: Kind = Kind::U64;
+    const WIDTH: Option<usize> = None;
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        write_varint(buf, *self);
+    }
+
+    fn read<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Option<Self> {
+        read_varint(cursor, 10)
+    }
+
+    fn decode<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Result<Self, DecodingError> {
+        cursor.decode_u64()
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    fn to_delta_bits(&self) -> Option<i64> {
+        Some(*self as i64)
+    }
+
+    fn from_delta_bits(bits: i64) -> Option<Self> {
+        Some(bits as u64)
+    }
+}
+
+impl Packed for i32 {
+    const KIND: Kind = Kind::I32;
+    const WIDTH: Option<usize> = None;
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        // Shift the value to the left by 1 bit, then flip the bits if the value is negative.
+        let mut cast_val = (*self as u32) << 1;
+        if *self < 0 {
+            cast_val = !cast_val;
+        }
+        write_varint(buf, cast_val as u64);
+    }
+
+    fn read<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Option<Self> {
+        let ux = read_varint(cursor, 5)? as u32;
+        let x = (ux >> 1) as i32;
+        Some(if ux & 1 != 0 { !x } else { x })
+    }
+
+    fn decode<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Result<Self, DecodingError> {
+        cursor.decode_i32()
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl Packed for i64 {
+    const KIND: Kind = Kind::I64;
+    const WIDTH: Option<usize> = None;
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        // Shift the value to the left by 1 bit, then flip the bits if the value is negative.
+        let mut cast_val = (*self as u64) << 1;
+        if *self < 0 {
+            cast_val = !cast_val;
+        }
+        write_varint(buf, cast_val);
+    }
+
+    fn read<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Option<Self> {
+        let ux = read_varint(cursor, 10)?;
+        let x = (ux >> 1) as i64;
+        Some(if ux & 1 != 0 { !x } else { x })
+    }
+
+    fn decode<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Result<Self, DecodingError> {
+        cursor.decode_i64()
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    fn to_delta_bits(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    fn from_delta_bits(bits: i64) -> Option<Self> {
+        Some(bits)
+    }
+}
+
+impl Packed for f32 {
+    const KIND: Kind = Kind::F32;
+    const WIDTH: Option<usize> = Some(4);
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn read<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Option<Self> {
+        read_fixed(cursor).map(f32::from_be_bytes)
+    }
+
+    fn decode<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Result<Self, DecodingError> {
+        cursor.decode_f32()
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self.to_bits() == other.to_bits()
+    }
+
+    #[cfg(target_endian = "big")]
+    fn write_all(vals: &[Self], buf: &mut Vec<u8>) {
+        buf.extend_from_slice(bytemuck::cast_slice(vals));
+    }
+
+    #[cfg(target_endian = "big")]
+    fn read_all(bytes: &[u8], buf: &mut Vec<Self>) -> Option<()> {
+        let start = buf.len();
+        buf.resize(start + bytes.len() / 4, 0.0);
+        bytemuck::cast_slice_mut(&mut buf[start..]).copy_from_slice(bytes);
+        Some(())
+    }
+}
+
+impl Packed for f64 {
+    const KIND: Kind = Kind::F64;
+    const WIDTH: Option<usize> = Some(8);
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn read<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Option<Self> {
+        read_fixed(cursor).map(f64::from_be_bytes)
+    }
+
+    fn decode<T: DecoderBuffer>(cursor: &mut Cursor<T>) -> Result<Self, DecodingError> {
+        cursor.decode_f64()
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self.to_bits() == other.to_bits()
+    }
+
+    #[cfg(target_endian = "big")]
+    fn write_all(vals: &[Self], buf: &mut Vec<u8>) {
+        buf.extend_from_slice(bytemuck::cast_slice(vals));
+    }
+
+    #[cfg(target_endian = "big")]
+    fn read_all(bytes: &[u8], buf: &mut Vec<Self>) -> Option<()> {
+        let start = buf.len();
+        buf.resize(start + bytes.len() / 8, 0.0);
+        bytemuck::cast_slice_mut(&mut buf[start..]).copy_from_slice(bytes);
+        Some(())
+    }
+}
+
+/// Writes the elements of a packed array, without its header.
+pub(crate) fn write_packed<P: Packed>(vals: &[P]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(vals.len() * P::WIDTH.unwrap_or(1));
+    P::write_all(vals, &mut buf);
+    buf
+}
+
+/// Writes the elements of a delta encoded array, without its header. The first
+/// element is the difference from zero.
+pub(crate) fn write_delta<P: Packed>(vals: &[P]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(vals.len());
+    let mut prev: i64 = 0;
+    for val in vals {
+        let bits = val.to_delta_bits().unwrap_or_default();
+        bits.wrapping_sub(prev).write(&mut buf);
+        prev = bits;
+    }
+    buf
+}
+
+/// Writes the runs of a run-length encoded array, without its header.
+pub(crate) fn write_run_length<P: Packed>(vals: &[P]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut rest = vals;
+    while let Some(first) = rest.first() {
+        let run = rest.iter().take_while(|val| val.same(first)).count();
+        write_varint(&mut buf, run as u64);
+        first.write(&mut buf);
+        rest = &rest[run..];
+    }
+    buf
+}
+
+/// Decodes an array of `P` into `buf`, whatever way it was encoded in.
+pub(crate) fn decode_packed<T: DecoderBuffer, P: Packed + Clone>(
+    cursor: &mut Cursor<T>,
+    buf: &mut Vec<P>,
+) -> Result<(), DecodingError> {
+    let start = cursor.position();
+    let kind = cursor.read_u8().ok().ok_or(DecodingError::InvalidArray)?;
+    let val_kind = cursor.read_u8().ok().ok_or(DecodingError::InvalidArray)?;
+    let kind = Kind::from(kind);
+    let supported = match kind {
+        Kind::Array | Kind::PackedArray | Kind::RunLengthArray => true,
+        Kind::DeltaArray => P::from_delta_bits(0).is_some(),
+        _ => false,
+    };
+    if val_kind != P::KIND as u8 || !supported {
+        cursor.set_position(start);
+        return Err(DecodingError::InvalidArray);
+    }
+    let size = cursor.decode_u32()? as usize;
+
+    // Every element takes up at least a byte, which keeps a corrupt size from
+    // allocating more than the buffer could hold. Runs can hold more elements
+    // than that, so their arrays grow as they are read up to `MAX_RUN_LENGTH`.
+    let len = cursor.get_ref().as_ref().len() as u64;
+    let remaining = len.saturating_sub(cursor.position());
+    let too_long = match kind {
+        Kind::RunLengthArray => size > MAX_RUN_LENGTH,
+        _ => size as u64 > remaining,
+    };
+    if too_long {
+        return Err(DecodingError::InvalidArray);
+    }
+    buf.clear();
+    buf.reserve(size.min(remaining as usize));
+    match kind {
+        Kind::Array => {
+            for _ in 0..size {
+                buf.push(P::decode(cursor)?);
+            }
+        }
+        Kind::DeltaArray => {
+            let mut prev: i64 = 0;
+            for _ in 0..size {
+                let delta = i64::read(cursor).ok_or(DecodingError::InvalidArray)?;
+                prev = prev.wrapping_add(delta);
+                buf.push(P::from_delta_bits(prev).ok_or(DecodingError::InvalidArray)?);
+            }
+        }
+        Kind::RunLengthArray => {
+            while buf.len() < size {
+                let run = read_varint(cursor, 5).ok_or(DecodingError::InvalidArray)? as usize;
+                if run == 0 || run > size - buf.len() {
+                    return Err(DecodingError::InvalidArray);
+                }
+                let val = P::read(cursor).ok_or(DecodingError::InvalidArray)?;
+                buf.resize(buf.len() + run, val);
+            }
+        }
+        _ => {
+            if let Some(width) = P::WIDTH {
+                let bytes = size.checked_mul(width).ok_or(DecodingError::InvalidArray)?;
+                let bytes = read_slice(cursor, bytes).ok_or(DecodingError::InvalidArray)?;
+                P::read_all(bytes, buf).ok_or(DecodingError::InvalidArray)?;
+            } else {
+                for _ in 0..size {
+                    buf.push(P::read(cursor).ok_or(DecodingError::InvalidArray)?);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Moves past a packed element of `kind`.
+fn skip_element<T: DecoderBuffer>(cursor: &mut Cursor<T>, kind: Kind) -> Result<(), DecodingError> {
+    match kind {
+        Kind::F32 => read_slice(cursor, 4).map(|_| ()),
+        Kind::F64 => read_slice(cursor, 8).map(|_| ()),
+        Kind::U32 | Kind::U64 | Kind::I32 | Kind::I64 => read_varint(cursor, 10).map(|_| ()),
+        _ => None,
+    }
+    .ok_or(DecodingError::InvalidArray)
+}
+
+/// Moves past the elements of an array written with `Kind::PackedArray`,
+/// `Kind::DeltaArray` or `Kind::RunLengthArray`, whose elements are of
+/// `val_kind`.
+pub(crate) fn skip_packed<T: DecoderBuffer>(
+    cursor: &mut Cursor<T>,
+    kind: Kind,
+    val_kind: Kind,
+    size: usize,
+) -> Result<(), DecodingError> {
+    match (kind, val_kind) {
+        (Kind::PackedArray, Kind::F32 | Kind::F64) => {
+            let width = if val_kind == Kind::F32 { 4 } else { 8 };
+            let bytes = size.checked_mul(width).ok_or(DecodingError::InvalidArray)?;
+            read_slice(cursor, bytes).ok_or(DecodingError::InvalidArray)?;
+        }
+        (Kind::PackedArray, Kind::Bool) => {
+            read_slice(cursor, size.div_ceil(8)).ok_or(DecodingError::InvalidArray)?;
+        }
+        (Kind::PackedArray, _) | (Kind::DeltaArray, Kind::I64 | Kind::U64) => {
+            for _ in 0..size {
+                skip_element(cursor, val_kind)?;
+            }
+        }
+        (Kind::RunLengthArray, _) => {
+            let mut count = 0;
+            while count < size {
+                let run = read_varint(cursor, 5).ok_or(DecodingError::InvalidArray)? as usize;
+                if run == 0 || run > size - count {
+                    return Err(DecodingError::InvalidArray);
+                }
+                skip_element(cursor, val_kind)?;
+                count += run;
+            }
+        }
+        _ => return Err(DecodingError::InvalidArray),
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+
+#[derive(Debug, PartialEq)]
+pub enum RpcError {
+    UnknownMethod(u32),
+    Encoding(String),
+    Decoding(String),
+    Transport(String),
+    Remote(String),
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for RpcError {}
+
+/// Carries encoded requests from a generated client to a server and returns the
+/// encoded response. `method` is the ID of the method within its service, which
+/// is its index among the methods of the service in declaration order. Streaming
+/// methods aren't generated but keep their IDs.
+pub trait Transport: Send + Sync {
+    fn call(
+        &self,
+        method: u32,
+        request: Vec<u8>,
+    ) -> impl Future<Output = Result<Vec<u8>, RpcError>> + Send;
+}
+
+/// Decodes requests, calls the matching method of a service and encodes its
+/// response. Implemented by the generated `<Service>Server` types.
+pub trait Handler: Send + Sync {
+    fn handle(
+        &self,
+        method: u32,
+        request: Vec<u8>,
+    ) -> impl Future<Output = Result<Vec<u8>, RpcError>> + Send;
+}
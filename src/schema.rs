@@ -0,0 +1,452 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use crate::decoder::{Decoder, DecoderBuffer, DecodingError, MAX_DEPTH};
+use crate::intern::resolve;
+use crate::kind::Kind;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Cursor;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I32,
+    I64,
+    F32,
+    F64,
+    String,
+    Bytes,
+    Enum(String),
+    Message(String),
+    Array(Box<FieldType>),
+    Map(Box<FieldType>, Box<FieldType>),
+    /// A value that is written as `None` when absent.
+    Optional(Box<FieldType>),
+    /// The field number of the member that is set, or `0`, followed by the value
+    /// of that member. Members are listed with their field numbers.
+    Oneof(Vec<(u32, FieldType)>),
+}
+
+impl FieldType {
+    pub fn array(val: FieldType) -> Self {
+        FieldType::Array(Box::new(val))
+    }
+
+    pub fn map(key: FieldType, val: FieldType) -> Self {
+        FieldType::Map(Box::new(key), Box::new(val))
+    }
+
+    pub fn optional(val: FieldType) -> Self {
+        FieldType::Optional(Box::new(val))
+    }
+
+    /// The kind written in front of a value of this type, or in the header of an
+    /// array or map containing it. Nested messages are written as `Kind::Any`.
+    pub fn kind(&self) -> Kind {
+        match self {
+            FieldType::Bool => Kind::Bool,
+            FieldType::U8 => Kind::U8,
+            FieldType::U16 => Kind::U16,
+            FieldType::U32 => Kind::U32,
+            FieldType::U64 => Kind::U64,
+            FieldType::I32 => Kind::I32,
+            FieldType::I64 => Kind::I64,
+            FieldType::F32 => Kind::F32,
+            FieldType::F64 => Kind::F64,
+            FieldType::String => Kind::String,
+            FieldType::Bytes => Kind::Bytes,
+            FieldType::Enum(_) => Kind::U32,
+            FieldType::Message(_) => Kind::Any,
+            FieldType::Array(_) => Kind::Array,
+            FieldType::Map(_, _) => Kind::Map,
+            FieldType::Optional(val) => val.kind(),
+            FieldType::Oneof(_) => Kind::U32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    /// The field number, or for a oneof the lowest number of its members.
+    pub number: u32,
+    pub ty: FieldType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageSchema {
+    pub name: String,
+    pub fields: Vec<Field>,
+    /// Whether the message is written with a length in front of it, so that
+    /// fields can be added to the end of it without breaking older decoders.
+    pub delimited: bool,
+}
+
+impl MessageSchema {
+    pub fn new(name: &str) -> Self {
+        MessageSchema {
+            name: name.to_owned(),
+            fields: Vec::new(),
+            delimited: false,
+        }
+    }
+
+    pub fn delimited(mut self) -> Self {
+        self.delimited = true;
+        self
+    }
+
+    /// Adds a field numbered one past the highest number so far.
+    pub fn with_field(self, name: &str, ty: FieldType) -> Self {
+        let number = self.fields.iter().map(|f| f.number).max().unwrap_or(0) + 1;
+        self.with_numbered_field(name, number, ty)
+    }
+
+    pub fn with_numbered_field(mut self, name: &str, number: u32, ty: FieldType) -> Self {
+        self.fields.push(Field {
+            name: name.to_owned(),
+            number,
+            ty,
+        });
+        self
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Returns the fields in the order the generated `Encode` implementations write them:
+    /// singular scalars, enums, strings and bytes first, then arrays, then maps and
+    /// nested messages, then oneofs. Fields keep their declaration order within each
+    /// group.
+    ///
+    /// Delimited messages are written in the order of their field numbers instead.
+    pub fn wire_fields(&self) -> Vec<&Field> {
+        let mut fields: Vec<&Field> = self.fields.iter().collect();
+        if self.delimited {
+            fields.sort_by_key(|f| f.number);
+            return fields;
+        }
+        fields.sort_by_key(|f| match f.ty {
+            FieldType::Array(_) => 1,
+            FieldType::Map(_, _) | FieldType::Message(_) => 2,
+            FieldType::Oneof(_) => 3,
+            _ => 0,
+        });
+        fields
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumSchema {
+    pub name: String,
+    pub values: Vec<(String, u32)>,
+}
+
+impl EnumSchema {
+    pub fn new(name: &str) -> Self {
+        EnumSchema {
+            name: name.to_owned(),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn with_value(mut self, name: &str, number: u32) -> Self {
+        self.values.push((name.to_owned(), number));
+        self
+    }
+}
+
+/// A runtime description of a set of polyglot messages, rooted at the message
+/// that is expected at the top level of a buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    pub root: String,
+    pub messages: Vec<MessageSchema>,
+    pub enums: Vec<EnumSchema>,
+}
+
+impl Schema {
+    pub fn new(root: &str) -> Self {
+        Schema {
+            root: root.to_owned(),
+            messages: Vec::new(),
+            enums: Vec::new(),
+        }
+    }
+
+    pub fn with_message(mut self, message: MessageSchema) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    pub fn with_enum(mut self, e: EnumSchema) -> Self {
+        self.enums.push(e);
+        self
+    }
+
+    pub fn get_message(&self, name: &str) -> Option<&MessageSchema> {
+        self.messages.iter().find(|m| m.name == name)
+    }
+
+    pub fn get_enum(&self, name: &str) -> Option<&EnumSchema> {
+        self.enums.iter().find(|e| e.name == name)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ValidationError {
+    UnknownMessage(String),
+    MismatchedKind {
+        path: String,
+        expected: Kind,
+        found: Kind,
+    },
+    InvalidValue {
+        path: String,
+        error: DecodingError,
+    },
+    UnexpectedEnd {
+        path: String,
+    },
+    TrailingBytes(usize),
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Walks `buf` using the `Decoder` and checks that every value matches the kind
+/// declared for it in `schema`, starting at the schema's root message.
+///
+/// The first disagreement is returned along with the path of the offending field,
+/// for example `searchResponse.results[2].url`. Interned messages are checked
+/// once their strings are resolved.
+pub fn validate(buf: &[u8], schema: &Schema) -> Result<(), ValidationError> {
+    if buf.first() == Some(&(Kind::StringTable as u8)) {
+        let buf = resolve(buf).map_err(|error| invalid("", error))?;
+        return validate(&buf, schema);
+    }
+    let mut validator = Validator {
+        schema,
+        decoder: Cursor::new(buf),
+        depth: MAX_DEPTH,
+    };
+    validator.message(&schema.root, "")?;
+
+    let remaining = buf.len() - validator.decoder.position() as usize;
+    if remaining > 0 {
+        return Err(ValidationError::TrailingBytes(remaining));
+    }
+    Ok(())
+}
+
+struct Validator<'a> {
+    schema: &'a Schema,
+    decoder: Cursor<&'a [u8]>,
+    depth: usize,
+}
+
+impl<'a> Validator<'a> {
+    fn peek(&self, offset: usize, path: &str) -> Result<Kind, ValidationError> {
+        let position = self.decoder.position() as usize + offset;
+        match self.decoder.get_ref().get(position) {
+            Some(kind) => Ok(Kind::from(*kind)),
+            None => Err(ValidationError::UnexpectedEnd {
+                path: path.to_owned(),
+            }),
+        }
+    }
+
+    fn expect(&self, offset: usize, expected: Kind, path: &str) -> Result<(), ValidationError> {
+        let found = self.peek(offset, path)?;
+        if found != expected {
+            return Err(ValidationError::MismatchedKind {
+                path: path.to_owned(),
+                expected,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    fn message(&mut self, name: &str, path: &str) -> Result<(), ValidationError> {
+        let message = self
+            .schema
+            .get_message(name)
+            .ok_or_else(|| ValidationError::UnknownMessage(name.to_owned()))?;
+
+        match self.peek(0, path) {
+            Ok(Kind::None) => {
+                self.decoder.decode_none();
+                return Ok(());
+            }
+            Ok(Kind::Error) => {
+                return self
+                    .decoder
+                    .decode_error()
+                    .map(|_| ())
+                    .map_err(|error| invalid(path, error));
+            }
+            _ => {}
+        }
+
+        if self.depth == 0 {
+            return Err(invalid(path, DecodingError::MaxDepthExceeded));
+        }
+        self.depth -= 1;
+        let end = if message.delimited {
+            self.expect(0, Kind::Any, path)?;
+            let end = self
+                .decoder
+                .decode_delimited()
+                .map_err(|error| invalid(path, error))?;
+            Some(end)
+        } else {
+            None
+        };
+        for field in message.wire_fields() {
+            // Fields missing from the end of a delimited message are left at
+            // their defaults.
+            if end.is_some_and(|end| self.decoder.position() >= end) {
+                break;
+            }
+            let field_path = if path.is_empty() {
+                field.name.clone()
+            } else {
+                format!("{path}.{}", field.name)
+            };
+            self.value(&field.ty, &field_path)?;
+        }
+        if let Some(end) = end {
+            skip_to(&mut self.decoder, end).map_err(|error| invalid(path, error))?;
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn value(&mut self, ty: &FieldType, path: &str) -> Result<(), ValidationError> {
+        match ty {
+            FieldType::Message(name) => self.message(name, path),
+            FieldType::Array(val) => {
+                if let Kind::PackedArray | Kind::DeltaArray | Kind::RunLengthArray =
+                    self.peek(0, path)?
+                {
+                    self.expect(1, val.kind(), path)?;
+                    return self
+                        .decoder
+                        .skip_value()
+                        .map_err(|error| invalid(path, error));
+                }
+                self.expect(0, Kind::Array, path)?;
+                self.expect(1, val.kind(), path)?;
+                let size = self
+                    .decoder
+                    .decode_array(val.kind())
+                    .map_err(|error| invalid(path, error))?;
+                for i in 0..size {
+                    self.value(val, &format!("{path}[{i}]"))?;
+                }
+                Ok(())
+            }
+            FieldType::Optional(val) => {
+                if self.peek(0, path)? == Kind::None {
+                    self.decoder.decode_none();
+                    return Ok(());
+                }
+                self.value(val, path)
+            }
+            FieldType::Oneof(members) => {
+                self.expect(0, Kind::U32, path)?;
+                let number = self
+                    .decoder
+                    .decode_u32()
+                    .map_err(|error| invalid(path, error))?;
+                if number == 0 {
+                    return Ok(());
+                }
+                match members.iter().find(|(n, _)| *n == number) {
+                    Some((_, ty)) => self.value(ty, &format!("{path}#{number}")),
+                    None => Err(invalid(path, DecodingError::InvalidOneof)),
+                }
+            }
+            FieldType::Map(key, val) => {
+                self.expect(0, Kind::Map, path)?;
+                self.expect(1, key.kind(), path)?;
+                self.expect(2, val.kind(), path)?;
+                let size = self
+                    .decoder
+                    .decode_map(key.kind(), val.kind())
+                    .map_err(|error| invalid(path, error))?;
+                for i in 0..size {
+                    self.value(key, &format!("{path}[{i}].key"))?;
+                    self.value(val, &format!("{path}[{i}].value"))?;
+                }
+                Ok(())
+            }
+            _ => {
+                self.expect(0, ty.kind(), path)?;
+                self.scalar(ty).map_err(|error| invalid(path, error))
+            }
+        }
+    }
+
+    fn scalar(&mut self, ty: &FieldType) -> Result<(), DecodingError> {
+        let d = &mut self.decoder;
+        match ty {
+            FieldType::Bool => d.decode_bool().map(|_| ()),
+            FieldType::U8 => d.decode_u8().map(|_| ()),
+            FieldType::U16 => d.decode_u16().map(|_| ()),
+            FieldType::U32 | FieldType::Enum(_) => d.decode_u32().map(|_| ()),
+            FieldType::U64 => d.decode_u64().map(|_| ()),
+            FieldType::I32 => d.decode_i32().map(|_| ()),
+            FieldType::I64 => d.decode_i64().map(|_| ()),
+            FieldType::F32 => d.decode_f32().map(|_| ()),
+            FieldType::F64 => d.decode_f64().map(|_| ()),
+            FieldType::String => d.decode_string().map(|_| ()),
+            FieldType::Bytes => d.decode_bytes().map(|_| ()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Skips the fields a delimited message ending at `end` has past the ones that
+/// are known, failing if the known ones already ran past it.
+pub(crate) fn skip_to<T: DecoderBuffer>(b: &mut Cursor<T>, end: u64) -> Result<(), DecodingError> {
+    while b.position() < end {
+        b.skip_value()?;
+    }
+    if b.position() != end {
+        return Err(DecodingError::InvalidStruct);
+    }
+    Ok(())
+}
+
+fn invalid(path: &str, error: DecodingError) -> ValidationError {
+    ValidationError::InvalidValue {
+        path: path.to_owned(),
+        error,
+    }
+}
@@ -0,0 +1,69 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_rs::Bits;
+
+#[test]
+fn test_bits() {
+    let mut bits = Bits::new();
+    assert!(bits.is_empty());
+    assert_eq!(bits.get(0), None);
+    for i in 0..10 {
+        bits.push(i % 3 == 0);
+    }
+    assert_eq!(bits.len(), 10);
+    assert_eq!(bits.count_ones(), 4);
+    assert_eq!(bits.as_bytes(), [0b0100_1001, 0b10]);
+    assert_eq!(bits.get(3), Some(true));
+    assert_eq!(bits.get(4), Some(false));
+    assert_eq!(bits.get(10), None);
+
+    bits.set(4, true);
+    bits.set(9, false);
+    assert_eq!(bits.as_bytes(), [0b0101_1001, 0]);
+
+    let vals = [true, true, false, true, false, false, false, false, true];
+    let bits = Bits::from(&vals[..]);
+    assert_eq!(bits.iter().collect::<Vec<_>>(), vals);
+    assert_eq!(bits.iter().len(), vals.len());
+    assert_eq!(
+        bits.iter().rev().collect::<Vec<_>>(),
+        vals.iter().rev().copied().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        bits.iter()
+            .enumerate()
+            .filter(|(_, b)| *b)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>(),
+        [0, 1, 3, 8]
+    );
+    assert_eq!(vals.into_iter().collect::<Bits>(), bits);
+
+    let mut extended = Bits::from(&vals[..4]);
+    extended.extend(vals[4..].iter().copied());
+    assert_eq!(extended, bits);
+    extended.clear();
+    assert!(extended.is_empty());
+    assert!(extended.as_bytes().is_empty());
+}
+
+#[test]
+#[should_panic]
+fn test_bits_set_out_of_range() {
+    let mut bits = Bits::from(&[true][..]);
+    bits.set(1, true);
+}
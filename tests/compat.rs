@@ -0,0 +1,307 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+extern crate polyglot_rs;
+
+use polyglot_rs::{
+    check_compatibility, BreakingChange, Change, EnumSchema, FieldType, Kind, MessageSchema,
+    Readers, Schema,
+};
+
+fn user(fields: &[(&str, u32, FieldType)]) -> Schema {
+    let mut message = MessageSchema::new("User");
+    for (name, number, ty) in fields {
+        message = message.with_numbered_field(name, *number, ty.clone());
+    }
+    Schema::new("User").with_message(message)
+}
+
+fn breaking(path: &str, change: Change, breaks: Readers) -> BreakingChange {
+    BreakingChange {
+        path: path.to_owned(),
+        change,
+        breaks,
+    }
+}
+
+#[test]
+fn test_compatible() {
+    let old = user(&[
+        ("name", 1, FieldType::String),
+        ("role", 2, FieldType::Enum("Role".to_string())),
+    ]);
+    let new = user(&[("name", 1, FieldType::String), ("role", 2, FieldType::U32)]);
+    assert_eq!(check_compatibility(&old, &new), []);
+}
+
+#[test]
+fn test_fields() {
+    let old = user(&[
+        ("id", 1, FieldType::U64),
+        ("name", 2, FieldType::String),
+        ("age", 3, FieldType::U32),
+        ("tags", 4, FieldType::array(FieldType::String)),
+    ]);
+    let new = user(&[
+        ("name", 2, FieldType::String),
+        ("id", 1, FieldType::U64),
+        ("tags", 4, FieldType::array(FieldType::Bytes)),
+        ("email", 5, FieldType::optional(FieldType::String)),
+    ]);
+    assert_eq!(
+        check_compatibility(&old, &new),
+        [
+            breaking("User.age", Change::RemovedField, Readers::Both),
+            breaking("User.email", Change::AddedField, Readers::Both),
+            breaking(
+                "User.name",
+                Change::ReorderedField {
+                    old_position: 1,
+                    new_position: 0,
+                },
+                Readers::Both,
+            ),
+            breaking(
+                "User.tags[]",
+                Change::ChangedKind {
+                    old: Kind::String,
+                    new: Kind::Bytes,
+                },
+                Readers::Both,
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_renamed_and_swapped_fields() {
+    let old = user(&[
+        ("name", 1, FieldType::String),
+        ("email", 2, FieldType::String),
+    ]);
+    let new = user(&[
+        ("full_name", 1, FieldType::String),
+        ("email_address", 2, FieldType::String),
+    ]);
+    assert_eq!(check_compatibility(&old, &new), []);
+
+    // Both are strings, so readers don't fail but read each into the other.
+    let new = user(&[
+        ("email", 2, FieldType::String),
+        ("name", 1, FieldType::String),
+    ]);
+    assert_eq!(
+        check_compatibility(&old, &new),
+        [breaking(
+            "User.email",
+            Change::ReorderedField {
+                old_position: 1,
+                new_position: 0,
+            },
+            Readers::Both,
+        )]
+    );
+
+    // A field that kept its number but changed its type is compared by number.
+    let new = user(&[
+        ("name", 1, FieldType::String),
+        ("email", 2, FieldType::Bytes),
+    ]);
+    assert_eq!(
+        check_compatibility(&old, &new),
+        [breaking(
+            "User.email",
+            Change::ChangedKind {
+                old: Kind::String,
+                new: Kind::Bytes,
+            },
+            Readers::Both,
+        )]
+    );
+}
+
+#[test]
+fn test_delimited() {
+    let old = Schema::new("User").with_message(
+        MessageSchema::new("User")
+            .delimited()
+            .with_field("name", FieldType::String)
+            .with_field("age", FieldType::U32),
+    );
+    let new = Schema::new("User").with_message(
+        MessageSchema::new("User")
+            .delimited()
+            .with_numbered_field("name", 1, FieldType::String)
+            .with_numbered_field("email", 3, FieldType::String),
+    );
+    assert_eq!(check_compatibility(&old, &new), []);
+
+    // Delimited messages are written in field number order, so declaring the
+    // fields in another order changes nothing, while a number in between the
+    // old ones doesn't go at the end.
+    let new = Schema::new("User").with_message(
+        MessageSchema::new("User")
+            .delimited()
+            .with_numbered_field("age", 2, FieldType::U32)
+            .with_numbered_field("name", 1, FieldType::String),
+    );
+    assert_eq!(check_compatibility(&old, &new), []);
+
+    let old = Schema::new("User").with_message(
+        MessageSchema::new("User")
+            .delimited()
+            .with_numbered_field("name", 1, FieldType::String)
+            .with_numbered_field("age", 3, FieldType::U32),
+    );
+    let new = Schema::new("User").with_message(
+        MessageSchema::new("User")
+            .delimited()
+            .with_numbered_field("name", 1, FieldType::String)
+            .with_numbered_field("email", 2, FieldType::String)
+            .with_numbered_field("age", 3, FieldType::U32),
+    );
+    assert_eq!(
+        check_compatibility(&old, &new),
+        [breaking("User.email", Change::AddedField, Readers::Both)]
+    );
+
+    let new = user(&[("name", 1, FieldType::String), ("age", 3, FieldType::U32)]);
+    assert_eq!(
+        check_compatibility(&old, &new),
+        [breaking("User", Change::ChangedDelimited, Readers::Both)]
+    );
+}
+
+#[test]
+fn test_maps_and_messages() {
+    let old = Schema::new("User")
+        .with_message(
+            MessageSchema::new("User")
+                .with_field("scores", FieldType::map(FieldType::String, FieldType::I32))
+                .with_field(
+                    "friends",
+                    FieldType::map(FieldType::U32, FieldType::Message("Friend".to_string())),
+                ),
+        )
+        .with_message(MessageSchema::new("Friend").with_field("name", FieldType::String));
+    let new = Schema::new("User")
+        .with_message(
+            MessageSchema::new("User")
+                .with_field("scores", FieldType::map(FieldType::U32, FieldType::I64))
+                .with_field(
+                    "friends",
+                    FieldType::map(FieldType::U32, FieldType::Message("Contact".to_string())),
+                ),
+        )
+        .with_message(MessageSchema::new("Contact").with_field("name", FieldType::Bytes));
+    assert_eq!(
+        check_compatibility(&old, &new),
+        [
+            breaking(
+                "User.scores",
+                Change::ChangedMapKey {
+                    old: Kind::String,
+                    new: Kind::U32,
+                },
+                Readers::Both,
+            ),
+            breaking(
+                "User.scores",
+                Change::ChangedMapValue {
+                    old: Kind::I32,
+                    new: Kind::I64,
+                },
+                Readers::Both,
+            ),
+            breaking(
+                "Contact.name",
+                Change::ChangedKind {
+                    old: Kind::String,
+                    new: Kind::Bytes,
+                },
+                Readers::Both,
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_oneofs_and_optionals() {
+    let old = user(&[
+        ("age", 1, FieldType::optional(FieldType::U32)),
+        (
+            "contact",
+            2,
+            FieldType::Oneof(vec![(1, FieldType::String), (2, FieldType::I64)]),
+        ),
+    ]);
+    let new = user(&[
+        ("age", 1, FieldType::U32),
+        (
+            "contact",
+            3,
+            FieldType::Oneof(vec![(2, FieldType::U64), (3, FieldType::Bytes)]),
+        ),
+    ]);
+    assert_eq!(
+        check_compatibility(&old, &new),
+        [
+            breaking("User.age", Change::ChangedOptional, Readers::Both),
+            breaking("User.contact#1", Change::RemovedOneofMember, Readers::New),
+            breaking(
+                "User.contact#2",
+                Change::ChangedKind {
+                    old: Kind::I64,
+                    new: Kind::U64,
+                },
+                Readers::Both,
+            ),
+            breaking("User.contact#3", Change::AddedOneofMember, Readers::Old),
+        ]
+    );
+}
+
+#[test]
+fn test_enums() {
+    let old = Schema::new("User").with_enum(
+        EnumSchema::new("Role")
+            .with_value("Admin", 0)
+            .with_value("Member", 1)
+            .with_value("Guest", 2),
+    );
+    let new = Schema::new("User").with_enum(
+        EnumSchema::new("Role")
+            .with_value("Administrator", 0)
+            .with_value("Member", 2)
+            .with_value("Owner", 3),
+    );
+    let changes = check_compatibility(&old, &new);
+    assert_eq!(
+        changes,
+        [
+            breaking(
+                "Role.Member",
+                Change::RenumberedEnumValue { old: 1, new: 2 },
+                Readers::Both,
+            ),
+            breaking("Role.Owner", Change::AddedEnumValue, Readers::Old),
+        ]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "Role.Member: RenumberedEnumValue { old: 1, new: 2 } breaks old and new readers"
+    );
+}
@@ -0,0 +1,130 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+#![cfg(feature = "lz4")]
+
+use polyglot_rs::{
+    compress, decompress, read_compressed, write_compressed, Compression, CompressionError,
+    Decoder, Encoder, Kind,
+};
+use std::io::Cursor;
+
+fn encode_prices() -> Vec<u8> {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_array(1000, Kind::String).unwrap();
+    for i in 0..1000 {
+        encoder.encode_str(&format!("price {}", i % 10)).unwrap();
+    }
+    encoder.into_inner()
+}
+
+#[test]
+fn test_compress() {
+    let buf = encode_prices();
+    let compressed = compress(&buf, Compression::Lz4);
+    assert_eq!(
+        compressed[..2],
+        [Kind::Compressed as u8, Compression::Lz4 as u8]
+    );
+    assert!(compressed.len() < buf.len() / 10);
+    assert_eq!(decompress(&compressed, buf.len()).unwrap(), buf);
+
+    let compressed = compress(&[], Compression::Lz4);
+    assert!(decompress(&compressed, 0).unwrap().is_empty());
+
+    // A compressed value is skipped like any other.
+    let mut both = compress(&buf, Compression::Lz4);
+    let mut encoder = Cursor::new(Vec::new());
+    encoder.encode_u32(7).unwrap();
+    both.extend_from_slice(encoder.get_ref());
+    let mut decoder = Cursor::new(both.as_slice());
+    decoder.skip_value().unwrap();
+    assert_eq!(decoder.decode_u32().unwrap(), 7);
+}
+
+#[test]
+fn test_decompress_limits() {
+    let buf = encode_prices();
+    let compressed = compress(&buf, Compression::Lz4);
+    assert_eq!(
+        decompress(&compressed, buf.len() - 1),
+        Err(CompressionError::TooLarge(buf.len()))
+    );
+
+    // A header claiming a huge length is rejected before allocating for it.
+    let mut bomb = vec![Kind::Compressed as u8, Compression::Lz4 as u8];
+    let mut encoder = Cursor::new(Vec::new());
+    encoder.encode_u32(u32::MAX).unwrap().encode_u32(4).unwrap();
+    bomb.extend_from_slice(encoder.get_ref());
+    bomb.extend_from_slice(&[0x1f, 0, 0, 0]);
+    assert_eq!(
+        decompress(&bomb, 1 << 20),
+        Err(CompressionError::TooLarge(u32::MAX as usize))
+    );
+
+    // Payloads that don't decompress to the length in their header.
+    let mut short = compress(&buf[..buf.len() - 1], Compression::Lz4);
+    short[3] = compressed[3];
+    short[4] = compressed[4];
+    assert_eq!(
+        decompress(&short, buf.len()),
+        Err(CompressionError::InvalidPayload)
+    );
+    assert_eq!(
+        decompress(&compressed[..compressed.len() - 1], buf.len()),
+        Err(CompressionError::InvalidPayload)
+    );
+
+    assert_eq!(
+        decompress(&buf, buf.len()),
+        Err(CompressionError::InvalidHeader)
+    );
+    let mut unknown = compressed.clone();
+    unknown[1] = 0x7f;
+    assert_eq!(
+        decompress(&unknown, buf.len()),
+        Err(CompressionError::UnknownAlgorithm(0x7f))
+    );
+}
+
+#[test]
+fn test_compressed_stream() {
+    let buf = encode_prices();
+    let mut stream = Vec::new();
+    write_compressed(&mut stream, &buf, Compression::Lz4).unwrap();
+    write_compressed(&mut stream, &buf[..10], Compression::Lz4).unwrap();
+
+    let mut reader = stream.as_slice();
+    assert_eq!(read_compressed(&mut reader, buf.len()).unwrap(), buf);
+    assert_eq!(read_compressed(&mut reader, buf.len()).unwrap(), buf[..10]);
+    assert!(reader.is_empty());
+    assert_eq!(
+        read_compressed(&mut reader, buf.len()),
+        Err(CompressionError::InvalidHeader)
+    );
+
+    let mut reader = stream.as_slice();
+    assert_eq!(
+        read_compressed(&mut reader, 10),
+        Err(CompressionError::TooLarge(buf.len()))
+    );
+
+    let mut reader = &stream[..20];
+    assert_eq!(
+        read_compressed(&mut reader, buf.len()),
+        Err(CompressionError::InvalidPayload)
+    );
+}
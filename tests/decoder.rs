@@ -35,6 +35,12 @@ fn test_decode_nil() {
     assert_eq!(decoder.get_ref().len() - decoder.position() as usize, 0);
     let next_val = decoder.decode_none();
     assert_eq!(next_val, false);
+    assert_eq!(decoder.position(), 1);
+
+    let mut empty = Vec::new();
+    let mut decoder = Cursor::new(&mut empty);
+    assert!(!decoder.decode_none());
+    assert_eq!(decoder.position(), 0);
 }
 
 #[test]
@@ -119,6 +125,71 @@ fn test_decode_string() {
     assert_eq!(error, DecodingError::InvalidString);
 }
 
+#[test]
+fn test_decode_borrowed() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_str("Test String")
+        .unwrap()
+        .encode_bytes(&[1, 2, 3])
+        .unwrap()
+        .encode_u32(3)
+        .unwrap()
+        .encode_str("Truncated")
+        .unwrap();
+    let mut buf = encoder.into_inner();
+    buf.truncate(buf.len() - 1);
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(decoder.decode_str().unwrap(), "Test String");
+    assert_eq!(decoder.decode_byte_slice().unwrap(), &[1, 2, 3]);
+    assert_eq!(
+        decoder.decode_str().unwrap_err(),
+        DecodingError::InvalidString
+    );
+    assert_eq!(decoder.decode_u32().unwrap(), 3);
+
+    // The length runs past the end of the buffer.
+    assert_eq!(
+        decoder.decode_str().unwrap_err(),
+        DecodingError::InvalidString
+    );
+}
+
+#[test]
+fn test_decode_into() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_str("Test String")
+        .unwrap()
+        .encode_bytes(&[1, 2, 3])
+        .unwrap()
+        .encode_u32(3)
+        .unwrap();
+
+    let mut string = String::with_capacity(64);
+    string.push_str("Stale");
+    let mut bytes = vec![9; 64];
+
+    let mut decoder = Cursor::new(encoder.get_mut());
+    decoder.decode_string_into(&mut string).unwrap();
+    decoder.decode_bytes_into(&mut bytes).unwrap();
+    assert_eq!(string, "Test String");
+    assert_eq!(bytes, [1, 2, 3]);
+    assert_eq!(string.capacity(), 64);
+    assert_eq!(bytes.capacity(), 64);
+
+    assert_eq!(
+        decoder.decode_string_into(&mut string).unwrap_err(),
+        DecodingError::InvalidString
+    );
+    assert_eq!(
+        decoder.decode_bytes_into(&mut bytes).unwrap_err(),
+        DecodingError::InvalidBytes
+    );
+    assert_eq!(decoder.decode_u32().unwrap(), 3);
+}
+
 #[test]
 fn test_decode_error() {
     let mut encoder = Cursor::new(Vec::with_capacity(512));
@@ -214,6 +285,14 @@ fn test_decode_i32() {
 
     let error = decoder.decode_i32().unwrap_err();
     assert_eq!(error, DecodingError::InvalidI32);
+
+    // Small negative values fit in a single byte, sign bit included.
+    for v in [-1, -32, 63, -64] {
+        let mut encoder = Cursor::new(Vec::with_capacity(512));
+        encoder.encode_i32(v).unwrap();
+        let mut decoder = Cursor::new(encoder.get_mut());
+        assert_eq!(decoder.decode_i32().unwrap(), v);
+    }
 }
 
 #[test]
@@ -228,6 +307,14 @@ fn test_decode_i64() {
 
     let error = decoder.decode_i64().unwrap_err();
     assert_eq!(error, DecodingError::InvalidI64);
+
+    // Small negative values fit in a single byte, sign bit included.
+    for v in [-1, -32, 63, -64] {
+        let mut encoder = Cursor::new(Vec::with_capacity(512));
+        encoder.encode_i64(v).unwrap();
+        let mut decoder = Cursor::new(encoder.get_mut());
+        assert_eq!(decoder.decode_i64().unwrap(), v);
+    }
 }
 
 #[test]
@@ -257,3 +344,363 @@ fn test_decode_f64() {
     let error = decoder.decode_f64().unwrap_err();
     assert_eq!(error, DecodingError::InvalidF64);
 }
+
+#[test]
+fn test_skip_value() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_none()
+        .unwrap()
+        .encode_array(2, Kind::String)
+        .unwrap()
+        .encode_str("a")
+        .unwrap()
+        .encode_str("b")
+        .unwrap()
+        .encode_map(1, Kind::U32, Kind::F64)
+        .unwrap()
+        .encode_u32(1)
+        .unwrap()
+        .encode_f64(1.0)
+        .unwrap()
+        .encode_bytes(&[1, 2, 3])
+        .unwrap()
+        .encode_error(Box::<dyn Error>::from("error"))
+        .unwrap()
+        .encode_bool(true)
+        .unwrap()
+        .encode_u8(1)
+        .unwrap()
+        .encode_u16(1024)
+        .unwrap()
+        .encode_u64(u64::MAX)
+        .unwrap()
+        .encode_i32(-1)
+        .unwrap()
+        .encode_i64(i64::MIN)
+        .unwrap()
+        .encode_f32(1.0)
+        .unwrap();
+    let start = encoder.reserve_delimited().unwrap();
+    encoder.encode_str("delimited").unwrap();
+    encoder.encode_delimited(start).unwrap();
+    encoder.encode_u32(7).unwrap();
+    let mut buf = encoder.into_inner();
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    for _ in 0..13 {
+        decoder.skip_value().unwrap();
+    }
+    assert_eq!(decoder.decode_u32().unwrap(), 7);
+    assert_eq!(decoder.position(), buf.len() as u64);
+
+    buf.push(0xff);
+    let mut decoder = Cursor::new(&buf[buf.len() - 1..]);
+    assert_eq!(
+        decoder.skip_value().unwrap_err(),
+        DecodingError::InvalidKind
+    );
+    assert_eq!(decoder.position(), 0);
+
+    let mut decoder = Cursor::new(&buf[..buf.len() - 4]);
+    for _ in 0..12 {
+        decoder.skip_value().unwrap();
+    }
+    assert!(decoder.skip_value().is_err());
+}
+
+#[test]
+fn test_decode_delimited() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    let start = encoder.reserve_delimited().unwrap();
+    encoder.encode_bool(true).unwrap();
+    encoder.encode_delimited(start).unwrap();
+    let mut buf = encoder.into_inner();
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    let end = decoder.decode_delimited().unwrap();
+    assert_eq!(end, buf.len() as u64);
+    assert!(decoder.decode_bool().unwrap());
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(
+        decoder.decode_bool().unwrap_err(),
+        DecodingError::InvalidBool
+    );
+    decoder.set_position(0);
+    buf.pop();
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(
+        decoder.decode_delimited().unwrap_err(),
+        DecodingError::InvalidStruct
+    );
+}
+
+#[test]
+fn test_decode_packed() {
+    let floats = [0.0, -1.5, f64::MAX, f64::MIN_POSITIVE];
+    let ints = [0, -1, i64::MIN, i64::MAX, 300];
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_f64_slice(&floats)
+        .unwrap()
+        .encode_i64_slice(&ints)
+        .unwrap()
+        .encode_f32_slice(&[0.25])
+        .unwrap()
+        .encode_u32_slice(&[u32::MAX])
+        .unwrap()
+        .encode_i32_slice(&[i32::MIN])
+        .unwrap()
+        .encode_u64_slice(&[u64::MAX])
+        .unwrap();
+    // The same floats with the kind of every element.
+    encoder.encode_array(floats.len(), Kind::F64).unwrap();
+    for val in floats {
+        encoder.encode_f64(val).unwrap();
+    }
+    let buf = encoder.into_inner();
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(decoder.decode_f64_vec().unwrap(), floats);
+    assert_eq!(decoder.decode_i64_vec().unwrap(), ints);
+    assert_eq!(decoder.decode_f32_vec().unwrap(), [0.25]);
+    assert_eq!(decoder.decode_u32_vec().unwrap(), [u32::MAX]);
+    assert_eq!(decoder.decode_i32_vec().unwrap(), [i32::MIN]);
+    assert_eq!(decoder.decode_u64_vec().unwrap(), [u64::MAX]);
+    let mut reused = vec![7.0; 8];
+    decoder.decode_f64_vec_into(&mut reused).unwrap();
+    assert_eq!(reused, floats);
+    assert_eq!(decoder.position(), buf.len() as u64);
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(
+        decoder.decode_i64_vec().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+    assert_eq!(decoder.position(), 0);
+    for _ in 0..7 {
+        decoder.skip_value().unwrap();
+    }
+    assert_eq!(decoder.position(), buf.len() as u64);
+}
+
+#[test]
+fn test_decode_packed_truncated() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_f64_slice(&[1.0, 2.0]).unwrap();
+    let mut buf = encoder.into_inner();
+    buf.pop();
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(
+        decoder.decode_f64_vec().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(
+        decoder.skip_value().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+
+    // A size larger than the buffer is rejected before allocating.
+    let buf = [
+        Kind::PackedArray as u8,
+        Kind::U64 as u8,
+        Kind::U32 as u8,
+        0xff,
+        0xff,
+        0xff,
+        0xff,
+        0x0f,
+        1,
+    ];
+    let mut decoder = Cursor::new(&buf[..]);
+    assert_eq!(
+        decoder.decode_u64_vec().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+}
+
+#[test]
+fn test_decode_delta() {
+    // Deltas between the extremes overflow and wrap around.
+    let ints = [i64::MIN, i64::MAX, i64::MIN, 0, -1, i64::MAX];
+    let uints = [u64::MAX, 0, u64::MAX, 3, 2, 1];
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_i64_delta_slice(&ints)
+        .unwrap()
+        .encode_u64_delta_slice(&uints)
+        .unwrap()
+        .encode_i64_delta_slice(&[])
+        .unwrap();
+    let buf = encoder.into_inner();
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(decoder.decode_i64_vec().unwrap(), ints);
+    assert_eq!(decoder.decode_u64_vec().unwrap(), uints);
+    let mut reused = vec![7; 8];
+    decoder.decode_i64_vec_into(&mut reused).unwrap();
+    assert!(reused.is_empty());
+    assert_eq!(decoder.position(), buf.len() as u64);
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(
+        decoder.decode_u64_vec().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+    assert_eq!(decoder.position(), 0);
+    for _ in 0..3 {
+        decoder.skip_value().unwrap();
+    }
+    assert_eq!(decoder.position(), buf.len() as u64);
+
+    // Only 64-bit integers are delta encoded.
+    let buf = [
+        Kind::DeltaArray as u8,
+        Kind::I32 as u8,
+        Kind::U32 as u8,
+        1,
+        2,
+    ];
+    let mut decoder = Cursor::new(&buf[..]);
+    assert_eq!(
+        decoder.decode_i32_vec().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+    let mut decoder = Cursor::new(&buf[..]);
+    assert_eq!(
+        decoder.skip_value().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+}
+
+#[test]
+fn test_decode_run_length() {
+    let floats = [f64::NAN, f64::NAN, 0.0, -0.0, -0.0, 1.5];
+    let ints = vec![-3; 1000];
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_f64_run_length_slice(&floats)
+        .unwrap()
+        .encode_i32_run_length_slice(&ints)
+        .unwrap()
+        .encode_u64_run_length_slice(&[])
+        .unwrap()
+        .encode_f32_run_length_slice(&[0.5, 0.25])
+        .unwrap()
+        .encode_u32_run_length_slice(&[u32::MAX, u32::MAX])
+        .unwrap()
+        .encode_i64_run_length_slice(&[i64::MIN])
+        .unwrap();
+    let buf = encoder.into_inner();
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    let decoded = decoder.decode_f64_vec().unwrap();
+    assert_eq!(
+        decoded.iter().map(|x| x.to_bits()).collect::<Vec<_>>(),
+        floats.iter().map(|x| x.to_bits()).collect::<Vec<_>>()
+    );
+    assert_eq!(decoder.decode_i32_vec().unwrap(), ints);
+    assert!(decoder.decode_u64_vec().unwrap().is_empty());
+    assert_eq!(decoder.decode_f32_vec().unwrap(), [0.5, 0.25]);
+    assert_eq!(decoder.decode_u32_vec().unwrap(), [u32::MAX, u32::MAX]);
+    assert_eq!(decoder.decode_i64_vec().unwrap(), [i64::MIN]);
+    assert_eq!(decoder.position(), buf.len() as u64);
+
+    // A thousand of the same value take up a single run.
+    assert!(buf.len() < 100);
+    let mut decoder = Cursor::new(buf.as_slice());
+    for _ in 0..6 {
+        decoder.skip_value().unwrap();
+    }
+    assert_eq!(decoder.position(), buf.len() as u64);
+}
+
+#[test]
+fn test_decode_run_length_invalid() {
+    let header = [Kind::RunLengthArray as u8, Kind::U32 as u8, Kind::U32 as u8];
+    for runs in [
+        // A run of nothing.
+        &[2, 0, 1, 2, 1][..],
+        // A run past the end of the array.
+        &[2, 3, 1][..],
+        // Fewer elements than the array holds.
+        &[2, 1, 1][..],
+    ] {
+        let mut buf = header.to_vec();
+        buf.extend_from_slice(runs);
+        let mut decoder = Cursor::new(buf.as_slice());
+        assert_eq!(
+            decoder.decode_u32_vec().unwrap_err(),
+            DecodingError::InvalidArray
+        );
+        let mut decoder = Cursor::new(buf.as_slice());
+        assert_eq!(
+            decoder.skip_value().unwrap_err(),
+            DecodingError::InvalidArray
+        );
+    }
+
+    // A few bytes can't expand past the limit.
+    let mut buf = header.to_vec();
+    buf.extend_from_slice(&[
+        0xff, 0xff, 0xff, 0xff, 0x0f, 0xff, 0xff, 0xff, 0xff, 0x0f, 1,
+    ]);
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(
+        decoder.decode_u32_vec().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+}
+
+#[test]
+fn test_decode_bools() {
+    let flags = (0..17).map(|i| i % 5 == 0).collect::<Vec<_>>();
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_bool_slice(&flags).unwrap();
+    // The same flags with the kind of every element.
+    encoder.encode_array(flags.len(), Kind::Bool).unwrap();
+    for val in &flags {
+        encoder.encode_bool(*val).unwrap();
+    }
+    encoder.encode_bool_slice(&flags).unwrap();
+    let buf = encoder.into_inner();
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(decoder.decode_bool_vec().unwrap(), flags);
+    let bits = decoder.decode_bits().unwrap();
+    assert_eq!(bits.iter().collect::<Vec<_>>(), flags);
+    let mut reused = vec![true; 32];
+    decoder.decode_bool_vec_into(&mut reused).unwrap();
+    assert_eq!(reused, flags);
+    assert_eq!(decoder.position(), buf.len() as u64);
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert_eq!(
+        decoder.decode_u32_vec().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+    assert_eq!(decoder.position(), 0);
+    for _ in 0..3 {
+        decoder.skip_value().unwrap();
+    }
+    assert_eq!(decoder.position(), buf.len() as u64);
+
+    // Bits past the end of the array are ignored, and missing bytes are an error.
+    let buf = [
+        Kind::PackedArray as u8,
+        Kind::Bool as u8,
+        Kind::U32 as u8,
+        3,
+        0xff,
+    ];
+    let mut decoder = Cursor::new(&buf[..]);
+    let bits = decoder.decode_bits().unwrap();
+    assert_eq!(bits.as_bytes(), [0b111]);
+    let mut decoder = Cursor::new(&buf[..buf.len() - 1]);
+    assert_eq!(
+        decoder.decode_bool_vec().unwrap_err(),
+        DecodingError::InvalidArray
+    );
+}
@@ -0,0 +1,424 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+extern crate polyglot_rs;
+
+use base64::{engine::general_purpose, Engine as _};
+use polyglot_rs::{
+    DecodingError, DynamicError, DynamicMessage, Encoder, EnumSchema, FieldType, MessageSchema,
+    Schema, Value, MAX_DEPTH,
+};
+use serde::Deserialize;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+struct GeneratorTestData {
+    testall: String,
+}
+
+fn get_test_data() -> Vec<u8> {
+    let data = serde_json::from_slice::<GeneratorTestData>(
+        &fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("resources")
+                .join("test")
+                .join("generator-test-data.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    general_purpose::STANDARD.decode(data.testall).unwrap()
+}
+
+fn test_all_schema() -> Schema {
+    Schema::new("TestAll")
+        .with_enum(
+            EnumSchema::new("Request.Corpus")
+                .with_value("Universal", 0)
+                .with_value("Web", 1),
+        )
+        .with_enum(
+            EnumSchema::new("Test")
+                .with_value("Potato", 0)
+                .with_value("Monkey", 1),
+        )
+        .with_message(
+            MessageSchema::new("TestAll")
+                .with_field("request", FieldType::Message("Request".to_string()))
+                .with_field("response", FieldType::Message("Response".to_string()))
+                .with_field(
+                    "searchResponse",
+                    FieldType::Message("SearchResponse".to_string()),
+                )
+                .with_field(
+                    "stockPricesSuperWrap",
+                    FieldType::Message("StockPricesSuperWrap".to_string()),
+                ),
+        )
+        .with_message(
+            MessageSchema::new("Request")
+                .with_field("Message", FieldType::String)
+                .with_field("corpus", FieldType::Enum("Request.Corpus".to_string())),
+        )
+        .with_message(
+            MessageSchema::new("Response")
+                .with_field("Message", FieldType::String)
+                .with_field("Test", FieldType::Message("Data".to_string())),
+        )
+        .with_message(
+            MessageSchema::new("Data")
+                .with_field("Message", FieldType::String)
+                .with_field("Checker", FieldType::Enum("Test".to_string())),
+        )
+        .with_message(
+            MessageSchema::new("SearchResponse")
+                .with_field(
+                    "results",
+                    FieldType::array(FieldType::Message("SearchResponse.Result".to_string())),
+                )
+                .with_field(
+                    "results2",
+                    FieldType::array(FieldType::Message("SearchResponse.Result".to_string())),
+                )
+                .with_field("snippets", FieldType::array(FieldType::String))
+                .with_field("snippets2", FieldType::array(FieldType::String)),
+        )
+        .with_message(
+            MessageSchema::new("SearchResponse.Result")
+                .with_field("url", FieldType::String)
+                .with_field("title", FieldType::String)
+                .with_field("snippets", FieldType::array(FieldType::String)),
+        )
+        .with_message(MessageSchema::new("StockPricesSuperWrap").with_field(
+            "prices",
+            FieldType::map(
+                FieldType::String,
+                FieldType::Message("StockPricesWrapper".to_string()),
+            ),
+        ))
+        .with_message(MessageSchema::new("StockPricesWrapper").with_field(
+            "sPrices",
+            FieldType::array(FieldType::Message("StockPrices".to_string())),
+        ))
+        .with_message(
+            MessageSchema::new("StockPrices")
+                .with_field("prices", FieldType::map(FieldType::String, FieldType::F64)),
+        )
+}
+
+#[test]
+fn test_decode() {
+    let data = get_test_data();
+    let schema = Arc::new(test_all_schema());
+
+    let message = DynamicMessage::decode(schema, &mut Cursor::new(data.as_slice()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(message.name(), "TestAll");
+
+    let request = message.get("request").unwrap().as_message().unwrap();
+    assert_eq!(request.get("Message").unwrap().as_str(), Some("Hello"));
+    assert_eq!(request.get("corpus"), Some(&Value::Enum(0)));
+
+    let search_response = message.get("searchResponse").unwrap().as_message().unwrap();
+    match search_response.get("results").unwrap() {
+        Value::Array(results) => {
+            assert_eq!(results.len(), 1);
+            let result = results[0].as_message().unwrap();
+            assert_eq!(
+                result.get("url").unwrap().as_str(),
+                Some("https://www.google.com")
+            );
+        }
+        other => panic!("unexpected value {other:?}"),
+    }
+
+    let names: Vec<&str> = message.iter().map(|(name, _)| name).collect();
+    assert_eq!(
+        names,
+        [
+            "request",
+            "response",
+            "searchResponse",
+            "stockPricesSuperWrap"
+        ]
+    );
+}
+
+#[test]
+fn test_encode() {
+    let data = get_test_data();
+    let schema = Arc::new(test_all_schema());
+
+    let message = DynamicMessage::decode(schema, &mut Cursor::new(data.as_slice()))
+        .unwrap()
+        .unwrap();
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    message.encode(&mut encoder).unwrap();
+    assert_eq!(encoder.into_inner(), data);
+}
+
+#[test]
+fn test_mutate() {
+    let schema = Arc::new(test_all_schema());
+
+    let mut prices = DynamicMessage::new(schema.clone(), "StockPrices").unwrap();
+    prices
+        .set(
+            "prices",
+            Value::Map(vec![(Value::String("AAPL".to_string()), Value::F64(100.0))]),
+        )
+        .unwrap();
+    if let Some(Value::Map(entries)) = prices.get_mut("prices") {
+        entries.push((Value::String("MSFT".to_string()), Value::F64(200.0)));
+    }
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    prices.encode(&mut encoder).unwrap();
+
+    let mut data = encoder.into_inner();
+    let decoded =
+        DynamicMessage::decode_message(schema, "StockPrices", &mut Cursor::new(&mut data))
+            .unwrap()
+            .unwrap();
+    assert_eq!(decoded, prices);
+    assert_eq!(
+        decoded.get("prices"),
+        Some(&Value::Map(vec![
+            (Value::String("AAPL".to_string()), Value::F64(100.0)),
+            (Value::String("MSFT".to_string()), Value::F64(200.0)),
+        ]))
+    );
+}
+
+#[test]
+fn test_set_invalid() {
+    let schema = Arc::new(test_all_schema());
+    let mut request = DynamicMessage::new(schema.clone(), "Request").unwrap();
+
+    assert_eq!(
+        request.set("Message", Value::U32(1)),
+        Err(DynamicError::InvalidValue("Message".to_string()))
+    );
+    assert_eq!(
+        request.set("missing", Value::U32(1)),
+        Err(DynamicError::UnknownField("missing".to_string()))
+    );
+    assert_eq!(
+        DynamicMessage::new(schema, "Missing"),
+        Err(DynamicError::UnknownMessage("Missing".to_string()))
+    );
+
+    *request.get_mut("corpus").unwrap() = Value::String("Web".to_string());
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    assert_eq!(
+        request.encode(&mut encoder).unwrap_err(),
+        DynamicError::InvalidValue("corpus".to_string())
+    );
+}
+
+#[test]
+fn test_decode_none() {
+    let schema = Arc::new(test_all_schema());
+    let mut data = vec![polyglot_rs::Kind::None as u8];
+
+    let decoded = DynamicMessage::decode(schema, &mut Cursor::new(&mut data)).unwrap();
+    assert!(decoded.is_none());
+}
+
+#[test]
+fn test_decode_depth() {
+    let schema = Arc::new(
+        Schema::new("Node").with_message(
+            MessageSchema::new("Node")
+                .with_field("value", FieldType::U32)
+                .with_field("parent", FieldType::Message("Node".to_string())),
+        ),
+    );
+    let nested = |depth: usize| {
+        let mut encoder = Cursor::new(Vec::with_capacity(512));
+        for i in 0..depth {
+            encoder.encode_u32(i as u32).unwrap();
+        }
+        encoder.encode_none().unwrap();
+        encoder.into_inner()
+    };
+
+    let buf = nested(MAX_DEPTH);
+    let message = DynamicMessage::decode(schema.clone(), &mut Cursor::new(buf.as_slice()))
+        .unwrap()
+        .unwrap();
+    assert_eq!(message.get("value"), Some(&Value::U32(0)));
+
+    let buf = nested(MAX_DEPTH + 1);
+    let error = DynamicMessage::decode(schema, &mut Cursor::new(buf.as_slice())).unwrap_err();
+    assert_eq!(
+        error,
+        DynamicError::Decoding(DecodingError::MaxDepthExceeded)
+    );
+}
+
+#[test]
+fn test_decode_huge_length() {
+    let schema = Arc::new(
+        Schema::new("Tags").with_message(
+            MessageSchema::new("Tags")
+                .with_field("tags", FieldType::array(FieldType::String))
+                .with_field(
+                    "scores",
+                    FieldType::map(FieldType::String, FieldType::String),
+                ),
+        ),
+    );
+    let buf = [0x01, 0x05, 0x0a, 0xff, 0xff, 0xff, 0xff, 0x0f];
+    assert!(DynamicMessage::decode(schema.clone(), &mut Cursor::new(&buf[..])).is_err());
+
+    let buf = [
+        0x01, 0x05, 0x0a, 0x00, 0x02, 0x05, 0x05, 0x0a, 0xff, 0xff, 0xff, 0xff, 0x0f,
+    ];
+    assert!(DynamicMessage::decode(schema, &mut Cursor::new(&buf[..])).is_err());
+}
+
+#[test]
+fn test_delimited() {
+    let old = Arc::new(
+        Schema::new("User").with_message(
+            MessageSchema::new("User")
+                .delimited()
+                .with_field("name", FieldType::String),
+        ),
+    );
+    let new = Arc::new(
+        Schema::new("User").with_message(
+            MessageSchema::new("User")
+                .delimited()
+                .with_field("name", FieldType::String)
+                .with_field("tags", FieldType::array(FieldType::String))
+                .with_field("age", FieldType::U32),
+        ),
+    );
+
+    let mut user = DynamicMessage::new(new.clone(), "User").unwrap();
+    user.set("name", Value::String("name".to_string())).unwrap();
+    user.set("age", Value::U32(42)).unwrap();
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    user.encode(&mut encoder).unwrap();
+    let mut data = encoder.into_inner();
+
+    let decoded = DynamicMessage::decode(old.clone(), &mut Cursor::new(&mut data))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        decoded.get("name"),
+        Some(&Value::String("name".to_string()))
+    );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    decoded.encode(&mut encoder).unwrap();
+    let mut data = encoder.into_inner();
+    let decoded = DynamicMessage::decode(new, &mut Cursor::new(&mut data))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        decoded.get("name"),
+        Some(&Value::String("name".to_string()))
+    );
+    assert_eq!(decoded.get("tags"), Some(&Value::Array(Vec::new())));
+    assert_eq!(decoded.get("age"), Some(&Value::U32(0)));
+}
+
+#[test]
+fn test_optional_and_oneof() {
+    let schema = Arc::new(
+        Schema::new("User").with_message(
+            MessageSchema::new("User")
+                .with_field("age", FieldType::optional(FieldType::U32))
+                .with_field(
+                    "contact",
+                    FieldType::Oneof(vec![(1, FieldType::String), (2, FieldType::U64)]),
+                )
+                .with_field("nickname", FieldType::optional(FieldType::String)),
+        ),
+    );
+
+    let mut user = DynamicMessage::new(schema.clone(), "User").unwrap();
+    assert_eq!(user.get("age"), Some(&Value::None));
+    assert_eq!(user.get("contact"), Some(&Value::None));
+    user.set("age", Value::U32(42)).unwrap();
+    user.set(
+        "contact",
+        Value::Oneof(1, Box::new(Value::String("email".to_string()))),
+    )
+    .unwrap();
+    assert_eq!(
+        user.set("contact", Value::Oneof(3, Box::new(Value::U64(1)))),
+        Err(DynamicError::InvalidValue("contact".to_string()))
+    );
+    assert_eq!(
+        user.set("contact", Value::Oneof(2, Box::new(Value::U32(1)))),
+        Err(DynamicError::InvalidValue("contact".to_string()))
+    );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    user.encode(&mut encoder).unwrap();
+    let mut data = encoder.into_inner();
+    let decoded = DynamicMessage::decode(schema, &mut Cursor::new(&mut data))
+        .unwrap()
+        .unwrap();
+    assert_eq!(decoded.get("age"), Some(&Value::U32(42)));
+    assert_eq!(
+        decoded.get("contact"),
+        Some(&Value::Oneof(
+            1,
+            Box::new(Value::String("email".to_string()))
+        ))
+    );
+    assert_eq!(decoded.get("nickname"), Some(&Value::None));
+}
+
+#[test]
+fn test_decode_packed() {
+    let schema = Arc::new(
+        Schema::new("Series").with_message(
+            MessageSchema::new("Series")
+                .with_field("values", FieldType::array(FieldType::F64))
+                .with_field("counts", FieldType::array(FieldType::U32)),
+        ),
+    );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_f64_slice(&[1.5])
+        .unwrap()
+        .encode_u32_slice(&[3, 4])
+        .unwrap();
+    let mut data = encoder.into_inner();
+    let series = DynamicMessage::decode(schema, &mut Cursor::new(&mut data))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        series.get("values"),
+        Some(&Value::Array(vec![Value::F64(1.5)]))
+    );
+    assert_eq!(
+        series.get("counts"),
+        Some(&Value::Array(vec![Value::U32(3), Value::U32(4)]))
+    );
+}
@@ -16,7 +16,9 @@
 
 extern crate polyglot_rs;
 
+use polyglot_rs::Bits;
 use polyglot_rs::Encoder;
+use polyglot_rs::EncodingError;
 use polyglot_rs::Kind;
 use std::error::Error;
 use std::io::Cursor;
@@ -173,3 +175,147 @@ fn test_encode_f64() {
     assert_eq!(encoder.position(), 9);
     assert_eq!(encoder.get_ref()[1..].to_owned(), e);
 }
+
+#[test]
+fn test_encode_delimited() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_u32(1).unwrap();
+    let start = encoder.reserve_delimited().unwrap();
+    encoder.encode_str("ab").unwrap();
+    encoder.encode_delimited(start).unwrap();
+
+    let e = [
+        Kind::U32 as u8,
+        1,
+        Kind::Any as u8,
+        Kind::U32 as u8,
+        0x85,
+        0x80,
+        0x80,
+        0x80,
+        0,
+        Kind::String as u8,
+        Kind::U32 as u8,
+        2,
+        b'a',
+        b'b',
+    ];
+    assert_eq!(encoder.position(), e.len() as u64);
+    assert_eq!(encoder.get_ref().to_owned(), e);
+
+    assert_eq!(
+        encoder.encode_delimited(0).unwrap_err(),
+        EncodingError::WriteFailed
+    );
+}
+
+#[test]
+fn test_encode_packed() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_f64_slice(&[1.0, -0.5]).unwrap();
+    let mut e = vec![Kind::PackedArray as u8, Kind::F64 as u8, Kind::U32 as u8, 2];
+    e.extend_from_slice(&1.0f64.to_be_bytes());
+    e.extend_from_slice(&(-0.5f64).to_be_bytes());
+    assert_eq!(encoder.get_ref().to_owned(), e);
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_i32_slice(&[1, -1, 300])
+        .unwrap()
+        .encode_u64_slice(&[])
+        .unwrap();
+    let e = [
+        Kind::PackedArray as u8,
+        Kind::I32 as u8,
+        Kind::U32 as u8,
+        3,
+        2,
+        1,
+        0xd8,
+        0x04,
+        Kind::PackedArray as u8,
+        Kind::U64 as u8,
+        Kind::U32 as u8,
+        0,
+    ];
+    assert_eq!(encoder.get_ref().to_owned(), e);
+}
+
+#[test]
+fn test_encode_delta_and_run_length() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_i64_delta_slice(&[1, 3, 2])
+        .unwrap()
+        .encode_u32_run_length_slice(&[5, 5, 5, 9])
+        .unwrap()
+        .encode_u64_delta_slice(&[])
+        .unwrap();
+    let e = [
+        Kind::DeltaArray as u8,
+        Kind::I64 as u8,
+        Kind::U32 as u8,
+        3,
+        2,
+        4,
+        1,
+        Kind::RunLengthArray as u8,
+        Kind::U32 as u8,
+        Kind::U32 as u8,
+        4,
+        3,
+        5,
+        1,
+        9,
+        Kind::DeltaArray as u8,
+        Kind::U64 as u8,
+        Kind::U32 as u8,
+        0,
+    ];
+    assert_eq!(encoder.get_ref().to_owned(), e);
+
+    // Zeros of different signs are different runs.
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_f32_run_length_slice(&[0.0, -0.0]).unwrap();
+    let mut e = vec![
+        Kind::RunLengthArray as u8,
+        Kind::F32 as u8,
+        Kind::U32 as u8,
+        2,
+    ];
+    e.push(1);
+    e.extend_from_slice(&0.0f32.to_be_bytes());
+    e.push(1);
+    e.extend_from_slice(&(-0.0f32).to_be_bytes());
+    assert_eq!(encoder.get_ref().to_owned(), e);
+}
+
+#[test]
+fn test_encode_bools() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_bool_slice(&[true, false, true, true, false, false, false, false, true])
+        .unwrap()
+        .encode_bits(&Bits::from(&[false, true][..]))
+        .unwrap()
+        .encode_bool_slice(&[])
+        .unwrap();
+    let e = [
+        Kind::PackedArray as u8,
+        Kind::Bool as u8,
+        Kind::U32 as u8,
+        9,
+        0b1101,
+        1,
+        Kind::PackedArray as u8,
+        Kind::Bool as u8,
+        Kind::U32 as u8,
+        2,
+        0b10,
+        Kind::PackedArray as u8,
+        Kind::Bool as u8,
+        Kind::U32 as u8,
+        0,
+    ];
+    assert_eq!(encoder.get_ref().to_owned(), e);
+}
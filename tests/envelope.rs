@@ -0,0 +1,107 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+extern crate polyglot_rs;
+
+use polyglot_rs::{
+    Decode, DecodingError, Encode, Encoder, Envelope, EnvelopeError, Kind, MessageType,
+    TypeRegistry,
+};
+use std::any::Any;
+use std::io::Cursor;
+
+#[test]
+fn test_envelope() {
+    let envelope = Envelope {
+        message_type: MessageType::Id(7),
+        payload: vec![1, 2, 3],
+    };
+    let buf = envelope.encode_to_vec().unwrap();
+    assert_eq!(buf[0], Kind::U32 as u8);
+    assert_eq!(Envelope::decode_from_slice(&buf).unwrap(), envelope);
+
+    let envelope = Envelope {
+        message_type: "test.v1.Request".into(),
+        payload: Vec::new(),
+    };
+    let buf = envelope.encode_to_vec().unwrap();
+    assert_eq!(buf[0], Kind::String as u8);
+    assert_eq!(Envelope::decode_from_slice(&buf).unwrap(), envelope);
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_bool(true)
+        .unwrap()
+        .encode_bytes(&[])
+        .unwrap();
+    assert_eq!(
+        *Envelope::decode_from_slice(encoder.get_ref())
+            .unwrap_err()
+            .downcast::<DecodingError>()
+            .unwrap(),
+        DecodingError::InvalidStruct
+    );
+}
+
+#[test]
+fn test_registry() {
+    let mut registry = TypeRegistry::new();
+    registry.register::<Envelope>(1);
+    assert!(registry.contains(&MessageType::Id(1)));
+
+    let inner = Envelope {
+        message_type: "inner".into(),
+        payload: vec![42],
+    };
+    let buf = Envelope::new(1, &inner).unwrap().encode_to_vec().unwrap();
+    let decoded: Box<dyn Any + Send> = registry.decode_from_slice(&buf).unwrap();
+    assert_eq!(decoded.downcast_ref::<Envelope>(), Some(&inner));
+
+    let buf = Envelope::new(2, &inner).unwrap().encode_to_vec().unwrap();
+    assert_eq!(
+        *registry
+            .decode_from_slice(&buf)
+            .unwrap_err()
+            .downcast::<EnvelopeError>()
+            .unwrap(),
+        EnvelopeError::UnknownType(MessageType::Id(2))
+    );
+}
+
+#[test]
+fn test_registry_enum() {
+    #[derive(Debug, PartialEq)]
+    enum Routed {
+        Envelope(Envelope),
+        Payload(Vec<u8>),
+    }
+
+    let mut registry = TypeRegistry::new();
+    registry
+        .register_with("envelope", Routed::Envelope)
+        .register_with(MessageType::Id(3), |e: Envelope| Routed::Payload(e.payload));
+
+    let inner = Envelope {
+        message_type: MessageType::Id(0),
+        payload: vec![42],
+    };
+    let routed = registry
+        .decode(&Envelope::new("envelope", &inner).unwrap())
+        .unwrap();
+    assert_eq!(routed, Routed::Envelope(inner.clone()));
+    let routed = registry.decode(&Envelope::new(3, &inner).unwrap()).unwrap();
+    assert_eq!(routed, Routed::Payload(vec![42]));
+}
@@ -2,7 +2,7 @@ extern crate polyglot_rs;
 
 mod tests;
 use crate::tests::{
-    Data, Decode, Encode, Request, RequestCorpus, Response, SearchResponse, SearchResponseResult,
+    Data, Decode, Encode, Request, request, Response, SearchResponse, search_response,
     StockPrices, StockPricesSuperWrap, StockPricesWrapper, Test,
 };
 use base64::{engine::general_purpose, Engine as _};
@@ -44,32 +44,32 @@ fn test_encode() {
     let poly_data = general_purpose::STANDARD.decode(test_b64.testall).unwrap();
 
     let test = tests::TestAll {
-        request: Request {
+        request: Some(Request {
             message: "Hello".to_string(),
-            corpus: RequestCorpus::Universal,
-        },
-        response: Response {
+            corpus: request::Corpus::Universal,
+        }),
+        response: Some(Response {
             message: "Hello".to_string(),
-            test: Data {
+            test: Some(Data {
                 message: "Hello".to_string(),
                 checker: Test::Potato,
-            },
-        },
-        search_response: SearchResponse {
-            results: Vec::from([SearchResponseResult {
+            }),
+        }),
+        search_response: Some(SearchResponse {
+            results: Vec::from([search_response::Result2 {
                 url: "https://www.google.com".to_string(),
                 title: "Google".to_string(),
                 snippets: ["Google is a search engine".to_string()].to_vec(),
             }]),
-            results2: Vec::from([SearchResponseResult {
+            results2: Vec::from([search_response::Result2 {
                 url: "https://www.google.com".to_string(),
                 title: "Google".to_string(),
                 snippets: ["Google is a search engine".to_string()].to_vec(),
             }]),
             snippets: ["Google is a search engine".to_string()].to_vec(),
             snippets2: ["Google is a search engine".to_string()].to_vec(),
-        },
-        stock_prices_super_wrap: StockPricesSuperWrap {
+        }),
+        stock_prices_super_wrap: Some(StockPricesSuperWrap {
             prices: HashMap::from([(
                 "AAPL".to_string(),
                 StockPricesWrapper {
@@ -78,7 +78,7 @@ fn test_encode() {
                     }]),
                 },
             )]),
-        },
+        }),
     };
 
     let mut encoder: Cursor<Vec<u8>> = Cursor::new(Vec::with_capacity(512));
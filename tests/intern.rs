@@ -0,0 +1,177 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use polyglot_rs::{intern, resolve, Decoder, DecodingError, Encoder, Kind, MAX_RESOLVED_LEN};
+use std::io::Cursor;
+use std::sync::Arc;
+
+fn encode_prices() -> Vec<u8> {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_array(3, Kind::Map).unwrap();
+    for price in [1.0, 2.0, 3.0] {
+        encoder
+            .encode_map(2, Kind::String, Kind::F64)
+            .unwrap()
+            .encode_str("AAPL")
+            .unwrap()
+            .encode_f64(price)
+            .unwrap()
+            .encode_str("MSFT")
+            .unwrap()
+            .encode_f64(price)
+            .unwrap();
+    }
+    encoder.encode_str("only once").unwrap();
+    encoder.into_inner()
+}
+
+#[test]
+fn test_intern() {
+    let buf = encode_prices();
+    let interned = intern(&buf).unwrap();
+    assert!(interned.len() < buf.len());
+    assert_eq!(
+        interned[..4],
+        [
+            Kind::StringTable as u8,
+            Kind::U32 as u8,
+            2,
+            Kind::String as u8
+        ]
+    );
+    assert_eq!(resolve(&interned).unwrap(), buf);
+
+    let mut decoder = Cursor::new(interned.as_slice());
+    let table = decoder.decode_string_table().unwrap();
+    assert_eq!(table.len(), 2);
+    assert_eq!(&*table.iter().next().unwrap().clone(), "AAPL");
+    assert_eq!(decoder.decode_array(Kind::Map).unwrap(), 3);
+    let mut keys = Vec::new();
+    for _ in 0..3 {
+        assert_eq!(decoder.decode_map(Kind::String, Kind::F64).unwrap(), 2);
+        for _ in 0..2 {
+            keys.push(decoder.decode_interned_str(&table).unwrap());
+            decoder.decode_f64().unwrap();
+        }
+    }
+    // Every key shares the string of the table.
+    assert_eq!(
+        keys.iter().map(|key| &**key).collect::<Vec<_>>(),
+        ["AAPL", "MSFT"].repeat(3)
+    );
+    assert!(keys.iter().step_by(2).all(|key| Arc::ptr_eq(key, &keys[0])));
+    assert_eq!(&*decoder.decode_interned_str(&table).unwrap(), "only once");
+    assert_eq!(decoder.position(), interned.len() as u64);
+
+    // The table and references are skipped like any other value.
+    let mut decoder = Cursor::new(interned.as_slice());
+    while decoder.position() < interned.len() as u64 {
+        decoder.skip_value().unwrap();
+    }
+}
+
+#[test]
+fn test_intern_unchanged() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_str("a")
+        .unwrap()
+        .encode_str("b")
+        .unwrap()
+        .encode_u32(7)
+        .unwrap();
+    let buf = encoder.into_inner();
+    assert_eq!(intern(&buf).unwrap(), buf);
+    assert_eq!(resolve(&buf).unwrap(), buf);
+    assert_eq!(intern(&[]).unwrap(), Vec::<u8>::new());
+
+    let mut decoder = Cursor::new(buf.as_slice());
+    assert!(decoder.decode_string_table().unwrap().is_empty());
+    assert_eq!(decoder.position(), 0);
+}
+
+#[test]
+fn test_intern_delimited() {
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_str("name").unwrap();
+    let start = encoder.reserve_delimited().unwrap();
+    encoder
+        .encode_str("name")
+        .unwrap()
+        .encode_error(Box::<dyn std::error::Error>::from("name"))
+        .unwrap()
+        .encode_bytes(b"name")
+        .unwrap()
+        .encode_delimited(start)
+        .unwrap();
+    let buf = encoder.into_inner();
+
+    // The delimited value shrinks along with the string inside it, while the
+    // strings of errors and bytes are left alone.
+    let interned = intern(&buf).unwrap();
+    let mut decoder = Cursor::new(interned.as_slice());
+    let table = decoder.decode_string_table().unwrap();
+    assert_eq!(table.len(), 1);
+    assert_eq!(&*decoder.decode_interned_str(&table).unwrap(), "name");
+    let end = decoder.decode_delimited().unwrap();
+    assert_eq!(&*decoder.decode_interned_str(&table).unwrap(), "name");
+    assert_eq!(decoder.decode_error().unwrap().to_string(), "name");
+    assert_eq!(decoder.decode_bytes().unwrap(), b"name");
+    assert_eq!(decoder.position(), end);
+    assert_eq!(end, interned.len() as u64);
+
+    // Resolving writes the length of the delimited value without the padding
+    // the encoder reserves for it.
+    let resolved = resolve(&interned).unwrap();
+    assert_eq!(resolved.len(), buf.len() - 4);
+    assert_eq!(intern(&resolved).unwrap(), interned);
+}
+
+#[test]
+fn test_resolve_invalid() {
+    let buf = encode_prices();
+    let interned = intern(&buf).unwrap();
+    assert_eq!(intern(&interned), Err(DecodingError::InvalidString));
+
+    // A reference past the end of the table.
+    let mut invalid = interned.clone();
+    let last_ref = invalid
+        .iter()
+        .rposition(|b| *b == Kind::StringRef as u8)
+        .unwrap();
+    invalid[last_ref + 2] = 5;
+    assert_eq!(resolve(&invalid), Err(DecodingError::InvalidString));
+
+    // A table larger than the buffer.
+    let invalid = [Kind::StringTable as u8, Kind::U32 as u8, 0x7f];
+    assert_eq!(resolve(&invalid), Err(DecodingError::InvalidString));
+
+    // A long string referenced many times can't expand without limit.
+    let long = "x".repeat(1 << 20);
+    let mut encoder = Cursor::new(Vec::new());
+    encoder
+        .encode_str(&long)
+        .unwrap()
+        .encode_str(&long)
+        .unwrap();
+    let mut bomb = intern(encoder.get_ref()).unwrap();
+    let reference = bomb[bomb.len() - 3..].to_vec();
+    assert_eq!(reference, [Kind::StringRef as u8, Kind::U32 as u8, 0]);
+    for _ in 0..MAX_RESOLVED_LEN >> 20 {
+        bomb.extend_from_slice(&reference);
+    }
+    assert_eq!(resolve(&bomb), Err(DecodingError::InvalidString));
+}
@@ -0,0 +1,424 @@
+/*
+    Copyright 2022 Loophole Labs
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+           http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+extern crate polyglot_rs;
+
+use base64::{engine::general_purpose, Engine as _};
+use polyglot_rs::{
+    validate, DecodingError, Encoder, EnumSchema, FieldType, Kind, MessageSchema, Schema,
+    ValidationError, MAX_DEPTH,
+};
+use serde::Deserialize;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct GeneratorTestData {
+    testall: String,
+}
+
+fn get_test_data() -> Vec<u8> {
+    let data = serde_json::from_slice::<GeneratorTestData>(
+        &fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("resources")
+                .join("test")
+                .join("generator-test-data.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    general_purpose::STANDARD.decode(data.testall).unwrap()
+}
+
+fn test_all_schema() -> Schema {
+    Schema::new("TestAll")
+        .with_enum(
+            EnumSchema::new("Request.Corpus")
+                .with_value("Universal", 0)
+                .with_value("Web", 1),
+        )
+        .with_enum(
+            EnumSchema::new("Test")
+                .with_value("Potato", 0)
+                .with_value("Monkey", 1),
+        )
+        .with_message(
+            MessageSchema::new("TestAll")
+                .with_field("request", FieldType::Message("Request".to_string()))
+                .with_field("response", FieldType::Message("Response".to_string()))
+                .with_field(
+                    "searchResponse",
+                    FieldType::Message("SearchResponse".to_string()),
+                )
+                .with_field(
+                    "stockPricesSuperWrap",
+                    FieldType::Message("StockPricesSuperWrap".to_string()),
+                ),
+        )
+        .with_message(
+            MessageSchema::new("Request")
+                .with_field("Message", FieldType::String)
+                .with_field("corpus", FieldType::Enum("Request.Corpus".to_string())),
+        )
+        .with_message(
+            MessageSchema::new("Response")
+                .with_field("Message", FieldType::String)
+                .with_field("Test", FieldType::Message("Data".to_string())),
+        )
+        .with_message(
+            MessageSchema::new("Data")
+                .with_field("Message", FieldType::String)
+                .with_field("Checker", FieldType::Enum("Test".to_string())),
+        )
+        .with_message(
+            MessageSchema::new("SearchResponse")
+                .with_field(
+                    "results",
+                    FieldType::array(FieldType::Message("SearchResponse.Result".to_string())),
+                )
+                .with_field(
+                    "results2",
+                    FieldType::array(FieldType::Message("SearchResponse.Result".to_string())),
+                )
+                .with_field("snippets", FieldType::array(FieldType::String))
+                .with_field("snippets2", FieldType::array(FieldType::String)),
+        )
+        .with_message(
+            MessageSchema::new("SearchResponse.Result")
+                .with_field("url", FieldType::String)
+                .with_field("title", FieldType::String)
+                .with_field("snippets", FieldType::array(FieldType::String)),
+        )
+        .with_message(MessageSchema::new("StockPricesSuperWrap").with_field(
+            "prices",
+            FieldType::map(
+                FieldType::String,
+                FieldType::Message("StockPricesWrapper".to_string()),
+            ),
+        ))
+        .with_message(MessageSchema::new("StockPricesWrapper").with_field(
+            "sPrices",
+            FieldType::array(FieldType::Message("StockPrices".to_string())),
+        ))
+        .with_message(
+            MessageSchema::new("StockPrices")
+                .with_field("prices", FieldType::map(FieldType::String, FieldType::F64)),
+        )
+}
+
+#[test]
+fn test_validate() {
+    let data = get_test_data();
+    validate(&data, &test_all_schema()).unwrap();
+}
+
+#[test]
+fn test_validate_trailing_bytes() {
+    let mut data = get_test_data();
+    data.push(Kind::None as u8);
+
+    let error = validate(&data, &test_all_schema()).unwrap_err();
+    assert_eq!(error, ValidationError::TrailingBytes(1));
+}
+
+#[test]
+fn test_validate_truncated() {
+    let mut data = get_test_data();
+    data.truncate(data.len() - 9);
+
+    let error = validate(&data, &test_all_schema()).unwrap_err();
+    assert!(matches!(
+        error,
+        ValidationError::InvalidValue { .. } | ValidationError::UnexpectedEnd { .. }
+    ));
+}
+
+#[test]
+fn test_validate_mismatched_kind() {
+    let schema = Schema::new("SearchResponse")
+        .with_message(MessageSchema::new("SearchResponse").with_field(
+            "results",
+            FieldType::array(FieldType::Message("Result".to_string())),
+        ))
+        .with_message(
+            MessageSchema::new("Result")
+                .with_field("url", FieldType::String)
+                .with_field("rank", FieldType::U32),
+        );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_array(3, Kind::Any).unwrap();
+    for i in 0..2 {
+        encoder
+            .encode_str("https://polyglot.dev")
+            .unwrap()
+            .encode_u32(i)
+            .unwrap();
+    }
+    encoder.encode_u32(2).unwrap().encode_str("rank").unwrap();
+
+    let error = validate(encoder.get_ref(), &schema).unwrap_err();
+    assert_eq!(
+        error,
+        ValidationError::MismatchedKind {
+            path: "results[2].url".to_string(),
+            expected: Kind::String,
+            found: Kind::U32,
+        }
+    );
+}
+
+#[test]
+fn test_validate_mismatched_header() {
+    let schema = Schema::new("StockPrices").with_message(
+        MessageSchema::new("StockPrices")
+            .with_field("prices", FieldType::map(FieldType::String, FieldType::F64)),
+    );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_map(1, Kind::String, Kind::F32).unwrap();
+    encoder.encode_str("AAPL").unwrap().encode_f32(1.0).unwrap();
+
+    let error = validate(encoder.get_ref(), &schema).unwrap_err();
+    assert_eq!(
+        error,
+        ValidationError::MismatchedKind {
+            path: "prices".to_string(),
+            expected: Kind::F64,
+            found: Kind::F32,
+        }
+    );
+}
+
+#[test]
+fn test_validate_invalid_value() {
+    let schema = Schema::new("Request")
+        .with_message(MessageSchema::new("Request").with_field("Message", FieldType::String));
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_bytes(&[0xff, 0xfe]).unwrap();
+    let mut data = encoder.into_inner();
+    data[0] = Kind::String as u8;
+
+    let error = validate(&data, &schema).unwrap_err();
+    assert_eq!(
+        error,
+        ValidationError::InvalidValue {
+            path: "Message".to_string(),
+            error: DecodingError::InvalidString,
+        }
+    );
+}
+
+#[test]
+fn test_validate_unknown_message() {
+    let schema = Schema::new("Missing");
+
+    let error = validate(&[], &schema).unwrap_err();
+    assert_eq!(
+        error,
+        ValidationError::UnknownMessage("Missing".to_string())
+    );
+}
+
+#[test]
+fn test_validate_none() {
+    let schema = Schema::new("Request")
+        .with_message(MessageSchema::new("Request").with_field("Message", FieldType::String));
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_none().unwrap();
+
+    validate(encoder.get_ref(), &schema).unwrap();
+}
+
+#[test]
+fn test_validate_depth() {
+    let schema = Schema::new("Node").with_message(
+        MessageSchema::new("Node")
+            .with_field("value", FieldType::U32)
+            .with_field("parent", FieldType::Message("Node".to_string())),
+    );
+    let nested = |depth: usize| {
+        let mut encoder = Cursor::new(Vec::with_capacity(512));
+        for i in 0..depth {
+            encoder.encode_u32(i as u32).unwrap();
+        }
+        encoder.encode_none().unwrap();
+        encoder.into_inner()
+    };
+
+    validate(&nested(MAX_DEPTH), &schema).unwrap();
+    let error = validate(&nested(MAX_DEPTH + 1), &schema).unwrap_err();
+    assert!(matches!(
+        error,
+        ValidationError::InvalidValue {
+            error: DecodingError::MaxDepthExceeded,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_validate_delimited() {
+    let schema = Schema::new("User").with_message(
+        MessageSchema::new("User")
+            .delimited()
+            .with_field("name", FieldType::String)
+            .with_field("age", FieldType::U32),
+    );
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    let start = encoder.reserve_delimited().unwrap();
+    encoder
+        .encode_str("name")
+        .unwrap()
+        .encode_u32(42)
+        .unwrap()
+        .encode_bool(true)
+        .unwrap()
+        .encode_delimited(start)
+        .unwrap();
+    validate(encoder.get_ref(), &schema).unwrap();
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    let start = encoder.reserve_delimited().unwrap();
+    encoder
+        .encode_str("name")
+        .unwrap()
+        .encode_delimited(start)
+        .unwrap();
+    validate(encoder.get_ref(), &schema).unwrap();
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_str("name").unwrap();
+    assert_eq!(
+        validate(encoder.get_ref(), &schema),
+        Err(ValidationError::MismatchedKind {
+            path: String::new(),
+            expected: Kind::Any,
+            found: Kind::String,
+        })
+    );
+}
+
+#[test]
+fn test_validate_optional_and_oneof() {
+    let schema = Schema::new("User").with_message(
+        MessageSchema::new("User")
+            .with_field("name", FieldType::String)
+            .with_field("age", FieldType::optional(FieldType::U32))
+            .with_field(
+                "contact",
+                FieldType::Oneof(vec![(1, FieldType::String), (2, FieldType::U64)]),
+            ),
+    );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_str("name")
+        .unwrap()
+        .encode_none()
+        .unwrap()
+        .encode_u32(1)
+        .unwrap()
+        .encode_str("email")
+        .unwrap();
+    validate(encoder.get_ref(), &schema).unwrap();
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_str("name")
+        .unwrap()
+        .encode_u32(42)
+        .unwrap()
+        .encode_u32(0)
+        .unwrap();
+    validate(encoder.get_ref(), &schema).unwrap();
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_str("name")
+        .unwrap()
+        .encode_u32(42)
+        .unwrap()
+        .encode_u32(2)
+        .unwrap()
+        .encode_str("email")
+        .unwrap();
+    assert_eq!(
+        validate(encoder.get_ref(), &schema),
+        Err(ValidationError::MismatchedKind {
+            path: "contact#2".to_string(),
+            expected: Kind::U64,
+            found: Kind::String,
+        })
+    );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder
+        .encode_str("name")
+        .unwrap()
+        .encode_u32(42)
+        .unwrap()
+        .encode_u32(3)
+        .unwrap();
+    assert_eq!(
+        validate(encoder.get_ref(), &schema),
+        Err(ValidationError::InvalidValue {
+            path: "contact".to_string(),
+            error: DecodingError::InvalidOneof,
+        })
+    );
+}
+
+#[test]
+fn test_validate_packed() {
+    let schema = Schema::new("Series").with_message(
+        MessageSchema::new("Series").with_field("values", FieldType::array(FieldType::F64)),
+    );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_f64_slice(&[1.0, 2.0]).unwrap();
+    validate(encoder.get_ref(), &schema).unwrap();
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_f64_run_length_slice(&[1.0, 1.0]).unwrap();
+    validate(encoder.get_ref(), &schema).unwrap();
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_i64_delta_slice(&[1, 2]).unwrap();
+    assert_eq!(
+        validate(encoder.get_ref(), &schema),
+        Err(ValidationError::MismatchedKind {
+            path: "values".to_string(),
+            expected: Kind::F64,
+            found: Kind::I64,
+        })
+    );
+
+    let mut encoder = Cursor::new(Vec::with_capacity(512));
+    encoder.encode_f32_slice(&[1.0, 2.0]).unwrap();
+    assert_eq!(
+        validate(encoder.get_ref(), &schema),
+        Err(ValidationError::MismatchedKind {
+            path: "values".to_string(),
+            expected: Kind::F64,
+            found: Kind::F32,
+        })
+    );
+}